@@ -0,0 +1,223 @@
+// A generic archive is a relocatable tarball holding the files that a deb
+// package would install under /usr, below a single top-level directory:
+//
+// foobar-1.2.3-x86_64.tar.gz
+//     foobar-1.2.3-x86_64/
+//         bin/foobar                          # Binary executable file
+//         lib/foobar/...                      # Resource files
+//         share/applications/foobar.desktop   # Desktop file (for apps)
+//         share/icons/hicolor/...             # Icon files (for apps)
+//
+// Keeping the deb layout means an app that finds its resources relative to
+// its executable works the same way when extracted from the archive.  The
+// entries are sorted and owned by root, and when SOURCE_DATE_EPOCH is set it
+// is used as their modification time, so that the archive is reproducible.
+
+use crate::bundle::{
+    PackageType, Settings, common,
+    linux::common::{generate_desktop_file, generate_icon_files, transfer_resource_files},
+};
+use anyhow::Context;
+use libflate::gzip;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+pub fn bundle_project(
+    settings: &Settings,
+    package_type: PackageType,
+) -> crate::Result<Vec<PathBuf>> {
+    bundle_archive(settings, package_type, common::source_date_epoch()?)
+}
+
+/// Writes the archive, giving its entries `mtime` as their modification time
+/// if it is given.
+fn bundle_archive(
+    settings: &Settings,
+    package_type: PackageType,
+    mtime: Option<u64>,
+) -> crate::Result<Vec<PathBuf>> {
+    let extension = match package_type {
+        PackageType::TarGz => "tar.gz",
+        PackageType::TarXz => "tar.xz",
+        other => anyhow::bail!("{other} is not an archive format"),
+    };
+    let arch = settings.binary_arch();
+    let archive_base_name = format!(
+        "{}-{}-{}",
+        settings.binary_name(),
+        settings.version_string(),
+        arch
+    );
+    let archive_name = format!(
+        "{}.{extension}",
+        settings.artifact_name(arch, archive_base_name.clone())
+    );
+    common::print_bundling(&archive_name)?;
+    let base_dir = settings.bundle_dir(package_type);
+    let package_dir = base_dir.join(&archive_base_name);
+    if package_dir.exists() {
+        std::fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {archive_base_name}"))?;
+    }
+    let archive_path = base_dir.join(&archive_name);
+    common::prepare_output_path(&archive_path, settings.force())?;
+
+    // The helpers shared with the Linux packages write below `usr/`.
+    common::copy_file(
+        &settings.binary_path(package_type),
+        &package_dir.join("usr/bin").join(settings.binary_name()),
+    )
+    .with_context(|| "Failed to copy binary file")?;
+    transfer_resource_files(settings, &package_dir)
+        .with_context(|| "Failed to copy resource files")?;
+    generate_icon_files(settings, &package_dir).with_context(|| "Failed to create icon files")?;
+    generate_desktop_file(settings, &package_dir)
+        .with_context(|| "Failed to create desktop file")?;
+
+    let data_dir = package_dir.join("usr");
+    let top_dir = Path::new(&archive_base_name);
+    match package_type {
+        PackageType::TarGz => {
+            let mut header = gzip::HeaderBuilder::new();
+            if let Some(mtime) = mtime {
+                header.modification_time(u32::try_from(mtime).unwrap_or(u32::MAX));
+            }
+            let options = gzip::EncodeOptions::new().header(header.finish());
+            let encoder =
+                gzip::Encoder::with_options(common::create_file(&archive_path)?, options)?;
+            let encoder = write_tar(&data_dir, top_dir, mtime, encoder)?;
+            encoder.finish().into_result()?.flush()?;
+        }
+        _ => {
+            let tar_path = archive_path.with_extension("");
+            common::prepare_output_path(&tar_path, true)?;
+            write_tar(&data_dir, top_dir, mtime, common::create_file(&tar_path)?)?.flush()?;
+            let mut xz = Command::new("xz");
+            xz.arg("--compress").arg("--threads=0").arg(&tar_path);
+            common::run_command(&mut xz, common::TOOL_TIMEOUT)
+                .with_context(|| "Failed to compress the archive with xz")?;
+        }
+    }
+    Ok(vec![archive_path])
+}
+
+/// Writes a tar file to `dest` containing the contents of `src_dir` below
+/// `top_dir`.  Entries are sorted by name, owned by root, and get `mtime` as
+/// their modification time if it is given.
+fn write_tar<W: Write>(
+    src_dir: &Path,
+    top_dir: &Path,
+    mtime: Option<u64>,
+    dest: W,
+) -> crate::Result<W> {
+    let mut tar_builder = tar::Builder::new(dest);
+    for entry in WalkDir::new(src_dir).sort_by_file_name() {
+        let entry = entry?;
+        let src_path = entry.path();
+        let rel_path = src_path.strip_prefix(src_dir).unwrap();
+        let dest_path = if rel_path.as_os_str().is_empty() {
+            top_dir.to_path_buf()
+        } else {
+            top_dir.join(rel_path)
+        };
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata_in_mode(&entry.metadata()?, tar::HeaderMode::Deterministic);
+        if let Some(mtime) = mtime {
+            header.set_mtime(mtime);
+        }
+        if entry.file_type().is_symlink() {
+            let target = std::fs::read_link(src_path)?;
+            tar_builder.append_link(&mut header, dest_path, target)?;
+        } else if entry.file_type().is_dir() {
+            tar_builder.append_data(&mut header, dest_path, io::empty())?;
+        } else {
+            tar_builder.append_data(&mut header, dest_path, File::open(src_path)?)?;
+        }
+    }
+    Ok(tar_builder.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bundle_archive;
+    use crate::bundle::{PackageType, Settings, target_info::BundleTargetInfo};
+    use crate::test_utils::write_fixture_package;
+    use libflate::gzip;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn relocatable_tarball() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("assets")).unwrap();
+        std::fs::write(tmp.path().join("assets/data.txt"), "data").unwrap();
+        write_fixture_package(
+            tmp.path(),
+            &format!(
+                "\n\
+                 [package.metadata.bundle]\nresources_mapping = [[{:?}, \"assets\"]]\n\
+                 filename_template = \"{{name}}-{{version}}-portable\"\n",
+                tmp.path().join("assets/*").display().to_string()
+            ),
+        );
+        let binary = tmp.path().join("prebuilt/foo");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, b"#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::TarGz, None).unwrap();
+        let settings = Settings::builder(info)
+            .prebuilt_binary(&binary)
+            .build()
+            .unwrap();
+
+        let output_paths = bundle_archive(&settings, PackageType::TarGz, Some(1700000000)).unwrap();
+        assert_eq!(
+            output_paths,
+            [settings
+                .bundle_dir(PackageType::TarGz)
+                .join("foo-0.1.0-portable.tar.gz")]
+        );
+
+        let decoder = gzip::Decoder::new(std::fs::File::open(&output_paths[0]).unwrap()).unwrap();
+        assert_eq!(decoder.header().modification_time(), 1700000000);
+        let mut archive = tar::Archive::new(decoder);
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+                assert_eq!(header.mtime().unwrap(), 1700000000);
+                assert_eq!(header.uid().unwrap(), 0);
+                (
+                    entry.path().unwrap().display().to_string(),
+                    header.mode().unwrap(),
+                )
+            })
+            .collect();
+        let top_dir = format!("foo-0.1.0-{}", settings.binary_arch());
+        let expected = [
+            (top_dir.clone(), 0o755),
+            (format!("{top_dir}/bin"), 0o755),
+            (format!("{top_dir}/bin/foo"), 0o755),
+            (format!("{top_dir}/lib"), 0o755),
+            (format!("{top_dir}/lib/foo"), 0o755),
+            (format!("{top_dir}/lib/foo/assets"), 0o755),
+            (format!("{top_dir}/lib/foo/assets/data.txt"), 0o644),
+            (format!("{top_dir}/share"), 0o755),
+            (format!("{top_dir}/share/applications"), 0o755),
+            (format!("{top_dir}/share/applications/foo.desktop"), 0o644),
+        ];
+        let paths = |entries: &[(String, u32)]| -> Vec<String> {
+            entries.iter().map(|(path, _)| path.clone()).collect()
+        };
+        assert_eq!(paths(&entries), paths(&expected));
+        // The modes come from the file system, which only has them on unix.
+        #[cfg(unix)]
+        assert_eq!(entries, expected);
+    }
+}
@@ -52,8 +52,53 @@ pub enum AppCategory {
 }
 
 impl AppCategory {
+    /// Every app category, in declaration order.
+    pub const ALL: &[AppCategory] = &[
+        AppCategory::Business,
+        AppCategory::DeveloperTool,
+        AppCategory::Education,
+        AppCategory::Entertainment,
+        AppCategory::Finance,
+        AppCategory::Game,
+        AppCategory::ActionGame,
+        AppCategory::AdventureGame,
+        AppCategory::ArcadeGame,
+        AppCategory::BoardGame,
+        AppCategory::CardGame,
+        AppCategory::CasinoGame,
+        AppCategory::DiceGame,
+        AppCategory::EducationalGame,
+        AppCategory::FamilyGame,
+        AppCategory::KidsGame,
+        AppCategory::MusicGame,
+        AppCategory::PuzzleGame,
+        AppCategory::RacingGame,
+        AppCategory::RolePlayingGame,
+        AppCategory::SimulationGame,
+        AppCategory::SportsGame,
+        AppCategory::StrategyGame,
+        AppCategory::TriviaGame,
+        AppCategory::WordGame,
+        AppCategory::GraphicsAndDesign,
+        AppCategory::HealthcareAndFitness,
+        AppCategory::Lifestyle,
+        AppCategory::Medical,
+        AppCategory::Music,
+        AppCategory::News,
+        AppCategory::Photography,
+        AppCategory::Productivity,
+        AppCategory::Reference,
+        AppCategory::SocialNetworking,
+        AppCategory::Sports,
+        AppCategory::Travel,
+        AppCategory::Utility,
+        AppCategory::Video,
+        AppCategory::Weather,
+    ];
+
     /// Given a string, returns the `AppCategory` it refers to, or the closest
     /// string that the user might have intended (if any).
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(input: &str) -> Result<AppCategory, Option<&'static str>> {
         // Canonicalize input:
         let mut input = input.to_ascii_lowercase();
@@ -76,12 +121,13 @@ impl AppCategory {
                 best_category = Some(category);
             }
         }
-        Err(best_category.map(AppCategory::canonical))
+        Err(best_category.map(AppCategory::as_str))
     }
 
-    /// Map an AppCategory to the string we recommend to use in Cargo.toml if
-    /// the users misspells the category name.
-    fn canonical(self) -> &'static str {
+    /// Map an AppCategory to its canonical name, the string we recommend to
+    /// use in Cargo.toml (e.g. "Puzzle Game").  This is also what it
+    /// serializes to.
+    pub fn as_str(self) -> &'static str {
         match self {
             AppCategory::Business => "Business",
             AppCategory::DeveloperTool => "Developer Tool",
@@ -126,50 +172,146 @@ impl AppCategory {
         }
     }
 
-    /// Map an AppCategory to the closest set of GNOME desktop registered
-    /// categories that matches that category.
-    pub fn gnome_desktop_categories(&self) -> &'static str {
+    /// Map an AppCategory to the closest set of freedesktop.org registered
+    /// categories that matches that category: a main category, followed by
+    /// any additional categories that are valid under it. See
+    /// <https://specifications.freedesktop.org/menu-spec/latest/category-registry.html>
+    pub fn freedesktop_categories(&self) -> &'static [&'static str] {
+        match &self {
+            AppCategory::Business => &["Office"],
+            AppCategory::DeveloperTool => &["Development"],
+            AppCategory::Education => &["Education"],
+            AppCategory::Entertainment => &["AudioVideo"],
+            AppCategory::Finance => &["Office", "Finance"],
+            AppCategory::Game => &["Game"],
+            AppCategory::ActionGame => &["Game", "ActionGame"],
+            AppCategory::AdventureGame => &["Game", "AdventureGame"],
+            AppCategory::ArcadeGame => &["Game", "ArcadeGame"],
+            AppCategory::BoardGame => &["Game", "BoardGame"],
+            AppCategory::CardGame => &["Game", "CardGame"],
+            AppCategory::CasinoGame => &["Game"],
+            AppCategory::DiceGame => &["Game"],
+            AppCategory::EducationalGame => &["Game", "Education"],
+            AppCategory::FamilyGame => &["Game"],
+            AppCategory::KidsGame => &["Game", "KidsGame"],
+            AppCategory::MusicGame => &["Game"],
+            AppCategory::PuzzleGame => &["Game", "LogicGame"],
+            AppCategory::RacingGame => &["Game"],
+            AppCategory::RolePlayingGame => &["Game", "RolePlaying"],
+            AppCategory::SimulationGame => &["Game", "Simulation"],
+            AppCategory::SportsGame => &["Game", "SportsGame"],
+            AppCategory::StrategyGame => &["Game", "StrategyGame"],
+            AppCategory::TriviaGame => &["Game"],
+            AppCategory::WordGame => &["Game"],
+            AppCategory::GraphicsAndDesign => &["Graphics"],
+            AppCategory::HealthcareAndFitness => &["Science"],
+            AppCategory::Lifestyle => &["Utility"],
+            AppCategory::Medical => &["Science", "MedicalSoftware"],
+            AppCategory::Music => &["AudioVideo", "Audio", "Music"],
+            AppCategory::News => &["Network", "News"],
+            AppCategory::Photography => &["Graphics", "Photography"],
+            AppCategory::Productivity => &["Office"],
+            AppCategory::Reference => &["Education"],
+            AppCategory::SocialNetworking => &["Network"],
+            AppCategory::Sports => &["Education", "Sports"],
+            AppCategory::Travel => &["Education"],
+            AppCategory::Utility => &["Utility"],
+            AppCategory::Video => &["AudioVideo", "Video"],
+            AppCategory::Weather => &["Science"],
+        }
+    }
+
+    /// Map an AppCategory to the closest Debian archive section.  See
+    /// <https://www.debian.org/doc/debian-policy/ch-archive.html#sections>
+    pub fn debian_section(&self) -> &'static str {
         match &self {
-            AppCategory::Business => "Office;",
-            AppCategory::DeveloperTool => "Development;",
-            AppCategory::Education => "Education;",
-            AppCategory::Entertainment => "Network;",
-            AppCategory::Finance => "Office;Finance;",
-            AppCategory::Game => "Game;",
-            AppCategory::ActionGame => "Game;ActionGame;",
-            AppCategory::AdventureGame => "Game;AdventureGame;",
-            AppCategory::ArcadeGame => "Game;ArcadeGame;",
-            AppCategory::BoardGame => "Game;BoardGame;",
-            AppCategory::CardGame => "Game;CardGame;",
-            AppCategory::CasinoGame => "Game;",
-            AppCategory::DiceGame => "Game;",
-            AppCategory::EducationalGame => "Game;Education;",
-            AppCategory::FamilyGame => "Game;",
-            AppCategory::KidsGame => "Game;KidsGame;",
-            AppCategory::MusicGame => "Game;",
-            AppCategory::PuzzleGame => "Game;LogicGame;",
-            AppCategory::RacingGame => "Game;",
-            AppCategory::RolePlayingGame => "Game;RolePlaying;",
-            AppCategory::SimulationGame => "Game;Simulation;",
-            AppCategory::SportsGame => "Game;SportsGame;",
-            AppCategory::StrategyGame => "Game;StrategyGame;",
-            AppCategory::TriviaGame => "Game;",
-            AppCategory::WordGame => "Game;",
-            AppCategory::GraphicsAndDesign => "Graphics;",
-            AppCategory::HealthcareAndFitness => "Science;",
-            AppCategory::Lifestyle => "Education;",
-            AppCategory::Medical => "Science;MedicalSoftware;",
-            AppCategory::Music => "AudioVideo;Audio;Music;",
-            AppCategory::News => "Network;News;",
-            AppCategory::Photography => "Graphics;Photography;",
-            AppCategory::Productivity => "Office;",
-            AppCategory::Reference => "Education;",
-            AppCategory::SocialNetworking => "Network;",
-            AppCategory::Sports => "Education;Sports;",
-            AppCategory::Travel => "Education;",
-            AppCategory::Utility => "Utility;",
-            AppCategory::Video => "AudioVideo;Video;",
-            AppCategory::Weather => "Science;",
+            AppCategory::Business => "misc",
+            AppCategory::DeveloperTool => "devel",
+            AppCategory::Education => "education",
+            AppCategory::Entertainment => "video",
+            AppCategory::Finance => "misc",
+            AppCategory::Game
+            | AppCategory::ActionGame
+            | AppCategory::AdventureGame
+            | AppCategory::ArcadeGame
+            | AppCategory::BoardGame
+            | AppCategory::CardGame
+            | AppCategory::CasinoGame
+            | AppCategory::DiceGame
+            | AppCategory::EducationalGame
+            | AppCategory::FamilyGame
+            | AppCategory::KidsGame
+            | AppCategory::MusicGame
+            | AppCategory::PuzzleGame
+            | AppCategory::RacingGame
+            | AppCategory::RolePlayingGame
+            | AppCategory::SimulationGame
+            | AppCategory::SportsGame
+            | AppCategory::StrategyGame
+            | AppCategory::TriviaGame
+            | AppCategory::WordGame => "games",
+            AppCategory::GraphicsAndDesign => "graphics",
+            AppCategory::HealthcareAndFitness => "misc",
+            AppCategory::Lifestyle => "misc",
+            AppCategory::Medical => "science",
+            AppCategory::Music => "sound",
+            AppCategory::News => "news",
+            AppCategory::Photography => "graphics",
+            AppCategory::Productivity => "misc",
+            AppCategory::Reference => "doc",
+            AppCategory::SocialNetworking => "net",
+            AppCategory::Sports => "misc",
+            AppCategory::Travel => "misc",
+            AppCategory::Utility => "utils",
+            AppCategory::Video => "video",
+            AppCategory::Weather => "science",
+        }
+    }
+
+    /// Map an AppCategory to the closest of the traditional RPM `Group:`
+    /// values, as listed in `/usr/share/doc/rpm/GROUPS`.
+    pub fn rpm_group(&self) -> &'static str {
+        match &self {
+            AppCategory::Business => "Applications/Productivity",
+            AppCategory::DeveloperTool => "Development/Tools",
+            AppCategory::Education => "Applications/Engineering",
+            AppCategory::Entertainment => "Amusements/Graphics",
+            AppCategory::Finance => "Applications/Productivity",
+            AppCategory::Game
+            | AppCategory::ActionGame
+            | AppCategory::AdventureGame
+            | AppCategory::ArcadeGame
+            | AppCategory::BoardGame
+            | AppCategory::CardGame
+            | AppCategory::CasinoGame
+            | AppCategory::DiceGame
+            | AppCategory::EducationalGame
+            | AppCategory::FamilyGame
+            | AppCategory::KidsGame
+            | AppCategory::MusicGame
+            | AppCategory::PuzzleGame
+            | AppCategory::RacingGame
+            | AppCategory::RolePlayingGame
+            | AppCategory::SimulationGame
+            | AppCategory::SportsGame
+            | AppCategory::StrategyGame
+            | AppCategory::TriviaGame
+            | AppCategory::WordGame => "Amusements/Games",
+            AppCategory::GraphicsAndDesign => "Applications/Multimedia",
+            AppCategory::HealthcareAndFitness => "Applications/Productivity",
+            AppCategory::Lifestyle => "Applications/Productivity",
+            AppCategory::Medical => "Applications/Engineering",
+            AppCategory::Music => "Applications/Multimedia",
+            AppCategory::News => "Applications/Internet",
+            AppCategory::Photography => "Applications/Multimedia",
+            AppCategory::Productivity => "Applications/Productivity",
+            AppCategory::Reference => "Documentation",
+            AppCategory::SocialNetworking => "Applications/Communications",
+            AppCategory::Sports => "Applications/Productivity",
+            AppCategory::Travel => "Applications/Productivity",
+            AppCategory::Utility => "Applications/System",
+            AppCategory::Video => "Applications/Multimedia",
+            AppCategory::Weather => "Applications/Internet",
         }
     }
 
@@ -221,6 +363,18 @@ impl AppCategory {
     }
 }
 
+impl fmt::Display for AppCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl serde::Serialize for AppCategory {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 impl<'d> serde::Deserialize<'d> for AppCategory {
     fn deserialize<D: serde::Deserializer<'d>>(deserializer: D) -> Result<AppCategory, D::Error> {
         deserializer.deserialize_str(AppCategoryVisitor { did_you_mean: None })
@@ -240,10 +394,12 @@ impl serde::de::Visitor<'_> for AppCategoryVisitor {
                 write!(
                     formatter,
                     "a valid app category string (did you mean \"{string}\"?)"
-                )
+                )?;
             }
-            None => write!(formatter, "a valid app category string"),
+            None => write!(formatter, "a valid app category string")?,
         }
+        let all: Vec<&str> = AppCategory::ALL.iter().map(|c| c.as_str()).collect();
+        write!(formatter, "; valid categories are: {}", all.join(", "))
     }
 
     fn visit_str<E: serde::de::Error>(mut self, value: &str) -> Result<AppCategory, E> {
@@ -399,6 +555,53 @@ mod tests {
         assert_eq!(AppCategory::from_str("WHARRGARBL"), Err(None));
     }
 
+    #[test]
+    fn serialization_round_trip() {
+        for &category in AppCategory::ALL {
+            let json = serde_json::to_string(&category).unwrap();
+            assert_eq!(json, format!("\"{category}\""));
+            assert_eq!(
+                serde_json::from_str::<AppCategory>(&json).unwrap(),
+                category
+            );
+            assert_eq!(AppCategory::from_str(category.as_str()), Ok(category));
+        }
+        assert_eq!(AppCategory::PuzzleGame.to_string(), "Puzzle Game");
+        assert_eq!(AppCategory::ALL.len(), 40);
+    }
+
+    #[test]
+    fn invalid_category_error_suggests_and_lists() {
+        let error = serde_json::from_str::<AppCategory>("\"Pzzle Game\"")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("did you mean \"Puzzle Game\"?"), "{error}");
+        assert!(
+            error.contains("valid categories are: Business, Developer Tool,"),
+            "{error}"
+        );
+        let error = serde_json::from_str::<AppCategory>("\"fhqwhgads\"")
+            .unwrap_err()
+            .to_string();
+        assert!(!error.contains("did you mean"), "{error}");
+        assert!(error.contains("Weather"), "{error}");
+    }
+
+    #[test]
+    fn debian_sections() {
+        assert_eq!(AppCategory::PuzzleGame.debian_section(), "games");
+        assert_eq!(AppCategory::Utility.debian_section(), "utils");
+        assert_eq!(AppCategory::DeveloperTool.debian_section(), "devel");
+        assert_eq!(AppCategory::Music.debian_section(), "sound");
+    }
+
+    #[test]
+    fn rpm_groups() {
+        assert_eq!(AppCategory::PuzzleGame.rpm_group(), "Amusements/Games");
+        assert_eq!(AppCategory::DeveloperTool.rpm_group(), "Development/Tools");
+        assert_eq!(AppCategory::Utility.rpm_group(), "Applications/System");
+    }
+
     #[test]
     fn ls_application_category_type_round_trip() {
         let values = &[
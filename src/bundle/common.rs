@@ -1,8 +1,74 @@
 use anyhow::Context;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
 use std::ffi::OsStr;
 use std::fs::{self, File};
-use std::io::{self, BufWriter, Write};
+use std::io::{self, BufWriter, IsTerminal, Write};
 use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long an external tool may run before `run_command` kills it.
+pub const TOOL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long to wait for Apple's notary service to accept or reject a
+/// submission, which usually takes minutes but can take much longer.
+const NOTARIZE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// How many resource files must be copied before progress is shown without
+/// `--progress`.
+const PROGRESS_THRESHOLD: usize = 50;
+
+/// How often the resource copy progress is redrawn.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How much extra output to print; set from the number of `-v` flags.
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the verbosity level used by `log_verbose`.
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+/// Returns the verbosity level, i.e. the number of `-v` flags given.
+pub fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Whether warnings are counted so that they can fail the run; set from
+/// `--strict`.
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// How many warnings `print_warning` has printed in strict mode.
+static STRICT_WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Held while printing a message, so that the messages of formats being
+/// bundled concurrently don't interleave.
+static OUTPUT: Mutex<()> = Mutex::new(());
+
+/// The unset environment variables that `expand_path` has warned about, so
+/// that each is only warned about once however often it is expanded.
+static UNSET_VARIABLES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+fn lock_output() -> MutexGuard<'static, ()> {
+    OUTPUT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Sets whether `print_warning` records warnings for `strict_warnings`.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Returns how many warnings have been printed since strict mode was turned
+/// on.
+pub fn strict_warnings() -> usize {
+    STRICT_WARNINGS.load(Ordering::Relaxed)
+}
 
 /// Returns true if the path has a filename indicating that it is a high-desity
 /// "retina" icon.  Specifically, returns true the the file stem ends with
@@ -16,6 +82,45 @@ pub fn is_retina<P: AsRef<Path>>(path: P) -> bool {
         .unwrap_or(false)
 }
 
+/// The frame sizes, in pixels, of generated Windows `.ico` files.
+const ICO_SIZES: [u32; 4] = [16, 32, 48, 256];
+
+/// Writes a multi-resolution `.ico` file, with 16, 32, 48 and 256 pixel
+/// frames all scaled from the largest of the PNG files in `icon_paths`.
+/// Returns `Ok(false)`, without writing anything, if there are no PNG files.
+pub fn write_ico_from_pngs<W: Write>(icon_paths: &[PathBuf], writer: W) -> crate::Result<bool> {
+    let mut largest: Option<image::DynamicImage> = None;
+    for icon_path in icon_paths {
+        if icon_path.extension() != Some(OsStr::new("png")) {
+            continue;
+        }
+        let icon = image::open(icon_path)?;
+        if largest
+            .as_ref()
+            .is_none_or(|largest| icon.width() > largest.width())
+        {
+            largest = Some(icon);
+        }
+    }
+    let Some(source) = largest else {
+        return Ok(false);
+    };
+    let mut frames = Vec::new();
+    for size in ICO_SIZES {
+        let frame = source
+            .resize_exact(size, size, image::imageops::FilterType::Lanczos3)
+            .into_rgba8();
+        frames.push(image::codecs::ico::IcoFrame::as_png(
+            frame.as_raw(),
+            size,
+            size,
+            image::ExtendedColorType::Rgba8,
+        )?);
+    }
+    image::codecs::ico::IcoEncoder::new(writer).encode_images(&frames)?;
+    Ok(true)
+}
+
 /// Creates a new file at the given path, creating any parent directories as
 /// needed.
 pub fn create_file(path: &Path) -> crate::Result<BufWriter<File>> {
@@ -27,6 +132,35 @@ pub fn create_file(path: &Path) -> crate::Result<BufWriter<File>> {
     Ok(BufWriter::new(file))
 }
 
+/// Makes way for a bundle output at `path`.  If something already exists
+/// there, it is removed when `force` is set, and an error is returned
+/// otherwise.
+pub fn prepare_output_path(path: &Path, force: bool) -> crate::Result<()> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !force {
+        anyhow::bail!("Output {path:?} already exists, use --force to overwrite it");
+    }
+    log_verbose("Removing", &path.display().to_string())?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("Failed to remove old output {path:?}"))
+}
+
+/// Removes a format's bundle output directory, along with any stale outputs
+/// of earlier runs in it.
+pub fn remove_bundle_dir(dir: &Path) -> crate::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    log_verbose("Removing", &dir.display().to_string())?;
+    fs::remove_dir_all(dir).with_context(|| format!("Failed to remove bundle directory {dir:?}"))
+}
+
 #[cfg(unix)]
 fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
     std::os::unix::fs::symlink(src, dst)
@@ -59,6 +193,10 @@ pub fn copy_file(from: &Path, to: &Path) -> crate::Result<()> {
     }
     let dest_dir = to.parent().unwrap();
     fs::create_dir_all(dest_dir).with_context(|| format!("Failed to create {dest_dir:?}"))?;
+    log_verbose(
+        "Copying",
+        &format!("{} -> {}", from.display(), to.display()),
+    )?;
     fs::copy(from, to).with_context(|| format!("Failed to copy {from:?} to {to:?}"))?;
     Ok(())
 }
@@ -75,6 +213,44 @@ pub fn read_file(file: &Path) -> crate::Result<String> {
     Ok(contents)
 }
 
+/// Copies the bundle's resource files into `output_base`.  Progress is shown
+/// on stderr when requested with `--progress`, or when copying many files to
+/// a terminal.
+pub fn copy_resources(settings: &crate::Settings, output_base: &Path) -> crate::Result<()> {
+    let resources = settings.resources_paths(output_base);
+    let total = resources.len();
+    let show_progress = show_progress(settings.progress(), total, io::stderr().is_terminal());
+    let mut last_update = Instant::now();
+    for (copied, (src, dst)) in resources.into_iter().enumerate() {
+        copy_file(&src, &dst)
+            .with_context(|| format!("Failed to copy resource file {src:?} to {dst:?}"))?;
+        let copied = copied + 1;
+        if show_progress && (copied == total || last_update.elapsed() >= PROGRESS_INTERVAL) {
+            let _output = lock_output();
+            let mut stderr = io::stderr();
+            write!(
+                stderr,
+                "\r    Copying resources: copied {copied}/{total} files"
+            )?;
+            if copied == total {
+                writeln!(stderr)?;
+            }
+            stderr.flush()?;
+            last_update = Instant::now();
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if progress should be shown for copying `file_count` files.
+/// Verbose output logs every file instead, so progress is never shown with it.
+fn show_progress(requested: bool, file_count: usize, is_terminal: bool) -> bool {
+    if verbosity() > 0 || file_count == 0 {
+        return false;
+    }
+    requested || (is_terminal && file_count > PROGRESS_THRESHOLD)
+}
+
 /// Recursively copies a directory file from one path to another, creating any
 /// parent directories of the destination path as necessary.  Fails if the
 /// source path is not a directory or doesn't exist, or if the destination path
@@ -91,6 +267,10 @@ pub fn copy_dir(from: &Path, to: &Path) -> crate::Result<()> {
     }
     let parent = to.parent().unwrap();
     fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+    log_verbose(
+        "Copying",
+        &format!("{} -> {}", from.display(), to.display()),
+    )?;
     for entry in walkdir::WalkDir::new(from) {
         let entry = entry?;
         debug_assert!(entry.path().starts_with(from));
@@ -112,6 +292,269 @@ pub fn copy_dir(from: &Path, to: &Path) -> crate::Result<()> {
     Ok(())
 }
 
+/// Runs `command` to completion with its output captured, killing it if it
+/// takes longer than `timeout`.  Fails if the command can't be started, times
+/// out, or exits unsuccessfully; the error includes the command line and
+/// whatever the command printed.
+pub fn run_command(command: &mut Command, timeout: Duration) -> crate::Result<Output> {
+    log_verbose("Running", &format!("{command:?}"))?;
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {program}, does the {program} binary exist?"))?;
+    // Drain the pipes on other threads, so that a chatty command can't block
+    // on a full pipe while we wait for it.
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            anyhow::bail!(
+                "`{command:?}` timed out after {}s and was killed",
+                timeout.as_secs()
+            );
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let output = Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    };
+    if !output.status.success() {
+        let mut message = format!("`{command:?}` was unsuccessful: {}", output.status);
+        for captured in [&output.stdout, &output.stderr] {
+            let captured = String::from_utf8_lossy(captured);
+            if !captured.trim().is_empty() {
+                message.push('\n');
+                message.push_str(captured.trim_end());
+            }
+        }
+        anyhow::bail!(message);
+    }
+    Ok(output)
+}
+
+fn read_in_background<R: io::Read + Send + 'static>(
+    pipe: Option<R>,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// The result of `xcrun notarytool submit --wait --output-format json`.
+#[derive(Debug, serde::Deserialize)]
+struct NotarySubmission {
+    id: String,
+    status: String,
+    #[serde(default)]
+    message: String,
+}
+
+/// Submits the app bundle or disk image at `path` to Apple's notary service
+/// with the credentials stored in `keychain_profile`, waits for the result
+/// and staples the ticket to `path`.  App bundles are submitted as a zip
+/// made with `ditto`.  If the submission is rejected, the error includes the
+/// notarization log listing the problems.
+pub fn notarize(path: &Path, keychain_profile: &str) -> crate::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    print_progress("Notarizing", &name)?;
+    let submission_path = if path.is_dir() {
+        let zip_path = path.with_extension("notarize.zip");
+        let mut ditto = Command::new("ditto");
+        ditto
+            .args(["-c", "-k", "--keepParent"])
+            .arg(path)
+            .arg(&zip_path);
+        run_command(&mut ditto, TOOL_TIMEOUT)
+            .with_context(|| format!("Failed to zip {name} for notarization"))?;
+        zip_path
+    } else {
+        path.to_path_buf()
+    };
+    let mut submit = Command::new("xcrun");
+    submit
+        .args(["notarytool", "submit"])
+        .arg(&submission_path)
+        .args(["--keychain-profile", keychain_profile])
+        .args(["--wait", "--output-format", "json"]);
+    let output = run_command(&mut submit, NOTARIZE_TIMEOUT);
+    if submission_path != path {
+        fs::remove_file(&submission_path)
+            .with_context(|| format!("Failed to remove {submission_path:?}"))?;
+    }
+    let output = output.with_context(|| format!("Failed to submit {name} for notarization"))?;
+    let submission: NotarySubmission = serde_json::from_slice(&output.stdout)
+        .with_context(|| "Failed to parse the output of notarytool")?;
+    if submission.status != "Accepted" {
+        let mut log = Command::new("xcrun");
+        log.args(["notarytool", "log", &submission.id])
+            .args(["--keychain-profile", keychain_profile]);
+        let log = match run_command(&mut log, TOOL_TIMEOUT) {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+            Err(error) => format!("The notarization log couldn't be fetched: {error:#}"),
+        };
+        anyhow::bail!(
+            "Notarization of {name} finished with status {:?}: {} (submission {})\n{}",
+            submission.status,
+            submission.message,
+            submission.id,
+            log.trim_end()
+        );
+    }
+    log_verbose(
+        "Notarized",
+        &format!("{name} (submission {})", submission.id),
+    )?;
+    let mut stapler = Command::new("xcrun");
+    stapler.args(["stapler", "staple"]).arg(path);
+    run_command(&mut stapler, TOOL_TIMEOUT)
+        .with_context(|| format!("Failed to staple the notarization ticket to {name}"))?;
+    Ok(())
+}
+
+/// Fails with a message naming the tool and how to install it if any of
+/// `tools`, which bundling `format` needs, isn't in a directory of `path`
+/// (the value of the `PATH` environment variable).
+pub fn check_tools(tools: &[&str], format: &str, path: Option<&OsStr>) -> crate::Result<()> {
+    let dirs: Vec<PathBuf> = path
+        .map(|path| std::env::split_paths(path).collect())
+        .unwrap_or_default();
+    for tool in tools {
+        let names = [tool.to_string(), format!("{tool}.exe")];
+        let found = dirs
+            .iter()
+            .any(|dir| names.iter().any(|name| dir.join(name).is_file()));
+        if !found {
+            anyhow::bail!(
+                "`{tool}` is needed to bundle {format} packages but was not found in PATH; {}",
+                tool_install_hint(tool)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Returns a hint on how to install the external `tool`.
+fn tool_install_hint(tool: &str) -> &'static str {
+    match tool {
+        "rpmbuild" => {
+            "install it with `sudo dnf install rpm-build` (Fedora) or `sudo apt install rpm` \
+             (Debian/Ubuntu)"
+        }
+        "mksquashfs" => {
+            "install it with `sudo apt install squashfs-tools` (Debian/Ubuntu), \
+             `sudo dnf install squashfs-tools` (Fedora) or `brew install squashfs` (macOS)"
+        }
+        "xz" => {
+            "install it with `sudo apt install xz-utils` (Debian/Ubuntu), \
+             `sudo dnf install xz` (Fedora) or `brew install xz` (macOS)"
+        }
+        "dotnet" => {
+            "install the .NET SDK from https://dotnet.microsoft.com/download, which builds \
+             the WiX project"
+        }
+        _ => "install it and make sure it is in PATH",
+    }
+}
+
+/// Returns `git describe --always --dirty` for the repository containing
+/// `dir`, or `None` if git isn't available or `dir` isn't in a repository.
+pub fn git_describe(dir: &Path) -> Option<String> {
+    let mut git = Command::new("git");
+    git.args(["describe", "--always", "--dirty"])
+        .current_dir(dir);
+    log_verbose("Running", &format!("{git:?}")).ok()?;
+    let output = git.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let description = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!description.is_empty()).then_some(description)
+}
+
+/// Returns the `SOURCE_DATE_EPOCH` environment variable, the timestamp that
+/// reproducible builds use in place of the current time, if it is set.
+pub fn source_date_epoch() -> crate::Result<Option<u64>> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value.trim().parse().map(Some).with_context(|| {
+            format!("SOURCE_DATE_EPOCH must be a number of seconds, not {value:?}")
+        }),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Returns the size in bytes of the bundle at `path`: the file's size, or for
+/// a directory bundle (like an `.app`) the total size of the files within it.
+/// Symlinks are not followed.
+pub fn bundle_size(path: &Path) -> crate::Result<u64> {
+    let mut total = 0;
+    for entry in walkdir::WalkDir::new(path) {
+        let entry = entry.with_context(|| format!("Failed to read {path:?}"))?;
+        if entry.file_type().is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Formats a size in bytes for humans, e.g. `512 B` or `3.4 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Writes the SHA-256 digest of the file at `path` to a `<path>.sha256`
+/// sidecar, in the format read by `sha256sum -c`.  Returns the path of the
+/// sidecar.
+pub fn write_checksum(path: &Path) -> crate::Result<PathBuf> {
+    if !path.is_file() {
+        anyhow::bail!("{:?} is not a file", path);
+    }
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path).with_context(|| format!("Failed to open file {path:?}"))?;
+    io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read file {path:?}"))?;
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let filename = path
+        .file_name()
+        .with_context(|| format!("{path:?} has no file name"))?
+        .to_string_lossy();
+    let mut checksum_path = path.as_os_str().to_owned();
+    checksum_path.push(".sha256");
+    let checksum_path = PathBuf::from(checksum_path);
+    let mut checksum_file = create_file(&checksum_path)?;
+    writeln!(checksum_file, "{digest}  {filename}")?;
+    checksum_file.flush()?;
+    log_verbose("Checksum", &checksum_path.display().to_string())?;
+    Ok(checksum_path)
+}
+
 /// Given a path (absolute or relative) to a resource file, returns the
 /// relative path from the bundle resources directory where that resource
 /// should be stored.
@@ -129,28 +572,209 @@ pub fn resource_relpath(path: &Path) -> PathBuf {
     dest
 }
 
+/// Expands a leading `~` to the home directory and `$VAR` or `${VAR}` to the
+/// value of the environment variable.  References to unset variables are
+/// left in place, with a warning the first time each variable is seen.
+pub fn expand_path(path: &str) -> String {
+    let mut output = String::with_capacity(path.len());
+    let mut rest = path;
+    if (rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\"))
+        && let Some(home_dir) = dirs::home_dir()
+    {
+        output.push_str(&home_dir.to_string_lossy());
+        rest = &rest[1..];
+    }
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() {
+            output.push('$');
+            rest = after;
+            continue;
+        }
+        let reference = &rest[start..start + 1 + len];
+        match std::env::var(name) {
+            Ok(value) => output.push_str(&value),
+            Err(_) => {
+                if first_unset_reference(name) {
+                    let _ = print_warning(&format!(
+                        "Environment variable {name} in {path:?} is not set, leaving {reference} \
+                         as is"
+                    ));
+                }
+                output.push_str(reference);
+            }
+        }
+        rest = &after[len..];
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Records that the environment variable `name` is unset, returning true if
+/// it wasn't recorded before.
+fn first_unset_reference(name: &str) -> bool {
+    UNSET_VARIABLES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(name.to_string())
+}
+
+/// Substitutes each `{key}` in `template` with its value from `placeholders`.
+/// Unknown placeholders are left in the output unchanged.
+pub fn render_filename(template: &str, placeholders: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let key = &rest[1..end];
+            placeholders
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (end, *value))
+        });
+        match value {
+            Some((end, value)) => {
+                output.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
 /// Prints a message to stderr, in the same format that `cargo` uses,
 /// indicating that we are creating a bundle with the given filename.
 pub fn print_bundling(filename: &str) -> crate::Result<()> {
     print_progress("Bundling", filename)
 }
 
+/// Prints a message to stderr, in the same format as `print_bundling`,
+/// explaining why something is not being bundled.
+pub fn print_skipping(reason: &str) -> crate::Result<()> {
+    print_progress("Skipping", reason)
+}
+
 /// Prints a message to stderr, in the same format that `cargo` uses,
-/// indicating that we have finished the the given bundles.
-pub fn print_finished(output_paths: &Vec<PathBuf>) -> crate::Result<()> {
-    let pluralised = if output_paths.len() == 1 {
+/// indicating that we have finished the given bundles, each paired with the
+/// short name of its format, followed by a one-line summary of what was
+/// bundled in the `elapsed` time.
+pub fn print_finished(bundles: &[(&str, PathBuf)], elapsed: Duration) -> crate::Result<()> {
+    let pluralised = if bundles.len() == 1 {
         "bundle"
     } else {
         "bundles"
     };
-    let msg = format!("{} {} at:", output_paths.len(), pluralised);
-    print_progress("Finished", &msg)?;
-    for path in output_paths {
-        println!("        {}", path.display());
+    let msg = format!("{} {} at:", bundles.len(), pluralised);
+    let sizes = bundles
+        .iter()
+        .map(|(_, path)| bundle_size(path))
+        .collect::<crate::Result<Vec<_>>>()?;
+    let _output = lock_output();
+    write_progress("Finished", &msg)?;
+    for ((_, path), size) in bundles.iter().zip(sizes) {
+        println!("        {} ({})", path.display(), format_size(size));
+    }
+    let formats: Vec<&str> = bundles.iter().map(|(format, _)| *format).collect();
+    write_progress("Bundled", &finished_summary(&formats, elapsed))
+}
+
+/// Returns e.g. `3 bundles (deb, appimage, rpm) in 12.4s` for bundles of the
+/// given formats, counting the bundles of each format if there are several.
+fn finished_summary(formats: &[&str], elapsed: Duration) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for format in formats {
+        match counts.iter_mut().find(|(other, _)| other == format) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((format, 1)),
+        }
+    }
+    let per_format = if counts.iter().all(|&(_, count)| count == 1) {
+        counts
+            .iter()
+            .map(|(format, _)| format.to_string())
+            .collect::<Vec<_>>()
+    } else {
+        counts
+            .iter()
+            .map(|(format, count)| format!("{count} {format}"))
+            .collect()
+    };
+    format!(
+        "{} bundle{} ({}) in {:.1}s",
+        formats.len(),
+        if formats.len() == 1 { "" } else { "s" },
+        per_format.join(", "),
+        elapsed.as_secs_f64()
+    )
+}
+
+/// Shows `path` in the platform's file manager: selected in a Finder or
+/// Explorer window, or elsewhere by opening its directory with `xdg-open`.
+pub fn reveal_in_file_manager(path: &Path) -> crate::Result<()> {
+    let mut command = reveal_command(path);
+    log_verbose("Running", &format!("{command:?}"))?;
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run {program}, does the {program} binary exist?"))?;
+    // Explorer exits with 1 even when it opened the window.
+    if !status.success() && !cfg!(windows) {
+        anyhow::bail!("`{command:?}` failed with {status}");
     }
     Ok(())
 }
 
+/// Returns the command that `reveal_in_file_manager` runs for `path`.
+fn reveal_command(path: &Path) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else if cfg!(windows) {
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(path);
+        let mut command = Command::new("explorer");
+        command.arg(select);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    }
+}
+
+/// Prints a message to stderr, in the same format as `print_bundling`, but
+/// only when `--verbose` was passed.
+pub fn log_verbose(step: &str, msg: &str) -> crate::Result<()> {
+    if verbosity() == 0 {
+        return Ok(());
+    }
+    print_progress(step, msg)
+}
+
 fn safe_term_attr<T: term::Terminal + ?Sized>(
     output: &mut Box<T>,
     attr: term::Attr,
@@ -161,7 +785,12 @@ fn safe_term_attr<T: term::Terminal + ?Sized>(
     }
 }
 
-fn print_progress(step: &str, msg: &str) -> crate::Result<()> {
+pub fn print_progress(step: &str, msg: &str) -> crate::Result<()> {
+    let _output = lock_output();
+    write_progress(step, msg)
+}
+
+fn write_progress(step: &str, msg: &str) -> crate::Result<()> {
     if let Some(mut output) = term::stderr() {
         safe_term_attr(&mut output, term::Attr::Bold)?;
         if output.supports_color() {
@@ -184,20 +813,36 @@ fn print_progress(step: &str, msg: &str) -> crate::Result<()> {
 }
 
 /// Prints a warning message to stderr, in the same format that `cargo` uses.
+/// In strict mode the warning is also recorded, to fail the run at the end.
 pub fn print_warning(message: &str) -> crate::Result<()> {
+    if STRICT.load(Ordering::Relaxed) {
+        STRICT_WARNINGS.fetch_add(1, Ordering::Relaxed);
+    }
+    print_labelled("warning:", term::color::YELLOW, message)
+}
+
+/// Prints a note to stderr, in the same format as `print_warning`.  Notes are
+/// for things that are printed on every run and that the user can't act on,
+/// so unlike warnings they don't fail strict mode.
+pub fn print_note(message: &str) -> crate::Result<()> {
+    print_labelled("note:", term::color::CYAN, message)
+}
+
+fn print_labelled(label: &str, color: term::color::Color, message: &str) -> crate::Result<()> {
+    let _output = lock_output();
     if let Some(mut output) = term::stderr() {
         safe_term_attr(&mut output, term::Attr::Bold)?;
         if output.supports_color() {
-            output.fg(term::color::YELLOW)?;
+            output.fg(color)?;
         }
-        write!(output, "warning:")?;
+        write!(output, "{label}")?;
         output.reset()?;
         writeln!(output, " {message}")?;
         output.flush()?;
         Ok(())
     } else {
         let mut output = io::stderr();
-        write!(output, "warning:")?;
+        write!(output, "{label}")?;
         writeln!(output, " {message}")?;
         output.flush()?;
         Ok(())
@@ -206,6 +851,7 @@ pub fn print_warning(message: &str) -> crate::Result<()> {
 
 /// Prints an error to stderr, in the same format that `cargo` uses.
 pub fn print_error(error: &anyhow::Error) -> crate::Result<()> {
+    let _output = lock_output();
     if let Some(mut output) = term::stderr() {
         safe_term_attr(&mut output, term::Attr::Bold)?;
         if output.supports_color() {
@@ -243,11 +889,53 @@ pub fn print_error(error: &anyhow::Error) -> crate::Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use super::{copy_dir, create_file, is_retina, read_file, resource_relpath, symlink_file};
+    use super::{
+        NotarySubmission, TOOL_TIMEOUT, bundle_size, check_tools, copy_dir, create_file,
+        expand_path, finished_summary, first_unset_reference, format_size, git_describe, is_retina,
+        prepare_output_path, print_warning, read_file, render_filename, resource_relpath,
+        reveal_command, run_command, set_strict, show_progress, strict_warnings, symlink_file,
+        write_checksum, write_ico_from_pngs,
+    };
 
     use std::io::Write;
     use std::path::{Path, PathBuf};
 
+    #[test]
+    fn bundle_sizes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let app = tmp.path().join("Foo.app");
+        std::fs::create_dir_all(app.join("Contents/MacOS")).unwrap();
+        std::fs::write(app.join("Contents/Info.plist"), [0; 100]).unwrap();
+        std::fs::write(app.join("Contents/MacOS/foo"), [0; 2000]).unwrap();
+        assert_eq!(bundle_size(&app).unwrap(), 2100);
+        assert_eq!(bundle_size(&app.join("Contents/MacOS/foo")).unwrap(), 2000);
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2100), "2.1 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn finished_summaries() {
+        use std::time::Duration;
+        assert_eq!(
+            finished_summary(&["deb"], Duration::from_millis(3040)),
+            "1 bundle (deb) in 3.0s"
+        );
+        assert_eq!(
+            finished_summary(&["deb", "appimage", "rpm"], Duration::from_millis(12400)),
+            "3 bundles (deb, appimage, rpm) in 12.4s"
+        );
+        // Bundling several packages makes several bundles of each format.
+        assert_eq!(
+            finished_summary(
+                &["deb", "rpm", "deb", "rpm", "deb"],
+                Duration::from_secs(65)
+            ),
+            "5 bundles (3 deb, 2 rpm) in 65.0s"
+        );
+    }
+
     #[test]
     fn create_file_with_parent_dirs() {
         let tmp = tempfile::tempdir().unwrap();
@@ -308,6 +996,170 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn run_command_reports_failures() {
+        use std::process::Command;
+        use std::time::Duration;
+
+        let output = run_command(Command::new("sh").args(["-c", "echo hi"]), TOOL_TIMEOUT).unwrap();
+        assert_eq!(output.stdout, b"hi\n");
+
+        let error = run_command(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            TOOL_TIMEOUT,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("exit status: 3"), "{error}");
+        assert!(error.contains("\nout\nerr"), "{error}");
+
+        let error = run_command(Command::new("sleep").arg("5"), Duration::from_millis(100))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("timed out"), "{error}");
+
+        let error = run_command(&mut Command::new("no-such-tool-xyz"), TOOL_TIMEOUT)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("does the no-such-tool-xyz binary exist"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn missing_tools_are_named() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = std::env::join_paths([tmp.path()]).unwrap();
+        let error = check_tools(&["rpmbuild"], "rpm", Some(&path))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("`rpmbuild` is needed to bundle rpm packages"),
+            "{error}"
+        );
+        assert!(error.contains("rpm-build"), "{error}");
+        assert!(check_tools(&["xz"], "tarxz", None).is_err());
+
+        std::fs::write(tmp.path().join("rpmbuild"), "").unwrap();
+        check_tools(&["rpmbuild"], "rpm", Some(&path)).unwrap();
+        check_tools(&[], "deb", None).unwrap();
+    }
+
+    #[test]
+    fn git_describe_outside_repository() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert_eq!(git_describe(tmp.path()), None);
+    }
+
+    #[test]
+    fn strict_mode_records_warnings() {
+        let before = strict_warnings();
+        set_strict(true);
+        print_warning("first").unwrap();
+        print_warning("second").unwrap();
+        set_strict(false);
+        assert!(strict_warnings() >= before + 2);
+    }
+
+    #[test]
+    fn existing_outputs_need_force() {
+        let tmp = tempfile::tempdir().unwrap();
+        let file = tmp.path().join("foobar.deb");
+        let dir = tmp.path().join("Foobar.app");
+        prepare_output_path(&file, false).unwrap();
+        std::fs::write(&file, b"old").unwrap();
+        std::fs::create_dir_all(dir.join("Contents")).unwrap();
+        for path in [&file, &dir] {
+            let error = prepare_output_path(path, false).unwrap_err();
+            assert!(error.to_string().contains("use --force"));
+            assert!(path.exists());
+            prepare_output_path(path, true).unwrap();
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn checksum_sidecar() {
+        let tmp = tempfile::tempdir().unwrap();
+        let artifact = tmp.path().join("foobar_1.2.3_amd64.deb");
+        std::fs::write(&artifact, b"abc").unwrap();
+        let checksum_path = write_checksum(&artifact).unwrap();
+        assert_eq!(
+            checksum_path,
+            tmp.path().join("foobar_1.2.3_amd64.deb.sha256")
+        );
+        assert_eq!(
+            read_file(&checksum_path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  \
+             foobar_1.2.3_amd64.deb\n"
+        );
+        assert!(write_checksum(tmp.path()).is_err());
+    }
+
+    #[test]
+    fn expand_home_and_variables() {
+        let home_dir = dirs::home_dir().unwrap();
+        assert_eq!(
+            expand_path("~/assets/*.png"),
+            format!("{}/assets/*.png", home_dir.display())
+        );
+        assert_eq!(expand_path("a~/b"), "a~/b");
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(expand_path("$PATH/x"), format!("{path}/x"));
+        assert_eq!(expand_path("${PATH}x"), format!("{path}x"));
+        assert_eq!(
+            expand_path("$CARGO_BUNDLER_UNSET_VARIABLE/${CARGO_BUNDLER_UNSET_VARIABLE}/$"),
+            "$CARGO_BUNDLER_UNSET_VARIABLE/${CARGO_BUNDLER_UNSET_VARIABLE}/$"
+        );
+        // The warning above was printed for the first reference only.
+        assert!(!first_unset_reference("CARGO_BUNDLER_UNSET_VARIABLE"));
+        assert!(first_unset_reference("CARGO_BUNDLER_OTHER_UNSET_VARIABLE"));
+        assert!(!first_unset_reference("CARGO_BUNDLER_OTHER_UNSET_VARIABLE"));
+    }
+
+    #[test]
+    fn progress_for_many_files_on_a_terminal() {
+        assert!(!show_progress(false, 10, true));
+        assert!(show_progress(false, 51, true));
+        assert!(!show_progress(false, 51, false));
+        assert!(show_progress(true, 1, false));
+        assert!(!show_progress(true, 0, false));
+    }
+
+    #[test]
+    fn render_filename_placeholders() {
+        let placeholders = [("name", "foobar"), ("version", "1.2.3"), ("arch", "x86_64")];
+        assert_eq!(
+            render_filename("{name}-{version}-{arch}", &placeholders),
+            "foobar-1.2.3-x86_64"
+        );
+        assert_eq!(
+            render_filename("{name}_{nightly}_{", &placeholders),
+            "foobar_{nightly}_{"
+        );
+        assert_eq!(render_filename("release", &placeholders), "release");
+    }
+
+    #[test]
+    fn ico_from_pngs() {
+        let icons = vec![
+            PathBuf::from("examples/hello/icon32x32.png"),
+            PathBuf::from("examples/hello/icon128x128.png"),
+        ];
+        let mut ico = Vec::new();
+        assert!(write_ico_from_pngs(&icons, &mut ico).unwrap());
+        let decoded = image::load_from_memory_with_format(&ico, image::ImageFormat::Ico).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (256, 256));
+        // The directory header lists one entry per generated size.
+        assert_eq!(u16::from_le_bytes([ico[4], ico[5]]), 4);
+
+        let mut ico = Vec::new();
+        assert!(!write_ico_from_pngs(&[PathBuf::from("icon.icns")], &mut ico).unwrap());
+        assert!(ico.is_empty());
+    }
+
     #[test]
     fn retina_icon_paths() {
         assert!(!is_retina("data/icons/512x512.png"));
@@ -330,6 +1182,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn notary_submission_output() {
+        let output = br#"{"id":"2efe2717-52ef-43a5-96dc-0797e4ca1041","message":"Processing complete","status":"Invalid"}"#;
+        let submission: NotarySubmission = serde_json::from_slice(output).unwrap();
+        assert_eq!(submission.id, "2efe2717-52ef-43a5-96dc-0797e4ca1041");
+        assert_eq!(submission.status, "Invalid");
+        assert_eq!(submission.message, "Processing complete");
+    }
+
+    #[test]
+    fn reveal_commands() {
+        let bundle = Path::new("/out/bundle/deb/foo_0.1.0_amd64.deb");
+        let command = reveal_command(bundle);
+        let args: Vec<_> = command.get_args().collect();
+        if cfg!(target_os = "macos") {
+            assert_eq!(command.get_program(), "open");
+            assert_eq!(args, [std::ffi::OsStr::new("-R"), bundle.as_os_str()]);
+        } else if cfg!(windows) {
+            assert_eq!(command.get_program(), "explorer");
+            assert_eq!(args, ["/select,/out/bundle/deb/foo_0.1.0_amd64.deb"]);
+        } else {
+            assert_eq!(command.get_program(), "xdg-open");
+            assert_eq!(args, ["/out/bundle/deb"]);
+        }
+    }
+
     #[test]
     fn read_files() {
         const HELLO_WORLD: &str = "Hello, world!";
@@ -3,12 +3,14 @@
 // Foobar.app         # Actually a directory
 //     Foobar             # The main binary executable of the app
 //     Info.plist         # An XML file containing the app's metadata
+//     embedded.mobileprovision  # The provisioning profile (optional)
+//     _CodeSignature/    # Added by codesign (optional)
 //     ...                # Icons and other resource files
 //
 // See https://developer.apple.com/go/?id=bundle-structure for a full
 // explanation.
 
-use super::common;
+use super::{common, plist};
 use crate::Settings;
 use anyhow::Context;
 use image::{self, GenericImageView};
@@ -17,27 +19,29 @@ use std::ffi::OsStr;
 use std::fs::{self, File};
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    common::print_warning("iOS bundle support is still experimental.")?;
+    common::print_note("iOS bundle support is still experimental.")?;
+    Ok(vec![build_app(settings, settings.force())?])
+}
 
-    let app_bundle_name = format!("{}.app", settings.bundle_name());
+/// Builds the `.app` bundle and returns its path.  An existing bundle at that
+/// path is only replaced if `overwrite` is set.
+pub(super) fn build_app(settings: &Settings, overwrite: bool) -> crate::Result<PathBuf> {
+    let app_bundle_name = format!(
+        "{}.app",
+        settings.artifact_name(settings.binary_arch(), settings.bundle_name())
+    );
     common::print_bundling(&app_bundle_name)?;
     let bundle_dir = settings
-        .get_target_dir()
-        .join("bundle/ios")
+        .bundle_dir(crate::bundle::PackageType::IosBundle)
         .join(&app_bundle_name);
-    if bundle_dir.exists() {
-        fs::remove_dir_all(&bundle_dir)
-            .with_context(|| format!("Failed to remove old {app_bundle_name}"))?;
-    }
+    common::prepare_output_path(&bundle_dir, overwrite)?;
     fs::create_dir_all(&bundle_dir)
         .with_context(|| format!("Failed to create bundle directory at {bundle_dir:?}"))?;
 
-    for (src, dst) in settings.resources_paths(bundle_dir.as_path()) {
-        common::copy_file(&src, &dst)
-            .with_context(|| format!("Failed to copy resource file {src:?} to {dst:?}"))?;
-    }
+    common::copy_resources(settings, &bundle_dir)?;
 
     let icon_filenames =
         generate_icon_files(&bundle_dir, settings).with_context(|| "Failed to create app icons")?;
@@ -56,7 +60,55 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
             settings.binary_path(crate::bundle::PackageType::IosBundle)
         )
     })?;
-    Ok(vec![bundle_dir])
+
+    // The decoded profile holds the signing certificates, so it and the
+    // entitlements are kept in a temporary directory that is removed once the
+    // bundle is signed.
+    let entitlements = match settings.ios_provisioning_profile() {
+        Some(profile) => {
+            common::copy_file(&profile, &bundle_dir.join("embedded.mobileprovision"))
+                .with_context(|| "Failed to embed provisioning profile")?;
+            let dir = tempfile::tempdir()
+                .with_context(|| "Failed to create a directory for the entitlements")?;
+            let path = dir.path().join("app.entitlements");
+            extract_entitlements(&profile, &path)
+                .with_context(|| "Failed to extract entitlements from provisioning profile")?;
+            Some((dir, path))
+        }
+        None => None,
+    };
+    if let Some(identity) = settings.ios_signing_identity() {
+        let mut codesign = Command::new("codesign");
+        codesign.arg("--force").arg("--sign").arg(identity);
+        if let Some((_, entitlements_path)) = &entitlements {
+            codesign.arg("--entitlements").arg(entitlements_path);
+        }
+        codesign.arg(&bundle_dir);
+        common::run_command(&mut codesign, common::TOOL_TIMEOUT)
+            .with_context(|| "Failed to sign the app bundle")?;
+    }
+    Ok(bundle_dir)
+}
+
+/// Writes the `Entitlements` dictionary of the provisioning profile at
+/// `profile` to a plist at `dest`, for passing to `codesign`.
+fn extract_entitlements(profile: &Path, dest: &Path) -> crate::Result<()> {
+    // The profile is a CMS-signed plist; decode it first.
+    let decoded = dest.with_extension("mobileprovision.plist");
+    let mut security = Command::new("security");
+    security
+        .args(["cms", "-D", "-i"])
+        .arg(profile)
+        .arg("-o")
+        .arg(&decoded);
+    common::run_command(&mut security, common::TOOL_TIMEOUT)?;
+    let mut plist_buddy = Command::new("/usr/libexec/PlistBuddy");
+    plist_buddy
+        .args(["-x", "-c", "Print :Entitlements"])
+        .arg(&decoded);
+    let output = common::run_command(&mut plist_buddy, common::TOOL_TIMEOUT)?;
+    fs::write(dest, output.stdout).with_context(|| format!("Failed to write {dest:?}"))?;
+    Ok(())
 }
 
 /// Generate the icon files and store them under the `bundle_dir`.
@@ -129,66 +181,86 @@ fn generate_icon_files(bundle_dir: &Path, settings: &Settings) -> crate::Result<
 fn generate_info_plist(
     bundle_dir: &Path,
     settings: &Settings,
-    icon_filenames: &Vec<String>,
+    icon_filenames: &[String],
 ) -> crate::Result<()> {
-    let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
-    write!(
-        file,
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-            <!DOCTYPE plist PUBLIC \"-//Apple Computer//DTD PLIST 1.0//EN\" \
-            \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
-            <plist version=\"1.0\">\n\
-            <dict>\n"
-    )?;
-
-    write!(
-        file,
-        "  <key>CFBundleIdentifier</key>\n  <string>{}</string>\n",
-        settings.bundle_identifier()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleDisplayName</key>\n  <string>{}</string>\n",
-        settings.bundle_name()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleName</key>\n  <string>{}</string>\n",
-        settings.bundle_name()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleExecutable</key>\n  <string>{}</string>\n",
-        settings.binary_name()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleVersion</key>\n  <string>{}</string>\n",
-        settings.version_string()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleShortVersionString</key>\n  <string>{}</string>\n",
-        settings.version_string()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleDevelopmentRegion</key>\n  <string>en_US</string>\n"
-    )?;
-    write!(
-        file,
-        "  <key>UILaunchStoryboardName</key>\n  <string></string>\n"
-    )?;
-
+    let mut dict = plist::Dictionary::new();
+    let mut insert = |key: &str, value: plist::Value| {
+        dict.insert(key.to_string(), value);
+    };
+    insert(
+        "CFBundleIdentifier",
+        settings.bundle_identifier().into_owned().into(),
+    );
+    insert("CFBundleDisplayName", settings.bundle_name().into());
+    insert("CFBundleName", settings.bundle_name().into());
+    insert("CFBundleExecutable", settings.binary_name().into());
+    insert(
+        "CFBundleVersion",
+        settings.version_string().to_string().into(),
+    );
+    if let Some(build_info) = settings.build_info() {
+        insert("BuildGitCommit", build_info.git_commit.into());
+        insert("BuildTimestamp", build_info.timestamp.into());
+    }
+    insert(
+        "CFBundleShortVersionString",
+        settings.version_string().to_string().into(),
+    );
+    insert("CFBundleDevelopmentRegion", "en_US".into());
+    insert("UILaunchStoryboardName", "".into());
     if !icon_filenames.is_empty() {
-        write!(file, "  <key>CFBundleIconFiles</key>\n  <array>\n")?;
-        for filename in icon_filenames {
-            writeln!(file, "    <string>{filename}</string>")?;
-        }
-        writeln!(file, "  </array>")?;
+        insert(
+            "CFBundleIconFiles",
+            plist::Value::Array(
+                icon_filenames
+                    .iter()
+                    .map(|filename| filename.as_str().into())
+                    .collect(),
+            ),
+        );
     }
-    write!(file, "  <key>LSRequiresIPhoneOS</key>\n  <true/>\n")?;
-    write!(file, "</dict>\n</plist>\n")?;
+    insert("LSRequiresIPhoneOS", true.into());
+    if let Some(version) = settings.ios_minimum_os_version() {
+        insert("MinimumOSVersion", version.into());
+    }
+    insert(
+        "UIDeviceFamily",
+        plist::Value::Array(
+            settings
+                .ios_device_family()
+                .iter()
+                .map(|&family| plist::Value::Integer(family.into()))
+                .collect(),
+        ),
+    );
+    if !settings.ios_supported_orientations().is_empty() {
+        insert(
+            "UISupportedInterfaceOrientations",
+            plist::Value::Array(
+                settings
+                    .ios_supported_orientations()
+                    .iter()
+                    .map(|orientation| orientation.as_str().into())
+                    .collect(),
+            ),
+        );
+    }
+    if let Some(capability) = settings.ios_required_device_capability() {
+        insert(
+            "UIRequiredDeviceCapabilities",
+            plist::Value::Array(vec![capability.into()]),
+        );
+    }
+    // Keys set by the user take precedence over the generated ones.
+    plist::merge(
+        &mut dict,
+        plist::dictionary_from_toml(settings.osx_info_plist_keys())?,
+    );
+
+    let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
+    write!(file, "{}", plist::HEADER)?;
+    plist::write_entries(file, &dict)?;
+    write!(file, "{}", plist::FOOTER)?;
     file.flush()?;
     Ok(())
 }
@@ -0,0 +1,109 @@
+// An iOS app archive is a zip file laid out like:
+//
+// Foobar.ipa
+//     Payload/
+//         Foobar.app/        # The (signed) app bundle made by `ios_bundle`
+//
+// This is the artifact that is uploaded to App Store Connect or installed
+// with tools like Apple Configurator.
+
+use super::{common, ios_bundle};
+use crate::Settings;
+use anyhow::Context;
+use std::fs::File;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    common::print_note("iOS bundle support is still experimental.")?;
+    // The .app is only an intermediate of the .ipa, so it is always rebuilt.
+    let app_path = ios_bundle::build_app(settings, true)?;
+    let ipa_name = format!(
+        "{}.ipa",
+        settings.artifact_name(settings.binary_arch(), settings.bundle_name())
+    );
+    common::print_bundling(&ipa_name)?;
+    let ipa_path = settings
+        .bundle_dir(crate::bundle::PackageType::IosIpa)
+        .join(&ipa_name);
+    common::prepare_output_path(&ipa_path, settings.force())?;
+    let file = common::create_file(&ipa_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    add_to_payload(&mut zip, &app_path)
+        .with_context(|| format!("Failed to add {app_path:?} to {ipa_name}"))?;
+    zip.finish()?;
+    Ok(vec![ipa_path])
+}
+
+/// Adds the app bundle at `app_path`, recursively, under `Payload/`.
+fn add_to_payload<W: io::Write + io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    app_path: &Path,
+) -> crate::Result<()> {
+    let base_dir = app_path.parent().unwrap();
+    for entry in WalkDir::new(app_path).sort_by_file_name() {
+        let entry = entry?;
+        let rel_path = entry.path().strip_prefix(base_dir).unwrap();
+        let name = Path::new("Payload").join(rel_path);
+        // Zip entry names always use forward slashes.
+        let name = name.to_string_lossy().replace('\\', "/");
+        #[allow(unused_mut)]
+        let mut options = SimpleFileOptions::default();
+        #[cfg(unix)]
+        {
+            options = options.unix_permissions(entry.metadata()?.permissions().mode());
+        }
+        if entry.file_type().is_dir() {
+            zip.add_directory(name, options)?;
+        } else {
+            common::log_verbose("Archiving", &name)?;
+            zip.start_file(name, options)?;
+            io::copy(&mut File::open(entry.path())?, zip)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::add_to_payload;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn app_goes_under_payload() {
+        let tmp = tempfile::tempdir().unwrap();
+        let app_path = tmp.path().join("Foobar.app");
+        std::fs::create_dir_all(app_path.join("assets")).unwrap();
+        std::fs::write(app_path.join("Foobar"), b"binary").unwrap();
+        std::fs::write(app_path.join("assets/data.txt"), b"data").unwrap();
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        add_to_payload(&mut zip, &app_path).unwrap();
+        let mut archive = zip.finish_into_readable().unwrap();
+        let mut names: Vec<String> = archive
+            .file_names()
+            .map(|name| name.unwrap().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "Payload/Foobar.app/",
+                "Payload/Foobar.app/Foobar",
+                "Payload/Foobar.app/assets/",
+                "Payload/Foobar.app/assets/data.txt",
+            ]
+        );
+        let mut contents = String::new();
+        archive
+            .by_name("Payload/Foobar.app/assets/data.txt")
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "data");
+    }
+}
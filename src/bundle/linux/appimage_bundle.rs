@@ -4,13 +4,15 @@ use std::os::unix::fs::PermissionsExt;
 use std::{
     fs::File,
     io::{BufReader, BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Command,
 };
 
 use crate::bundle::{Settings, common, linux::common::transfer_resource_files};
 
-use super::common::{generate_desktop_file, generate_icon_files};
+use super::common::{
+    extract_debug_symbols, generate_desktop_file, generate_icon_files, transfer_linux_files,
+};
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     let package_base_name = format!(
@@ -19,16 +21,20 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         settings.version_string(),
         settings.binary_arch()
     );
-    let package_name = format!("{package_base_name}.AppImage");
+    let package_name = format!(
+        "{}.AppImage",
+        settings.artifact_name(settings.binary_arch(), package_base_name.clone())
+    );
     common::print_bundling(&package_name)?;
 
-    let base_dir = settings.get_target_dir().join("bundle/appimage");
+    let base_dir = settings.bundle_dir(crate::bundle::PackageType::AppImage);
     let package_dir = base_dir.join(&package_base_name);
     if package_dir.exists() {
         std::fs::remove_dir_all(&package_dir)
             .with_context(|| format!("Failed to remove old {package_base_name}"))?;
     }
     let package_path = base_dir.join(&package_name);
+    common::prepare_output_path(&package_path, settings.force())?;
 
     let app_dir = package_dir.join("AppDir");
     let binary_dest_rel = PathBuf::from("usr/bin").join(settings.binary_name());
@@ -40,27 +46,51 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         &binary_dest_abs,
     )?;
     transfer_resource_files(settings, &app_dir).with_context(|| "Failed to copy resource files")?;
+    transfer_linux_files(settings, &app_dir).with_context(|| "Failed to copy linux files")?;
     generate_icon_files(settings, &app_dir)?;
     generate_desktop_file(settings, &app_dir)?;
+    if settings.appimage_no_integration() {
+        disable_desktop_integration(settings, &app_dir)?;
+    }
 
     // TODO Symlinks (AppRun, .DirIcon, .desktop)
-    common::symlink_file(&binary_dest_rel, &app_dir.join("AppRun"))?;
+    if settings.appimage_bundle_libs() {
+        bundle_shared_libs(&binary_dest_abs, &app_dir.join("usr/lib"))
+            .with_context(|| "Failed to bundle shared libraries")?;
+    }
+    if settings.appimage_bundle_libs() || settings.appimage_no_integration() {
+        generate_app_run(settings, &app_dir).with_context(|| "Failed to create AppRun")?;
+    } else {
+        common::symlink_file(&binary_dest_rel, &app_dir.join("AppRun"))?;
+    }
 
     // Download the AppImage runtime
     let runtime = fetch_runtime(settings.binary_arch())?;
 
     // Make the squashfs
     let squashfs = base_dir.join(format!("{package_name}.squashfs"));
-    let _status = Command::new("mksquashfs")
+    let mut mksquashfs = Command::new("mksquashfs");
+    mksquashfs
         .arg(&app_dir)
         .arg(&squashfs)
         .arg("-root-owned")
         .arg("-noappend")
-        .arg("-quiet")
-        .status()
-        .with_context(|| "Failed to make sqaushfs, does the mksquashfs binary exist?")?;
+        .arg("-quiet");
+    if let Some(compression) = settings.appimage_compression() {
+        mksquashfs.arg("-comp").arg(compression);
+    }
+    common::run_command(&mut mksquashfs, common::TOOL_TIMEOUT)
+        .with_context(|| "Failed to make squashfs")?;
 
     // Write the runtime and the fs to the .AppImage file
+    common::log_verbose(
+        "Archiving",
+        &format!(
+            "runtime + {} -> {}",
+            squashfs.display(),
+            package_path.display()
+        ),
+    )?;
     let mut squashfs = BufReader::new(File::open(squashfs)?);
     let mut f = File::create(&package_path)?;
     let mut out = BufWriter::new(&mut f);
@@ -73,7 +103,134 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     perms.set_mode(0o755);
     std::fs::set_permissions(&package_path, perms)?;
 
-    Ok(vec![package_path])
+    let mut output_paths = vec![package_path];
+    if settings.include_debug_symbols() {
+        let debug_path = output_paths[0].with_extension("debug");
+        output_paths.push(extract_debug_symbols(
+            settings,
+            crate::bundle::PackageType::AppImage,
+            &debug_path,
+        )?);
+    }
+    Ok(output_paths)
+}
+
+// Libraries that have to come from the host system: glibc (which must match
+// the host's dynamic loader) and the graphics/windowing stack (which must
+// match the host's drivers and display server).
+const HOST_LIBRARIES: &[&str] = &[
+    "ld-linux",
+    "libc.so",
+    "libm.so",
+    "libdl.so",
+    "libpthread.so",
+    "librt.so",
+    "libresolv.so",
+    "libutil.so",
+    "libnsl.so",
+    "libanl.so",
+    "libBrokenLocale.so",
+    "libGL.so",
+    "libGLX.so",
+    "libGLdispatch.so",
+    "libEGL.so",
+    "libOpenGL.so",
+    "libdrm.so",
+    "libX11.so",
+    "libX11-xcb.so",
+    "libxcb.so",
+];
+
+/// Parses the output of `ldd`, returning the resolved paths of the shared
+/// libraries it lists.
+fn parse_ldd_libraries(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (_, resolved) = line.split_once(" => ")?;
+            let end = resolved.find(" (").unwrap_or(resolved.len());
+            let path = resolved[..end].trim();
+            path.starts_with('/').then(|| PathBuf::from(path))
+        })
+        .collect()
+}
+
+/// Returns true if the given library must be loaded from the host system
+/// rather than bundled.
+fn is_host_library(path: &Path) -> bool {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    HOST_LIBRARIES.iter().any(|lib| file_name.starts_with(lib))
+}
+
+/// Copies the shared libraries the binary links against, other than the ones
+/// in `HOST_LIBRARIES`, into `lib_dir`.
+fn bundle_shared_libs(binary: &Path, lib_dir: &Path) -> crate::Result<()> {
+    let mut ldd = Command::new("ldd");
+    ldd.arg(binary);
+    let out = common::run_command(&mut ldd, common::TOOL_TIMEOUT)?;
+    for library in parse_ldd_libraries(&String::from_utf8_lossy(&out.stdout)) {
+        if is_host_library(&library) {
+            continue;
+        }
+        common::copy_file(&library, &lib_dir.join(library.file_name().unwrap()))?;
+    }
+    Ok(())
+}
+
+/// Marks the AppImage's desktop file with `X-AppImage-Integrate=false`, which
+/// appimaged and AppImageLauncher honor by not offering to integrate it.
+fn disable_desktop_integration(settings: &Settings, app_dir: &Path) -> crate::Result<()> {
+    let desktop_file_path = app_dir
+        .join("usr/share/applications")
+        .join(format!("{}.desktop", settings.binary_name()));
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&desktop_file_path)
+        .with_context(|| format!("Failed to open {desktop_file_path:?}"))?;
+    writeln!(file, "X-AppImage-Integrate=false")?;
+    Ok(())
+}
+
+/// Returns the `AppRun` script that starts the binary.  With `bundle_libs`
+/// it adds the bundled `usr/lib` directory to the library search path, and
+/// with `no_integration` it sets `APPIMAGELAUNCHER_DISABLE`, so that
+/// AppImageLauncher doesn't prompt when the app relaunches itself.
+fn app_run_script(binary_name: &str, bundle_libs: bool, no_integration: bool) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str("HERE=\"$(dirname \"$(readlink -f \"$0\")\")\"\n");
+    if bundle_libs {
+        script.push_str(
+            "export LD_LIBRARY_PATH=\"$HERE/usr/lib${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}\"\n",
+        );
+    }
+    if no_integration {
+        script.push_str("export APPIMAGELAUNCHER_DISABLE=1\n");
+    }
+    script.push_str(&format!("exec \"$HERE/usr/bin/{binary_name}\" \"$@\"\n"));
+    script
+}
+
+/// Writes the `AppRun` script returned by `app_run_script`.
+fn generate_app_run(settings: &Settings, app_dir: &Path) -> crate::Result<()> {
+    let app_run = app_dir.join("AppRun");
+    let mut file = common::create_file(&app_run)?;
+    file.write_all(
+        app_run_script(
+            &settings.binary_name(),
+            settings.appimage_bundle_libs(),
+            settings.appimage_no_integration(),
+        )
+        .as_bytes(),
+    )?;
+    file.flush()?;
+    drop(file);
+
+    #[allow(unused_mut)]
+    let mut perms = std::fs::metadata(&app_run)?.permissions();
+    #[cfg(unix)]
+    perms.set_mode(0o755);
+    std::fs::set_permissions(&app_run, perms)?;
+    Ok(())
 }
 
 fn fetch_runtime(arch: &str) -> crate::Result<Vec<u8>> {
@@ -88,3 +245,50 @@ fn fetch_runtime(arch: &str) -> crate::Result<Vec<u8>> {
 
     Ok(response.to_vec())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{app_run_script, is_host_library, parse_ldd_libraries};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn parse_ldd_output() {
+        let output = "\
+\tlinux-vdso.so.1 (0x00007ffd6c5f2000)
+\tlibSDL2-2.0.so.0 => /usr/lib/x86_64-linux-gnu/libSDL2-2.0.so.0 (0x00007f0e4a000000)
+\tlibc.so.6 => /lib/x86_64-linux-gnu/libc.so.6 (0x00007f0e49c00000)
+\tlibmissing.so.1 => not found
+\t/lib64/ld-linux-x86-64.so.2 (0x00007f0e4a4c1000)
+";
+        assert_eq!(
+            parse_ldd_libraries(output),
+            vec![
+                PathBuf::from("/usr/lib/x86_64-linux-gnu/libSDL2-2.0.so.0"),
+                PathBuf::from("/lib/x86_64-linux-gnu/libc.so.6"),
+            ]
+        );
+    }
+
+    #[test]
+    fn host_libraries() {
+        assert!(is_host_library(Path::new(
+            "/lib/x86_64-linux-gnu/libc.so.6"
+        )));
+        assert!(is_host_library(Path::new("/usr/lib/libGL.so.1")));
+        assert!(is_host_library(Path::new("/usr/lib/libX11.so.6")));
+        assert!(!is_host_library(Path::new("/usr/lib/libSDL2-2.0.so.0")));
+        assert!(!is_host_library(Path::new("/usr/lib/libmagic.so.1")));
+    }
+
+    #[test]
+    fn app_run_scripts() {
+        let script = app_run_script("foo", true, false);
+        assert!(script.contains("export LD_LIBRARY_PATH=\"$HERE/usr/lib"));
+        assert!(!script.contains("APPIMAGELAUNCHER_DISABLE"));
+        assert!(script.ends_with("exec \"$HERE/usr/bin/foo\" \"$@\"\n"));
+
+        let script = app_run_script("foo", false, true);
+        assert!(!script.contains("LD_LIBRARY_PATH"));
+        assert!(script.contains("export APPIMAGELAUNCHER_DISABLE=1\n"));
+    }
+}
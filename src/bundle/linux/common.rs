@@ -1,4 +1,4 @@
-use crate::bundle::{Settings, common};
+use crate::bundle::{PackageType, Settings, category::AppCategory, common};
 use anyhow::Context;
 use image::GenericImageView;
 use libflate::gzip;
@@ -9,8 +9,11 @@ use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use walkdir::WalkDir;
 
+const HICOLOR_ICONS_DIR: &str = "usr/share/icons/hicolor";
+
 /// Generate the application desktop file and store it under the `data_dir`.
 pub fn generate_desktop_file(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
     let bin_name = settings.binary_name();
@@ -28,7 +31,7 @@ pub fn generate_desktop_file(settings: &Settings, data_dir: &Path) -> crate::Res
     writeln!(file, "[Desktop Entry]")?;
     writeln!(file, "Encoding=UTF-8")?;
     if let Some(category) = settings.app_category() {
-        writeln!(file, "Categories={}", category.gnome_desktop_categories())?;
+        writeln!(file, "Categories={}", desktop_categories(category))?;
     }
     if !settings.short_description().is_empty() {
         writeln!(file, "Comment={}", settings.short_description())?;
@@ -52,12 +55,51 @@ pub fn generate_desktop_file(settings: &Settings, data_dir: &Path) -> crate::Res
     Ok(())
 }
 
+/// Returns the name of the deb or RPM package: the bundle name, lowercased
+/// and with spaces replaced by dashes.
+pub fn package_name(settings: &Settings) -> String {
+    settings
+        .bundle_name()
+        .replace(' ', "-")
+        .to_ascii_lowercase()
+}
+
+/// Writes the debug symbols of the binary to `dest` with
+/// `objcopy --only-keep-debug`, so that they can be shipped separately from
+/// the package.
+pub fn extract_debug_symbols(
+    settings: &Settings,
+    package_type: PackageType,
+    dest: &Path,
+) -> crate::Result<PathBuf> {
+    let mut objcopy = Command::new("objcopy");
+    objcopy
+        .arg("--only-keep-debug")
+        .arg(settings.binary_path(package_type))
+        .arg(dest);
+    common::run_command(&mut objcopy, common::TOOL_TIMEOUT)
+        .with_context(|| "Failed to extract debug symbols")?;
+    Ok(dest.to_path_buf())
+}
+
+/// Formats the `Categories=` value of a desktop file for `category`.
+fn desktop_categories(category: AppCategory) -> String {
+    category
+        .freedesktop_categories()
+        .iter()
+        .fold("".to_owned(), |acc, s| format!("{acc}{s};"))
+}
+
 /// Creates a `.tar.gz` file from the given directory (placing the new file
 /// within the given directory's parent directory), then deletes the original
 /// directory and returns the path to the new file.
 pub fn tar_and_gzip_dir<P: AsRef<Path>>(src_dir: P) -> crate::Result<PathBuf> {
     let src_dir = src_dir.as_ref();
     let dest_path = src_dir.with_extension("tar.gz");
+    common::log_verbose(
+        "Archiving",
+        &format!("{} -> {}", src_dir.display(), dest_path.display()),
+    )?;
     let dest_file = common::create_file(&dest_path)?;
     let gzip_encoder = gzip::Encoder::new(dest_file)?;
     let gzip_encoder = create_tar_from_dir(src_dir, gzip_encoder)?;
@@ -132,10 +174,7 @@ fn generate_icon_files_png(
     binary_name: &str,
     mut sizes: BTreeSet<(u32, u32, bool)>,
 ) -> crate::Result<BTreeSet<(u32, u32, bool)>> {
-    let img = image::ImageReader::open(icon_path)?
-        .with_guessed_format()?
-        .decode()?;
-    let (width, height) = img.dimensions();
+    let (width, height) = image::image_dimensions(icon_path)?;
     let is_high_density = common::is_retina(icon_path);
 
     if !sizes.contains(&(width, height, is_high_density)) {
@@ -184,19 +223,24 @@ fn generate_icon_files_non_png(
     Ok(sizes.to_owned())
 }
 
-/// Generate the icon files and store them under the `data_dir`.
+/// Generate the icon files and store them under the `data_dir`.  Bitmap
+/// icons go in the `hicolor` directory matching their size, and an SVG icon
+/// goes in `hicolor/scalable`.
 pub fn generate_icon_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
-    let base_dir = data_dir.join("usr/share/icons/hicolor");
+    let base_dir = data_dir.join(HICOLOR_ICONS_DIR);
 
     let mut sizes: BTreeSet<(u32, u32, bool)> = BTreeSet::new();
     let binary_name = settings.binary_name();
 
     for icon_path in settings.icon_files() {
         let icon_path = icon_path?;
-        if icon_path.extension() == Some(OsStr::new("png")) {
+        if icon_path.extension() == Some(OsStr::new("svg")) {
+            let dest_path = base_dir.join(format!("scalable/apps/{binary_name}.svg"));
+            common::copy_file(&icon_path, &dest_path)?;
+        } else if icon_path.extension() == Some(OsStr::new("png")) {
             let new_sizes =
                 generate_icon_files_png(&icon_path, &base_dir, binary_name.as_str(), sizes.clone())
-                    .unwrap();
+                    .with_context(|| format!("Failed to install icon {icon_path:?}"))?;
             sizes.append(&mut new_sizes.to_owned())
         } else {
             let new_sizes = generate_icon_files_non_png(
@@ -205,7 +249,7 @@ pub fn generate_icon_files(settings: &Settings, data_dir: &Path) -> crate::Resul
                 binary_name.as_str(),
                 sizes.clone(),
             )
-            .unwrap();
+            .with_context(|| format!("Failed to install icon {icon_path:?}"))?;
             sizes.append(&mut new_sizes.to_owned())
         }
     }
@@ -213,6 +257,17 @@ pub fn generate_icon_files(settings: &Settings, data_dir: &Path) -> crate::Resul
     Ok(())
 }
 
+/// Returns a shell snippet that refreshes the `hicolor` icon cache, for
+/// package scripts to run after icons were installed or removed.  Does
+/// nothing if the package installs no icons.
+pub fn icon_cache_update_script(data_dir: &Path) -> Option<&'static str> {
+    data_dir.join(HICOLOR_ICONS_DIR).is_dir().then_some(
+        "if command -v gtk-update-icon-cache >/dev/null 2>&1; then\n\
+         \x20   gtk-update-icon-cache -q -t -f /usr/share/icons/hicolor || true\n\
+         fi\n",
+    )
+}
+
 /// Compute the md5 hash of the given file.
 pub fn generate_md5sum(file_path: &Path) -> crate::Result<Digest> {
     let mut file = File::open(file_path)?;
@@ -221,13 +276,227 @@ pub fn generate_md5sum(file_path: &Path) -> crate::Result<Digest> {
     Ok(hash.finalize())
 }
 
+/// Copy the bundle's resource files into an appropriate directory under the
+/// `data_dir`.
+pub fn transfer_resource_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let resource_dir = data_dir.join("usr/lib").join(settings.binary_name());
+
+    common::copy_resources(settings, &resource_dir)
+}
+
+/// Writes a `build-info` file with the git commit and build time next to the
+/// resource files, if `embed_build_info` is enabled.
+pub fn generate_build_info(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let Some(build_info) = settings.build_info() else {
+        return Ok(());
+    };
+    let path = data_dir
+        .join("usr/lib")
+        .join(settings.binary_name())
+        .join("build-info");
+    let mut file = common::create_file(&path)?;
+    writeln!(file, "commit={}", build_info.git_commit)?;
+    writeln!(file, "timestamp={}", build_info.timestamp)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Copy the bundle's `linux_files` into the `data_dir`, placing each one at
+/// its absolute install location.  A destination ending in `/` is treated as
+/// a directory, and the source file keeps its name inside it.
+pub fn transfer_linux_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    for (src, dest) in settings.linux_files() {
+        if !dest.starts_with('/') {
+            anyhow::bail!("Install path {dest:?} for linux file {src:?} must be absolute");
+        }
+        let src = common::expand_path(src);
+        let src = Path::new(&src);
+        let mut dest_path = data_dir.join(dest.trim_start_matches('/'));
+        if dest.ends_with('/') {
+            let file_name = src
+                .file_name()
+                .with_context(|| format!("Linux file {src:?} has no file name"))?;
+            dest_path.push(file_name);
+        }
+        common::copy_file(src, &dest_path)
+            .with_context(|| format!("Failed to copy linux file {src:?}"))?;
+    }
+    Ok(())
+}
+
+/// Returns the man page section (e.g. `"1"` or `"3"`) that the given man page
+/// file belongs to, based on its filename suffix (e.g. `myapp.1` or
+/// `libfoo.3ssl`).
+fn man_page_section(path: &Path) -> crate::Result<String> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some(ext) if ext.starts_with(|c: char| matches!(c, '1'..='9')) => Ok(ext[..1].to_string()),
+        _ => anyhow::bail!("Man page {path:?} does not end in a section number (e.g. `.1`)"),
+    }
+}
+
+/// Gzip the bundle's man pages and install them under
+/// `usr/share/man/manN/` within the `data_dir`.
+pub fn generate_man_pages(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    for man_page in settings.linux_man_pages() {
+        let man_page = common::expand_path(man_page);
+        let src_path = Path::new(&man_page);
+        let section = man_page_section(src_path)?;
+        let file_name = src_path.file_name().unwrap().to_string_lossy();
+        let dest_path = data_dir
+            .join(format!("usr/share/man/man{section}"))
+            .join(format!("{file_name}.gz"));
+        let mut src_file = File::open(src_path)
+            .with_context(|| format!("Failed to open man page {src_path:?}"))?;
+        let mut gzip_encoder = gzip::Encoder::new(common::create_file(&dest_path)?)?;
+        io::copy(&mut src_file, &mut gzip_encoder)?;
+        let mut dest_file = gzip_encoder.finish().into_result()?;
+        dest_file.flush()?;
+    }
+    Ok(())
+}
+
+/// Returns the directory, relative to the package root, that completion files
+/// for the given shell are installed into.
+fn completions_dir(shell: &str) -> crate::Result<&'static str> {
+    match shell {
+        "bash" => Ok("usr/share/bash-completion/completions"),
+        "zsh" => Ok("usr/share/zsh/vendor-completions"),
+        "fish" => Ok("usr/share/fish/vendor_completions.d"),
+        other => anyhow::bail!(
+            "Unsupported shell {other:?} in linux_completions; expected one of bash, zsh, fish"
+        ),
+    }
+}
+
+/// Install the bundle's shell completion files into the shell-specific vendor
+/// directories under the `data_dir`.
+pub fn transfer_completion_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    for (shell, src) in settings.linux_completions() {
+        let dest_dir = data_dir.join(completions_dir(shell)?);
+        let src = common::expand_path(src);
+        let src = Path::new(&src);
+        let file_name = src
+            .file_name()
+            .with_context(|| format!("Completion file {src:?} has no file name"))?;
+        common::copy_file(src, &dest_dir.join(file_name))
+            .with_context(|| format!("Failed to copy {shell} completion file {src:?}"))?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_utils::{fixture_package, write_fixture_package};
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
 
+    #[test]
+    fn debug_symbols_sidecar() {
+        if Command::new("objcopy").arg("--version").output().is_err() {
+            return;
+        }
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n",
+        );
+        let mut info = crate::BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        info.target_dir = Some(tmp.path().join("target"));
+        let settings = Settings::builder(info)
+            .include_debug_symbols(true)
+            .build()
+            .unwrap();
+        let binary_path = settings.binary_path(PackageType::Deb);
+        std::fs::create_dir_all(binary_path.parent().unwrap()).unwrap();
+        std::fs::copy(std::env::current_exe().unwrap(), &binary_path).unwrap();
+
+        let debug_path = tmp.path().join("foo_0.1.0_amd64.debug");
+        let written = extract_debug_symbols(&settings, PackageType::Deb, &debug_path).unwrap();
+        assert_eq!(written, debug_path);
+        assert!(debug_path.metadata().unwrap().len() > 0);
+    }
+
+    #[test]
+    fn icons_are_installed_by_size() {
+        let tmp = tempdir().unwrap();
+        let icons_dir = tmp.path().join("icons");
+        std::fs::create_dir_all(&icons_dir).unwrap();
+        image::RgbaImage::new(64, 64)
+            .save(icons_dir.join("icon64.png"))
+            .unwrap();
+        image::RgbaImage::new(128, 128)
+            .save(icons_dir.join("icon128.png"))
+            .unwrap();
+        std::fs::write(icons_dir.join("icon.svg"), "<svg/>").unwrap();
+        write_fixture_package(
+            tmp.path(),
+            &format!(
+                "\n\
+                 [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n\
+                 icon = [\"{}/*\"]\n",
+                icons_dir.display().to_string().replace('\\', "/")
+            ),
+        );
+        let info = crate::BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+
+        let data_dir = tmp.path().join("data");
+        assert_eq!(icon_cache_update_script(&data_dir), None);
+        generate_icon_files(&settings, &data_dir).unwrap();
+        let hicolor = data_dir.join("usr/share/icons/hicolor");
+        assert!(hicolor.join("64x64/apps/foo.png").is_file());
+        assert!(hicolor.join("128x128/apps/foo.png").is_file());
+        assert!(hicolor.join("scalable/apps/foo.svg").is_file());
+        assert!(
+            icon_cache_update_script(&data_dir)
+                .unwrap()
+                .contains("gtk-update-icon-cache")
+        );
+    }
+
+    #[test]
+    fn desktop_categories_have_a_main_category() {
+        const MAIN_CATEGORIES: &[&str] = &[
+            "AudioVideo",
+            "Audio",
+            "Video",
+            "Development",
+            "Education",
+            "Game",
+            "Graphics",
+            "Network",
+            "Office",
+            "Science",
+            "Settings",
+            "System",
+            "Utility",
+        ];
+        assert_eq!(
+            desktop_categories(AppCategory::PuzzleGame),
+            "Game;LogicGame;"
+        );
+        assert_eq!(
+            desktop_categories(AppCategory::Music),
+            "AudioVideo;Audio;Music;"
+        );
+        for category in [
+            AppCategory::Business,
+            AppCategory::DeveloperTool,
+            AppCategory::Entertainment,
+            AppCategory::Finance,
+            AppCategory::RolePlayingGame,
+            AppCategory::Lifestyle,
+            AppCategory::Medical,
+            AppCategory::Weather,
+        ] {
+            let value = desktop_categories(category);
+            assert!(value.ends_with(';'), "{value}");
+            let main = value.split(';').next().unwrap();
+            assert!(MAIN_CATEGORIES.contains(&main), "{category:?}: {value}");
+        }
+    }
+
     #[test]
     fn test_tar_and_gzip_dir() {
         let temp_dir = tempdir().unwrap();
@@ -273,16 +542,25 @@ mod tests {
 
         assert_eq!(md5_str, "098f6bcd4621d373cade4e832627b4f6".to_string());
     }
-}
 
-/// Copy the bundle's resource files into an appropriate directory under the
-/// `data_dir`.
-pub fn transfer_resource_files(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
-    let resource_dir = data_dir.join("usr/lib").join(settings.binary_name());
+    #[test]
+    fn test_man_page_section() {
+        assert_eq!(man_page_section(Path::new("docs/myapp.1")).unwrap(), "1");
+        assert_eq!(man_page_section(Path::new("libfoo.3ssl")).unwrap(), "3");
+        assert!(man_page_section(Path::new("docs/myapp.md")).is_err());
+        assert!(man_page_section(Path::new("docs/myapp")).is_err());
+    }
 
-    for (src, dest) in settings.resources_paths(resource_dir.as_path()) {
-        common::copy_file(&src, &dest)
-            .with_context(|| format!("Failed to copy resource file {src:?}"))?;
+    #[test]
+    fn test_completions_dir() {
+        assert_eq!(
+            completions_dir("bash").unwrap(),
+            "usr/share/bash-completion/completions"
+        );
+        assert_eq!(
+            completions_dir("fish").unwrap(),
+            "usr/share/fish/vendor_completions.d"
+        );
+        assert!(completions_dir("powershell").is_err());
     }
-    Ok(())
 }
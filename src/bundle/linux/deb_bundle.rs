@@ -5,6 +5,7 @@
 //     control.tar.gz          # Contains files controlling the installation:
 //         control                  # Basic package metadata
 //         md5sums                  # Checksums for files in data.tar.gz below
+//         conffiles                # Configuration files to preserve (optional)
 //         postinst                 # Post-installation script (optional)
 //         prerm                    # Pre-uninstallation script (optional)
 //     data.tar.gz             # Contains files to be installed:
@@ -12,23 +13,33 @@
 //         usr/share/applications/foobar.desktop     # Desktop file (for apps)
 //         usr/share/icons/hicolor/...               # Icon files (for apps)
 //         usr/lib/foobar/...                        # Other resource files
+//         usr/share/doc/foobar/changelog.Debian.gz  # Package changelog
+//         usr/share/doc/foobar/copyright            # Copyright and license
+//     _gpgbuilder             # Signature added by dpkg-sig (optional)
 //
 // For cargo-bundle, we put bundle resource files under /usr/lib/package_name/,
 // and then generate the desktop file and control file from the bundle
-// metadata, as well as generating the md5sums file.  Currently we do not
-// generate postinst or prerm files.
+// metadata, as well as generating the md5sums file.  A postinst file is only
+// generated to refresh the icon cache when the package installs icons, and we
+// do not generate prerm files.
 
 use crate::bundle::{
     Settings, common,
     linux::common::{
-        create_file_with_data, generate_desktop_file, generate_icon_files, generate_md5sum,
-        tar_and_gzip_dir, total_dir_size,
+        create_file_with_data, extract_debug_symbols, generate_build_info, generate_desktop_file,
+        generate_icon_files, generate_man_pages, generate_md5sum, icon_cache_update_script,
+        package_name, tar_and_gzip_dir, total_dir_size, transfer_completion_files,
+        transfer_linux_files,
     },
 };
 use anyhow::Context;
+use libflate::gzip;
 
 use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use walkdir::WalkDir;
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
@@ -45,15 +56,19 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         settings.version_string(),
         arch
     );
-    let package_name = format!("{package_base_name}.deb");
+    let package_name = format!(
+        "{}.deb",
+        settings.artifact_name(arch, package_base_name.clone())
+    );
     common::print_bundling(&package_name)?;
-    let base_dir = settings.get_target_dir().join("bundle/deb");
+    let base_dir = settings.bundle_dir(crate::bundle::PackageType::Deb);
     let package_dir = base_dir.join(&package_base_name);
     if package_dir.exists() {
         std::fs::remove_dir_all(&package_dir)
             .with_context(|| format!("Failed to remove old {package_base_name}"))?;
     }
     let package_path = base_dir.join(package_name);
+    common::prepare_output_path(&package_path, settings.force())?;
 
     // Generate data files.
     let data_dir = package_dir.join("data");
@@ -67,14 +82,26 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     .with_context(|| "Failed to copy binary file")?;
     crate::bundle::linux::common::transfer_resource_files(settings, &data_dir)
         .with_context(|| "Failed to copy resource files")?;
+    transfer_linux_files(settings, &data_dir).with_context(|| "Failed to copy linux files")?;
+    generate_build_info(settings, &data_dir).with_context(|| "Failed to create build-info file")?;
+    generate_man_pages(settings, &data_dir).with_context(|| "Failed to create man pages")?;
+    transfer_completion_files(settings, &data_dir)
+        .with_context(|| "Failed to copy shell completion files")?;
     generate_icon_files(settings, &data_dir).with_context(|| "Failed to create icon files")?;
     generate_desktop_file(settings, &data_dir).with_context(|| "Failed to create desktop file")?;
+    generate_changelog(settings, &data_dir, common::source_date_epoch()?)
+        .with_context(|| "Failed to create changelog")?;
+    generate_copyright_file(settings, &data_dir)
+        .with_context(|| "Failed to create copyright file")?;
 
     // Generate control files.
     let control_dir = package_dir.join("control");
     generate_control_file(settings, arch, &control_dir, &data_dir)
         .with_context(|| "Failed to create control file")?;
     generate_md5sums(&control_dir, &data_dir).with_context(|| "Failed to create md5sums file")?;
+    generate_postinst(&control_dir, &data_dir).with_context(|| "Failed to create postinst file")?;
+    generate_conffiles(settings, &control_dir, &data_dir)
+        .with_context(|| "Failed to create conffiles file")?;
 
     // Generate `debian-binary` file; see
     // http://www.tldp.org/HOWTO/Debian-Binary-Package-Building-HOWTO/x60.html#AEN66
@@ -92,7 +119,128 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         &package_path,
     )
     .with_context(|| "Failed to create package archive")?;
-    Ok(vec![package_path])
+    sign_package(settings, &package_path)?;
+    let mut output_paths = vec![package_path];
+    if settings.include_debug_symbols() {
+        let debug_path = output_paths[0].with_extension("debug");
+        output_paths.push(extract_debug_symbols(
+            settings,
+            crate::bundle::PackageType::Deb,
+            &debug_path,
+        )?);
+    }
+    Ok(output_paths)
+}
+
+/// Adds a `_gpgbuilder` signature to the package with `dpkg-sig`, if a
+/// signing key is configured.
+fn sign_package(settings: &Settings, package_path: &Path) -> crate::Result<()> {
+    let Some(key) = settings.deb_sign_key() else {
+        return common::print_note("No deb_sign_key set, not signing the package");
+    };
+    let mut dpkg_sig = Command::new("dpkg-sig");
+    dpkg_sig
+        .arg("--sign")
+        .arg("builder")
+        .arg("-k")
+        .arg(key)
+        .arg(package_path);
+    common::run_command(&mut dpkg_sig, common::TOOL_TIMEOUT)
+        .with_context(|| "Failed to sign the package")?;
+    Ok(())
+}
+
+/// Install the bundle's `deb_changelog`, gzipped, where lintian expects the
+/// package changelog.  Without one, a changelog with a single entry for the
+/// current version is generated, signed by the package's first author and
+/// dated `mtime` if it is given.
+fn generate_changelog(
+    settings: &Settings,
+    data_dir: &Path,
+    mtime: Option<u64>,
+) -> crate::Result<()> {
+    let changelog = match settings.deb_changelog() {
+        Some(path) => common::read_file(&path)?,
+        None => {
+            // The trailer line names exactly one maintainer.
+            let maintainer = match settings.author_names().first() {
+                Some(author) => author.clone(),
+                None => format!("{} maintainers <unknown@localhost>", package_name(settings)),
+            };
+            let date = mtime
+                .and_then(|mtime| chrono::DateTime::from_timestamp(i64::try_from(mtime).ok()?, 0))
+                .unwrap_or_else(chrono::Utc::now);
+            format!(
+                "{} ({}) unstable; urgency=medium\n\n  * Release {}.\n\n -- {maintainer}  {}\n",
+                package_name(settings),
+                settings.version_string(),
+                settings.version_string(),
+                date.to_rfc2822()
+            )
+        }
+    };
+    let dest_path = data_dir
+        .join("usr/share/doc")
+        .join(package_name(settings))
+        .join("changelog.Debian.gz");
+    let mut gzip_encoder = gzip::Encoder::new(common::create_file(&dest_path)?)?;
+    gzip_encoder.write_all(changelog.as_bytes())?;
+    let mut dest_file = gzip_encoder.finish().into_result()?;
+    dest_file.flush()?;
+    Ok(())
+}
+
+/// Install the bundle's `deb_copyright_file`, or generate a copyright file in
+/// the machine-readable format, see
+/// <https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/>
+fn generate_copyright_file(settings: &Settings, data_dir: &Path) -> crate::Result<()> {
+    let dest_path = data_dir
+        .join("usr/share/doc")
+        .join(package_name(settings))
+        .join("copyright");
+    if let Some(path) = settings.deb_copyright_file() {
+        return common::copy_file(&path, &dest_path);
+    }
+    let mut file = common::create_file(&dest_path)?;
+    writeln!(
+        file,
+        "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/"
+    )?;
+    writeln!(file, "Upstream-Name: {}", package_name(settings))?;
+    if let Some(authors) = settings.authors_comma_separated() {
+        writeln!(file, "Upstream-Contact: {authors}")?;
+    }
+    if !settings.homepage_url().is_empty() {
+        writeln!(file, "Source: {}", settings.homepage_url())?;
+    }
+    writeln!(file)?;
+    writeln!(file, "Files: *")?;
+    let copyright = match settings.copyright_string() {
+        Some(copyright) => copyright.to_string(),
+        None => settings
+            .authors_comma_separated()
+            .unwrap_or_else(|| "Unknown".to_string()),
+    };
+    writeln!(file, "Copyright: {copyright}")?;
+    // The license is left out entirely rather than claimed to be custom when
+    // neither its name nor its text is known.
+    let license_name = settings.license_expression();
+    let license_content = settings.license_content();
+    if license_name.is_some() || license_content.is_some() {
+        writeln!(file, "License: {}", license_name.unwrap_or("custom"))?;
+    }
+    if let Some(license) = license_content {
+        for line in license.trim().lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                writeln!(file, " .")?;
+            } else {
+                writeln!(file, " {line}")?;
+            }
+        }
+    }
+    file.flush()?;
+    Ok(())
 }
 
 fn generate_control_file(
@@ -105,14 +253,11 @@ fn generate_control_file(
     // https://www.debian.org/doc/debian-policy/ch-controlfields.html
     let dest_path = control_dir.join("control");
     let mut file = common::create_file(&dest_path)?;
-    let bundle_name = settings.bundle_name();
-    writeln!(
-        &mut file,
-        "Package: {}",
-        str::replace(bundle_name.as_str(), " ", "-").to_ascii_lowercase()
-    )?;
+    writeln!(&mut file, "Package: {}", package_name(settings))?;
     writeln!(&mut file, "Version: {}", settings.version_string())?;
     writeln!(&mut file, "Architecture: {arch}")?;
+    writeln!(&mut file, "Section: {}", settings.deb_section())?;
+    writeln!(&mut file, "Priority: {}", settings.deb_priority())?;
     // deb Installed-Size is size in bytes / 1024
     // https://www.debian.org/doc/debian-policy/ch-controlfields.html#s-f-installed-size
     writeln!(
@@ -125,9 +270,26 @@ fn generate_control_file(
     if !settings.homepage_url().is_empty() {
         writeln!(&mut file, "Homepage: {}", settings.homepage_url())?;
     }
-    let dependencies = settings.debian_dependencies();
-    if !dependencies.is_empty() {
-        writeln!(&mut file, "Depends: {}", dependencies.join(", "))?;
+    let depends = if settings.deb_auto_depends() {
+        let binary = data_dir.join("usr/bin").join(settings.binary_name());
+        let detected = shlib_depends(&binary, &data_dir.with_file_name("shlibdeps"))
+            .with_context(|| "Failed to detect the binary's shared library dependencies")?;
+        merge_depends(settings.debian_dependencies(), detected)
+    } else {
+        settings.debian_dependencies().to_vec()
+    };
+    let relationships = [
+        ("Depends", depends.as_slice()),
+        ("Recommends", settings.deb_recommends()),
+        ("Suggests", settings.deb_suggests()),
+        ("Conflicts", settings.deb_conflicts()),
+        ("Provides", settings.deb_provides()),
+        ("Replaces", settings.deb_replaces()),
+    ];
+    for (field, packages) in relationships {
+        if !packages.is_empty() {
+            writeln!(&mut file, "{field}: {}", packages.join(", "))?;
+        }
     }
     let mut short_description = settings.short_description().trim();
     if short_description.is_empty() {
@@ -150,6 +312,104 @@ fn generate_control_file(
     Ok(())
 }
 
+/// Runs `dpkg-shlibdeps` on `binary`, returning the packages that provide the
+/// shared libraries it links against, with their minimum versions.  The tool
+/// only runs in a source package tree, so an empty `debian/control` is made
+/// for it in `work_dir`, which is removed afterwards.
+fn shlib_depends(binary: &Path, work_dir: &Path) -> crate::Result<Vec<String>> {
+    let binary = std::path::absolute(binary)?;
+    common::create_file(&work_dir.join("debian/control"))?;
+    let mut shlibdeps = Command::new("dpkg-shlibdeps");
+    shlibdeps
+        .current_dir(work_dir)
+        .arg("-O")
+        .arg(format!("-e{}", binary.display()));
+    let output = common::run_command(&mut shlibdeps, common::TOOL_TIMEOUT);
+    std::fs::remove_dir_all(work_dir)?;
+    Ok(parse_shlibs_depends(&String::from_utf8_lossy(
+        &output?.stdout,
+    )))
+}
+
+/// Parses the `shlibs:Depends` substitution variable printed by
+/// `dpkg-shlibdeps -O` into its comma separated dependencies.
+fn parse_shlibs_depends(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("shlibs:Depends="))
+        .flat_map(|depends| depends.split(','))
+        .map(str::trim)
+        .filter(|depend| !depend.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Appends the `detected` dependencies to the `configured` ones, skipping
+/// those on packages that `deb_depends` already names, so that a manually
+/// configured version constraint takes precedence.
+fn merge_depends(configured: &[String], detected: Vec<String>) -> Vec<String> {
+    fn package(depend: &str) -> &str {
+        depend.split([' ', '(', ':', '|']).next().unwrap_or(depend)
+    }
+    let mut depends = configured.to_vec();
+    for depend in detected {
+        if !depends
+            .iter()
+            .any(|existing| package(existing) == package(&depend))
+        {
+            depends.push(depend);
+        }
+    }
+    depends
+}
+
+/// Create a `postinst` script in the `control_dir` that refreshes the icon
+/// cache, if the package installs any icons.
+fn generate_postinst(control_dir: &Path, data_dir: &Path) -> crate::Result<()> {
+    let Some(icon_cache_update) = icon_cache_update_script(data_dir) else {
+        return Ok(());
+    };
+    let postinst_path = control_dir.join("postinst");
+    create_file_with_data(
+        &postinst_path,
+        &format!("#!/bin/sh\nset -e\n{icon_cache_update}"),
+    )?;
+
+    #[allow(unused_mut)]
+    let mut perms = std::fs::metadata(&postinst_path)?.permissions();
+    #[cfg(unix)]
+    perms.set_mode(0o755);
+    std::fs::set_permissions(&postinst_path, perms)?;
+    Ok(())
+}
+
+/// Create a `conffiles` file in the `control_dir` listing the bundle's
+/// `deb_conffiles`, warning about any that the package doesn't install.
+fn generate_conffiles(
+    settings: &Settings,
+    control_dir: &Path,
+    data_dir: &Path,
+) -> crate::Result<()> {
+    let conffiles = settings.deb_conffiles();
+    if conffiles.is_empty() {
+        return Ok(());
+    }
+    let mut file = common::create_file(&control_dir.join("conffiles"))?;
+    for conffile in conffiles {
+        if !conffile.starts_with('/') {
+            anyhow::bail!("Configuration file path {conffile:?} must be absolute");
+        }
+        if !data_dir.join(conffile.trim_start_matches('/')).is_file() {
+            common::print_warning(&format!(
+                "Configuration file {conffile} is not installed by the package"
+            ))?;
+        }
+        writeln!(file, "{conffile}")?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
 /// Create an `md5sums` file in the `control_dir` containing the MD5 checksums
 /// for each file within the `data_dir`.
 fn generate_md5sums(control_dir: &Path, data_dir: &Path) -> crate::Result<()> {
@@ -179,8 +439,234 @@ fn generate_md5sums(control_dir: &Path, data_dir: &Path) -> crate::Result<()> {
 fn create_archive(srcs: Vec<PathBuf>, dest: &Path) -> crate::Result<()> {
     let mut builder = ar::Builder::new(common::create_file(dest)?);
     for path in &srcs {
+        common::log_verbose(
+            "Archiving",
+            &format!("{} -> {}", path.display(), dest.display()),
+        )?;
         builder.append_path(path)?;
     }
     builder.into_inner()?.flush()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generate_changelog, generate_conffiles, generate_control_file, generate_copyright_file,
+        merge_depends, parse_shlibs_depends,
+    };
+    use crate::bundle::{PackageType, Settings, target_info::BundleTargetInfo};
+    use crate::test_utils::write_fixture_package;
+    use std::io::Read;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    fn load_settings(tmp: &std::path::Path, bundle_metadata: &str) -> Settings {
+        write_fixture_package(
+            tmp,
+            &format!(
+                "authors = [\"Jane Doe <jane@example.com>\", \"John Roe <john@example.com>\"]\n\
+                 \n\
+                 [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n{bundle_metadata}"
+            ),
+        );
+        let info = BundleTargetInfo::load(tmp, PackageType::Deb, None).unwrap();
+        Settings::builder(info).build().unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn prebuilt_binary() {
+        let tmp = tempfile::tempdir().unwrap();
+        load_settings(tmp.path(), "");
+        let binary = tmp.path().join("prebuilt/foo-linux");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, b"#!/bin/sh\n").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let builder = Settings::builder(info).prebuilt_binary(&binary);
+        let error = builder.clone().build().unwrap_err().to_string();
+        assert!(error.contains("not an executable file"), "{error}");
+
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let settings = builder.build().unwrap();
+        assert!(settings.no_build());
+        assert_eq!(settings.binary_path(PackageType::Deb), binary);
+        let output_paths = crate::bundle(&settings, PackageType::Deb).unwrap();
+        assert!(output_paths[0].is_file());
+        let package_dir = output_paths[0].with_extension("");
+        let bundled_binary = package_dir.join("data/usr/bin/foo");
+        assert_eq!(std::fs::read(bundled_binary).unwrap(), b"#!/bin/sh\n");
+    }
+
+    fn control_file(bundle_metadata: &str) -> String {
+        let tmp = tempfile::tempdir().unwrap();
+        let settings = load_settings(tmp.path(), bundle_metadata);
+        let data_dir = tmp.path().join("data");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        generate_control_file(&settings, "amd64", tmp.path(), &data_dir).unwrap();
+        std::fs::read_to_string(tmp.path().join("control")).unwrap()
+    }
+
+    #[test]
+    fn relationship_fields() {
+        let control = control_file("");
+        for field in [
+            "Depends:",
+            "Recommends:",
+            "Suggests:",
+            "Conflicts:",
+            "Provides:",
+            "Replaces:",
+        ] {
+            assert!(!control.contains(field), "{control}");
+        }
+
+        let control = control_file(
+            "deb_recommends = [\"fonts-noto\", \"xdg-utils\"]\n\
+             deb_conflicts = [\"foo-legacy\"]\n\
+             deb_provides = [\"foo-app\"]\n\
+             deb_replaces = [\"foo-legacy (<< 0.1)\"]\n",
+        );
+        assert!(
+            control.contains("\nRecommends: fonts-noto, xdg-utils\n"),
+            "{control}"
+        );
+        assert!(control.contains("\nConflicts: foo-legacy\n"));
+        assert!(control.contains("\nProvides: foo-app\n"));
+        assert!(control.contains("\nReplaces: foo-legacy (<< 0.1)\n"));
+        assert!(!control.contains("Suggests:"));
+        assert!(!control.contains("Depends:"));
+    }
+
+    #[test]
+    fn shlibs_depends() {
+        let output = "shlibs:Depends=libc6 (>= 2.34), libsdl2-2.0-0 (>= 2.0.12)\n";
+        assert_eq!(
+            parse_shlibs_depends(output),
+            ["libc6 (>= 2.34)", "libsdl2-2.0-0 (>= 2.0.12)"]
+        );
+        assert!(parse_shlibs_depends("shlibs:Depends=\n").is_empty());
+
+        let configured = vec![
+            "libsdl2-2.0-0 (>= 2.0.5)".to_string(),
+            "xdg-utils".to_string(),
+        ];
+        assert_eq!(
+            merge_depends(&configured, parse_shlibs_depends(output)),
+            ["libsdl2-2.0-0 (>= 2.0.5)", "xdg-utils", "libc6 (>= 2.34)"]
+        );
+    }
+
+    #[test]
+    fn conffiles() {
+        let tmp = tempfile::tempdir().unwrap();
+        let control_dir = tmp.path().join("control");
+        let data_dir = tmp.path().join("data");
+        std::fs::create_dir_all(data_dir.join("etc/foo")).unwrap();
+        std::fs::write(data_dir.join("etc/foo/foo.conf"), "key = value\n").unwrap();
+
+        let settings = load_settings(tmp.path(), "");
+        generate_conffiles(&settings, &control_dir, &data_dir).unwrap();
+        assert!(!control_dir.join("conffiles").exists());
+
+        let settings = load_settings(
+            tmp.path(),
+            "deb_conffiles = [\"/etc/foo/foo.conf\", \"/etc/foo/missing.conf\"]\n",
+        );
+        generate_conffiles(&settings, &control_dir, &data_dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(control_dir.join("conffiles")).unwrap(),
+            "/etc/foo/foo.conf\n/etc/foo/missing.conf\n"
+        );
+
+        let settings = load_settings(tmp.path(), "deb_conffiles = [\"etc/foo/foo.conf\"]\n");
+        assert!(generate_conffiles(&settings, &control_dir, &data_dir).is_err());
+    }
+
+    #[test]
+    fn changelog() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        let read_changelog = || {
+            let file = std::fs::File::open(data_dir.join("usr/share/doc/foo/changelog.Debian.gz"))
+                .unwrap();
+            let mut changelog = String::new();
+            libflate::gzip::Decoder::new(file)
+                .unwrap()
+                .read_to_string(&mut changelog)
+                .unwrap();
+            changelog
+        };
+
+        let settings = load_settings(tmp.path(), "");
+        generate_changelog(&settings, &data_dir, None).unwrap();
+        let changelog = read_changelog();
+        assert!(
+            changelog.starts_with(
+                "foo (0.1.0) unstable; urgency=medium\n\n  * Release 0.1.0.\n\n \
+                 -- Jane Doe <jane@example.com>  "
+            ),
+            "{changelog}"
+        );
+        assert!(changelog.ends_with(" +0000\n"), "{changelog}");
+
+        write_fixture_package(tmp.path(), "");
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+        generate_changelog(&settings, &data_dir, Some(1700000000)).unwrap();
+        assert!(
+            read_changelog().ends_with(
+                "\n -- foo maintainers <unknown@localhost>  Tue, 14 Nov 2023 22:13:20 +0000\n"
+            ),
+            "{}",
+            read_changelog()
+        );
+
+        std::fs::write(
+            tmp.path().join("CHANGELOG"),
+            "foo (0.1.0) stable; urgency=low\n",
+        )
+        .unwrap();
+        let settings = load_settings(tmp.path(), "deb_changelog = \"CHANGELOG\"\n");
+        generate_changelog(&settings, &data_dir, None).unwrap();
+        assert_eq!(read_changelog(), "foo (0.1.0) stable; urgency=low\n");
+    }
+
+    #[test]
+    fn copyright_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        let copyright_path = data_dir.join("usr/share/doc/foo/copyright");
+        let settings = load_settings(tmp.path(), "copyright = \"Copyright (c) 2024 Foo Inc.\"\n");
+        generate_copyright_file(&settings, &data_dir).unwrap();
+        let copyright = std::fs::read_to_string(&copyright_path).unwrap();
+        assert!(
+            copyright.starts_with(
+                "Format: https://www.debian.org/doc/packaging-manuals/copyright-format/1.0/\n\
+                 Upstream-Name: foo\n"
+            ),
+            "{copyright}"
+        );
+        assert!(copyright.contains("\n\nFiles: *\nCopyright: Copyright (c) 2024 Foo Inc.\n"));
+        assert!(
+            copyright.ends_with("\nCopyright: Copyright (c) 2024 Foo Inc.\n"),
+            "{copyright}"
+        );
+
+        std::fs::write(tmp.path().join("LICENSE"), "Some terms\n").unwrap();
+        generate_copyright_file(&settings, &data_dir).unwrap();
+        let copyright = std::fs::read_to_string(&copyright_path).unwrap();
+        assert!(
+            copyright.ends_with("\nLicense: custom\n Some terms\n"),
+            "{copyright}"
+        );
+
+        std::fs::write(tmp.path().join("copyright"), "Custom copyright\n").unwrap();
+        let settings = load_settings(tmp.path(), "deb_copyright_file = \"copyright\"\n");
+        generate_copyright_file(&settings, &data_dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&copyright_path).unwrap(),
+            "Custom copyright\n"
+        );
+    }
+}
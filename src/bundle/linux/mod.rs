@@ -1,4 +1,4 @@
 pub(crate) mod appimage_bundle;
-mod common;
+pub(crate) mod common;
 pub(crate) mod deb_bundle;
 pub(crate) mod rpm_bundle;
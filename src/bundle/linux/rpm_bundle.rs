@@ -1,6 +1,263 @@
-use crate::Settings;
-use std::path::PathBuf;
+// An RPM package is built by `rpmbuild` from a spec file.  We stage the files
+// to install in a directory laid out like the target filesystem:
+//
+// foobar-1.2.3-1.x86_64/
+//     foobar.spec             # Package metadata, scriptlets and file list
+//     buildroot/              # Files to be installed:
+//         usr/bin/foobar                            # Binary executable file
+//         usr/share/applications/foobar.desktop     # Desktop file (for apps)
+//         usr/share/icons/hicolor/...               # Icon files (for apps)
+//         usr/lib/foobar/...                        # Other resource files
+//
+// The spec's `%install` section just copies the staged tree into rpmbuild's
+// own build root, and `%files` lists every staged file, so `rpmbuild` never
+// has to compile anything.
 
-pub fn bundle_project(_settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    unimplemented!();
+use crate::bundle::{
+    Settings, common,
+    linux::common::{
+        extract_debug_symbols, generate_build_info, generate_desktop_file, generate_icon_files,
+        generate_man_pages, icon_cache_update_script, package_name, transfer_completion_files,
+        transfer_linux_files, transfer_resource_files,
+    },
+};
+use anyhow::Context;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let arch = match settings.binary_arch() {
+        "x86" => "i686",
+        "arm" => "armv7hl",
+        other => other,
+    };
+    let name = package_name(settings);
+    let version = rpm_version(&settings.version_string().to_string());
+    let package_base_name = format!("{name}-{version}-1.{arch}");
+    let package_name = format!(
+        "{}.rpm",
+        settings.artifact_name(arch, package_base_name.clone())
+    );
+    common::print_bundling(&package_name)?;
+    let base_dir = settings.bundle_dir(crate::bundle::PackageType::Rpm);
+    let package_dir = base_dir.join(&package_base_name);
+    if package_dir.exists() {
+        std::fs::remove_dir_all(&package_dir)
+            .with_context(|| format!("Failed to remove old {package_base_name}"))?;
+    }
+    let package_path = base_dir.join(&package_name);
+    common::prepare_output_path(&package_path, settings.force())?;
+
+    // Stage the files to install.
+    let data_dir = package_dir.join("buildroot");
+    let binary_dest = data_dir.join("usr/bin").join(settings.binary_name());
+    common::copy_file(
+        settings
+            .binary_path(crate::bundle::PackageType::Rpm)
+            .as_path(),
+        &binary_dest,
+    )
+    .with_context(|| "Failed to copy binary file")?;
+    transfer_resource_files(settings, &data_dir)
+        .with_context(|| "Failed to copy resource files")?;
+    transfer_linux_files(settings, &data_dir).with_context(|| "Failed to copy linux files")?;
+    generate_build_info(settings, &data_dir).with_context(|| "Failed to create build-info file")?;
+    generate_man_pages(settings, &data_dir).with_context(|| "Failed to create man pages")?;
+    transfer_completion_files(settings, &data_dir)
+        .with_context(|| "Failed to copy shell completion files")?;
+    generate_icon_files(settings, &data_dir).with_context(|| "Failed to create icon files")?;
+    generate_desktop_file(settings, &data_dir).with_context(|| "Failed to create desktop file")?;
+
+    // Generate the spec file and let rpmbuild assemble the package.
+    let spec_path = package_dir.join(format!("{name}.spec"));
+    let spec = generate_spec(settings, &name, &version, &data_dir)
+        .with_context(|| "Failed to generate spec file")?;
+    let mut spec_file = common::create_file(&spec_path)?;
+    spec_file.write_all(spec.as_bytes())?;
+    spec_file.flush()?;
+
+    let mut rpmbuild = Command::new("rpmbuild");
+    rpmbuild
+        .arg("-bb")
+        .arg("--target")
+        .arg(arch)
+        .arg("--define")
+        .arg(format!(
+            "_topdir {}",
+            package_dir.join("rpmbuild").display()
+        ))
+        .arg("--define")
+        .arg(format!("_rpmdir {}", base_dir.display()))
+        .arg("--define")
+        .arg(format!("_build_name_fmt {package_name}"))
+        .arg(&spec_path);
+    common::run_command(&mut rpmbuild, common::TOOL_TIMEOUT)
+        .with_context(|| "Failed to build the package")?;
+    sign_package(settings, &package_path)?;
+    let mut output_paths = vec![package_path];
+    if settings.include_debug_symbols() {
+        let debug_path = output_paths[0].with_extension("debug");
+        output_paths.push(extract_debug_symbols(
+            settings,
+            crate::bundle::PackageType::Rpm,
+            &debug_path,
+        )?);
+    }
+    Ok(output_paths)
+}
+
+/// Signs the package with `rpm --addsign`, if a signing key is configured.
+fn sign_package(settings: &Settings, package_path: &Path) -> crate::Result<()> {
+    let Some(key) = settings.rpm_sign_key() else {
+        return common::print_note("No rpm_sign_key set, not signing the package");
+    };
+    let mut rpm = Command::new("rpm");
+    rpm.arg("--addsign")
+        .arg("--define")
+        .arg(format!("_gpg_name {key}"))
+        .arg(package_path);
+    common::run_command(&mut rpm, common::TOOL_TIMEOUT)
+        .with_context(|| "Failed to sign the package")?;
+    Ok(())
+}
+
+/// RPM versions may not contain `-`, which separates the version from the
+/// release, so semver pre-release suffixes are joined with `~` instead (which
+/// also makes them sort before the final release).
+fn rpm_version(version: &str) -> String {
+    version.replace('-', "~")
+}
+
+/// Generates the contents of the spec file for the staged files in
+/// `data_dir`.
+fn generate_spec(
+    settings: &Settings,
+    name: &str,
+    version: &str,
+    data_dir: &Path,
+) -> crate::Result<String> {
+    // For more information about the format of this file, see
+    // https://rpm-software-management.github.io/rpm/manual/spec.html
+    let mut spec = String::new();
+    writeln!(spec, "%global debug_package %{{nil}}")?;
+    writeln!(spec, "Name: {name}")?;
+    writeln!(spec, "Version: {version}")?;
+    writeln!(spec, "Release: 1")?;
+    let mut short_description = settings.short_description().trim();
+    if short_description.is_empty() {
+        short_description = "(none)";
+    }
+    writeln!(spec, "Summary: {short_description}")?;
+    // Without an SPDX expression, a license text can only be referred to as
+    // a custom license.
+    let license = match settings.license_expression() {
+        Some(expression) => expression,
+        None if settings.license_content().is_some() => "LicenseRef-Custom",
+        None => "Unknown",
+    };
+    writeln!(spec, "License: {license}")?;
+    writeln!(spec, "Group: {}", settings.rpm_group())?;
+    if !settings.homepage_url().is_empty() {
+        writeln!(spec, "URL: {}", settings.homepage_url())?;
+    }
+    if let Some(authors) = settings.authors_comma_separated() {
+        writeln!(spec, "Packager: {authors}")?;
+    }
+    writeln!(spec)?;
+    writeln!(spec, "%description")?;
+    let mut long_description = settings.long_description().unwrap_or("").trim();
+    if long_description.is_empty() {
+        long_description = short_description;
+    }
+    writeln!(spec, "{long_description}")?;
+    writeln!(spec)?;
+    writeln!(spec, "%install")?;
+    writeln!(spec, "mkdir -p %{{buildroot}}")?;
+    writeln!(spec, "cp -a \"{}/.\" %{{buildroot}}/", data_dir.display())?;
+    writeln!(spec)?;
+    let scriptlets = [
+        ("pre", settings.rpm_pre()),
+        ("post", settings.rpm_post()),
+        ("preun", settings.rpm_preun()),
+        ("postun", settings.rpm_postun()),
+    ];
+    for (section, script) in scriptlets {
+        let mut contents = match script {
+            Some(script) => common::read_file(Path::new(&script))
+                .with_context(|| format!("Failed to read %{section} scriptlet"))?,
+            None => String::new(),
+        };
+        if section == "post"
+            && let Some(icon_cache_update) = icon_cache_update_script(data_dir)
+        {
+            if !contents.trim().is_empty() {
+                contents = format!("{}\n", contents.trim_end());
+            }
+            contents.push_str(icon_cache_update);
+        }
+        if !contents.trim().is_empty() {
+            writeln!(spec, "%{section}")?;
+            writeln!(spec, "{}", contents.trim_end())?;
+            writeln!(spec)?;
+        }
+    }
+    writeln!(spec, "%files")?;
+    for entry in WalkDir::new(data_dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let rel_path = entry.path().strip_prefix(data_dir).unwrap();
+        writeln!(spec, "\"/{}\"", rel_path.display())?;
+    }
+    Ok(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate_spec;
+    use crate::bundle::{PackageType, Settings, target_info::BundleTargetInfo};
+    use crate::test_utils::write_fixture_package;
+
+    fn generate(tmp: &std::path::Path, package_fields: &str, bundle_metadata: &str) -> String {
+        write_fixture_package(
+            tmp,
+            &format!(
+                "{package_fields}\
+                 \n\
+                 [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n{bundle_metadata}"
+            ),
+        );
+        let info = BundleTargetInfo::load(tmp, PackageType::Rpm, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+        let data_dir = tmp.join("buildroot");
+        std::fs::create_dir_all(&data_dir).unwrap();
+        generate_spec(&settings, "foo", "0.1.0", &data_dir).unwrap()
+    }
+
+    #[test]
+    fn license_and_group() {
+        let tmp = tempfile::tempdir().unwrap();
+        let spec = generate(
+            tmp.path(),
+            "license = \"MIT OR Apache-2.0\"\n",
+            "category = \"Puzzle Game\"\n",
+        );
+        assert!(spec.contains("License: MIT OR Apache-2.0\n"), "{spec}");
+        assert!(spec.contains("Group: Amusements/Games\n"), "{spec}");
+
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("LICENSE"), "All rights reserved.").unwrap();
+        let spec = generate(tmp.path(), "", "rpm_group = \"Applications/Text\"\n");
+        assert!(spec.contains("License: LicenseRef-Custom\n"), "{spec}");
+        assert!(spec.contains("Group: Applications/Text\n"), "{spec}");
+
+        let tmp = tempfile::tempdir().unwrap();
+        let spec = generate(tmp.path(), "", "");
+        assert!(spec.contains("License: Unknown\n"), "{spec}");
+        assert!(spec.contains("Group: Unspecified\n"), "{spec}");
+    }
 }
@@ -0,0 +1,209 @@
+// A manifest lists the files that were actually written into each bundle, so
+// that it can be committed and diffed across releases.  It is read back from
+// the finished bundles rather than derived from the settings, so that
+// excluded, renamed and generated files (such as converted icons or desktop
+// files) show up as they were bundled:
+//
+// deb/usr/bin/foobar                          1234567
+// deb/usr/share/applications/foobar.desktop   187
+// osx/Contents/Info.plist                     1024
+// osx/Contents/MacOS/foobar                   1234567
+//
+// Each line holds a path, prefixed by the short name of the bundle's format,
+// and the file's size, separated by a tab.  Directory bundles are walked, and
+// the contents of zip, tar.gz and deb archives are listed.  Bundles whose
+// contents can't be read this way, such as rpm packages or MSI installers,
+// are listed as a single file.
+
+use crate::bundle::PackageType;
+use anyhow::Context;
+use libflate::gzip;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Returns the files in `bundles`, each paired with its format, as pairs of
+/// a path and the file size, sorted by path.
+pub fn manifest_entries(bundles: &[(PackageType, PathBuf)]) -> crate::Result<Vec<(String, u64)>> {
+    let mut entries = Vec::new();
+    for (package_type, path) in bundles {
+        let files = bundle_files(*package_type, path)
+            .with_context(|| format!("Failed to read {path:?}"))?;
+        for (file, size) in files {
+            entries.push((format!("{}/{file}", package_type.short_name()), size));
+        }
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Returns the files in the bundle at `path` with their sizes.  The
+/// top-level directory of zips and tarballs, which is named after the
+/// version, is left out so that the paths stay the same across releases.
+fn bundle_files(package_type: PackageType, path: &Path) -> crate::Result<Vec<(String, u64)>> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let files = if path.is_dir() {
+        dir_files(path)?
+    } else if name.ends_with(".zip") || name.ends_with(".ipa") {
+        zip_files(path)?
+    } else if name.ends_with(".tar.gz") {
+        tar_files(gzip::Decoder::new(File::open(path)?)?)?
+    } else if name.ends_with(".deb") {
+        deb_files(path)?
+    } else {
+        vec![(name.into_owned(), std::fs::metadata(path)?.len())]
+    };
+    if !matches!(package_type, PackageType::WindowsZip | PackageType::TarGz) {
+        return Ok(files);
+    }
+    Ok(files
+        .into_iter()
+        .map(|(file, size)| match file.split_once('/') {
+            Some((_, file)) => (file.to_string(), size),
+            None => (file, size),
+        })
+        .collect())
+}
+
+/// Lists the files below `dir`, relative to it.
+fn dir_files(dir: &Path) -> crate::Result<Vec<(String, u64)>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let path = entry.path().strip_prefix(dir).unwrap();
+            // Manifest paths always use forward slashes, so that they diff
+            // cleanly across platforms.
+            let path = path.to_string_lossy().replace('\\', "/");
+            files.push((path, entry.metadata()?.len()));
+        }
+    }
+    Ok(files)
+}
+
+/// Lists the files in the zip archive at `path`.
+fn zip_files(path: &Path) -> crate::Result<Vec<(String, u64)>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut files = Vec::new();
+    for index in 0..archive.len() {
+        let file = archive.by_index(index)?;
+        if file.is_file() {
+            files.push((file.name()?.to_string(), file.size()));
+        }
+    }
+    Ok(files)
+}
+
+/// Lists the files in the tar archive read from `reader`.
+fn tar_files(reader: impl Read) -> crate::Result<Vec<(String, u64)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let path = path.strip_prefix("./").unwrap_or(&path).to_string();
+            files.push((path, entry.size()));
+        }
+    }
+    Ok(files)
+}
+
+/// Lists the files that the deb package at `path` installs, i.e. those in
+/// its `data.tar.gz`.
+fn deb_files(path: &Path) -> crate::Result<Vec<(String, u64)>> {
+    let mut archive = ar::Archive::new(File::open(path)?);
+    while let Some(entry) = archive.next_entry() {
+        let entry = entry?;
+        if entry.header().identifier() == b"data.tar.gz" {
+            return tar_files(gzip::Decoder::new(entry)?);
+        }
+    }
+    anyhow::bail!("No data.tar.gz in {path:?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::manifest_entries;
+    use crate::bundle::PackageType;
+    use libflate::gzip;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(gzip::Encoder::new(Vec::new()).unwrap());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+        builder
+            .into_inner()
+            .unwrap()
+            .finish()
+            .into_result()
+            .unwrap()
+    }
+
+    #[test]
+    fn bundle_contents() {
+        let tmp = tempfile::tempdir().unwrap();
+        let app = tmp.path().join("Foo.app");
+        std::fs::create_dir_all(app.join("Contents/MacOS")).unwrap();
+        std::fs::write(app.join("Contents/Info.plist"), "plist").unwrap();
+        std::fs::write(app.join("Contents/MacOS/foo"), "binary").unwrap();
+
+        let zip_path = tmp.path().join("foo-0.1.0-x86_64.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        zip.start_file("foo-0.1.0-x86_64/foo.exe", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"MZ").unwrap();
+        zip.finish().unwrap();
+
+        let deb_path = tmp.path().join("foo_0.1.0_amd64.deb");
+        let data = tar_gz(&[
+            ("usr/bin/foo", b"binary"),
+            ("./usr/share/doc/foo/copyright", b"c"),
+        ]);
+        let mut deb = ar::Builder::new(std::fs::File::create(&deb_path).unwrap());
+        deb.append(
+            &ar::Header::new(b"debian-binary".to_vec(), 4),
+            &b"2.0\n"[..],
+        )
+        .unwrap();
+        deb.append(
+            &ar::Header::new(b"data.tar.gz".to_vec(), data.len() as u64),
+            data.as_slice(),
+        )
+        .unwrap();
+        drop(deb);
+
+        let rpm_path = tmp.path().join("foo-0.1.0-1.x86_64.rpm");
+        std::fs::write(&rpm_path, "rpm").unwrap();
+
+        let entries = manifest_entries(&[
+            (PackageType::OsxBundle, app),
+            (PackageType::WindowsZip, zip_path),
+            (PackageType::Deb, deb_path),
+            (PackageType::Rpm, rpm_path),
+        ])
+        .unwrap();
+        let entries: Vec<(&str, u64)> = entries
+            .iter()
+            .map(|(path, size)| (path.as_str(), *size))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("deb/usr/bin/foo", 6),
+                ("deb/usr/share/doc/foo/copyright", 1),
+                ("osx/Contents/Info.plist", 5),
+                ("osx/Contents/MacOS/foo", 6),
+                ("rpm/foo-0.1.0-1.x86_64.rpm", 3),
+                ("zip/foo.exe", 2),
+            ]
+        );
+    }
+}
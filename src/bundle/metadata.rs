@@ -1,34 +1,201 @@
 use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
 
 use crate::bundle::category::AppCategory;
+use crate::bundle::settings::{PackageType, ResourcePaths};
+
+/// The string-valued settings that can be overridden with `--config`.
+const STRING_CONFIG_KEYS: &[&str] = &[
+    "name",
+    "identifier",
+    "version",
+    "copyright",
+    "category",
+    "short_description",
+    "long_description",
+    "before_build_command",
+    "filename_template",
+    "linux_exec_args",
+    "appimage_compression",
+    "deb_sign_key",
+    "deb_changelog",
+    "deb_copyright_file",
+    "deb_section",
+    "deb_priority",
+    "rpm_sign_key",
+    "rpm_group",
+    "rpm_pre",
+    "rpm_post",
+    "rpm_preun",
+    "rpm_postun",
+    "msi_start_menu_folder",
+    "msi_license_rtf",
+    "ios_provisioning_profile",
+    "ios_signing_identity",
+    "ios_minimum_os_version",
+    "osx_minimum_system_version",
+    "osx_display_name",
+    "osx_signing_identity",
+    "osx_notarize_keychain_profile",
+];
 
-#[derive(Clone, Debug, Default, serde::Deserialize)]
+/// The boolean settings that can be overridden with `--config`.
+const BOOL_CONFIG_KEYS: &[&str] = &[
+    "emit_checksums",
+    "require_clean_tree",
+    "embed_build_info",
+    "linux_use_terminal",
+    "appimage_bundle_libs",
+    "appimage_no_integration",
+    "deb_auto_depends",
+    "msi_create_desktop_shortcut",
+    "osx_bundle_dylibs",
+    "osx_high_resolution_capable",
+    "osx_requires_aqua_system_appearance",
+    "osx_adhoc_sign",
+];
+
+#[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize)]
 pub struct BundleSettings {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub identifier: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub icon: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub resources_mapping: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub copyright: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub category: Option<AppCategory>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub short_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub long_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_build_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_env: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emit_checksums: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_clean_tree: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embed_build_info: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bundle_size: Option<u64>,
     // OS-specific settings:
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub linux_mime_types: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub linux_exec_args: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub linux_use_terminal: Option<bool>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub linux_files: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linux_man_pages: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub linux_completions: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appimage_bundle_libs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appimage_compression: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appimage_no_integration: Option<bool>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub deb_depends: Vec<String>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deb_recommends: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deb_suggests: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deb_conflicts: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deb_provides: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deb_replaces: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deb_conffiles: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deb_sign_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deb_changelog: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deb_copyright_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deb_section: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deb_priority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deb_auto_depends: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm_sign_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm_pre: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm_post: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm_preun: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm_postun: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msi_create_desktop_shortcut: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msi_start_menu_folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msi_license_rtf: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ios_provisioning_profile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ios_signing_identity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ios_minimum_os_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ios_device_family: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ios_supported_orientations: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub osx_frameworks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub osx_plugins: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub osx_minimum_system_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_bundle_dylibs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub osx_additional_binaries: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_high_resolution_capable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_requires_aqua_system_appearance: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub osx_url_schemes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub osx_info_plist_exts: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_info_plist_keys: Option<HashMap<String, toml::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_localizations: Option<HashMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_localized_display_names: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_signing_identity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_adhoc_sign: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub osx_notarize_keychain_profile: Option<String>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub targets: HashMap<String, BundleSettings>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
@@ -38,6 +205,106 @@ pub struct BundleSettings {
 }
 
 impl BundleSettings {
+    /// Loads bundle settings from a standalone file.  Files with a `.json`
+    /// extension are parsed as JSON, anything else as TOML.  The top-level
+    /// keys are the same as those of `[package.metadata.bundle]`.
+    pub fn from_file(path: &Path) -> crate::Result<Self> {
+        let contents = crate::bundle::common::read_file(path)?;
+        let settings = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Failed to parse bundle metadata file {path:?}"))?
+        } else {
+            toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse bundle metadata file {path:?}"))?
+        };
+        Ok(settings)
+    }
+
+    /// Parses `--config KEY=VALUE` overrides into bundle settings.  Only the
+    /// scalar settings listed in `STRING_CONFIG_KEYS` and `BOOL_CONFIG_KEYS`
+    /// can be overridden.
+    pub fn from_overrides(overrides: &[String]) -> crate::Result<Self> {
+        let mut table = toml::Table::new();
+        for entry in overrides {
+            let Some((key, value)) = entry.split_once('=') else {
+                anyhow::bail!("Invalid --config value {entry:?}, expected KEY=VALUE");
+            };
+            let key = key.trim();
+            let value = if STRING_CONFIG_KEYS.contains(&key) {
+                toml::Value::String(value.to_string())
+            } else if BOOL_CONFIG_KEYS.contains(&key) {
+                let value = value
+                    .parse()
+                    .with_context(|| format!("--config {key} must be `true` or `false`"))?;
+                toml::Value::Boolean(value)
+            } else {
+                anyhow::bail!(
+                    "Unsupported --config key {key:?}, expected one of: {}",
+                    [STRING_CONFIG_KEYS, BOOL_CONFIG_KEYS].concat().join(", ")
+                );
+            };
+            table.insert(key.to_string(), value);
+        }
+        toml::Value::Table(table)
+            .try_into()
+            .with_context(|| "Invalid --config value")
+    }
+
+    /// Checks the settings for bundling as `package_type` for problems that
+    /// would otherwise only surface deep inside a bundler, and fails with a
+    /// report of all of them, each with a suggested fix.
+    pub fn validate(&self, package_type: PackageType) -> crate::Result<()> {
+        let mut problems = Vec::new();
+        if matches!(
+            package_type,
+            PackageType::OsxBundle | PackageType::IosBundle | PackageType::IosIpa
+        ) && self.identifier.is_none()
+        {
+            problems.push(format!(
+                "`identifier` is not set, but {package_type} bundles need one; add e.g. \
+                 `identifier = \"com.example.app\"` to `[package.metadata.bundle]`"
+            ));
+        }
+        // Entries matching no files are only warned about, see
+        // `Settings::check_patterns_match`.
+        for icon in &self.icon {
+            let paths = ResourcePaths::new(std::slice::from_ref(icon), false);
+            if let Some(error) = paths.filter_map(Result::err).next() {
+                problems.push(format!(
+                    "icon {icon:?} can't be used ({error}); point it at image files instead"
+                ));
+            }
+        }
+        if package_type == PackageType::Deb
+            && let Some(section) = &self.deb_section
+            && !is_debian_section(section)
+        {
+            let suggestion = match self.category {
+                Some(category) => format!(
+                    "use {:?}, the section matching the `{category:?}` category",
+                    category.debian_section()
+                ),
+                None => "use e.g. \"utils\" or \"misc\"".to_string(),
+            };
+            problems.push(format!(
+                "`deb_section` {section:?} is not a Debian archive section; {suggestion}"
+            ));
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let mut report = format!(
+            "Found {} problem{} with the bundle settings:",
+            problems.len(),
+            if problems.len() == 1 { "" } else { "s" }
+        );
+        for problem in problems {
+            report.push_str("\n  - ");
+            report.push_str(&problem);
+        }
+        anyhow::bail!(report)
+    }
+
     pub fn merge(self, other: BundleSettings) -> Self {
         Self {
             name: if self.name.is_empty() {
@@ -48,9 +315,9 @@ impl BundleSettings {
             targets: self.targets.into_iter().chain(other.targets).collect(),
             identifier: self.identifier.or(other.identifier),
             icon: if self.icon.is_empty() {
-                self.icon
-            } else {
                 other.icon
+            } else {
+                self.icon
             },
             version: self.version.or(other.version),
             resources_mapping: if self.resources_mapping.is_empty() {
@@ -62,6 +329,17 @@ impl BundleSettings {
             category: self.category.or(other.category),
             short_description: self.short_description.or(other.short_description),
             long_description: self.long_description.or(other.long_description),
+            before_build_command: self.before_build_command.or(other.before_build_command),
+            // Variables are overridden one by one, rather than all at once.
+            build_env: match (self.build_env, other.build_env) {
+                (Some(env), Some(other)) => Some(other.into_iter().chain(env).collect()),
+                (env, other) => env.or(other),
+            },
+            filename_template: self.filename_template.or(other.filename_template),
+            emit_checksums: self.emit_checksums.or(other.emit_checksums),
+            max_bundle_size: self.max_bundle_size.or(other.max_bundle_size),
+            require_clean_tree: self.require_clean_tree.or(other.require_clean_tree),
+            embed_build_info: self.embed_build_info.or(other.embed_build_info),
             linux_mime_types: if self.linux_mime_types.is_empty() {
                 other.linux_mime_types
             } else {
@@ -69,11 +347,87 @@ impl BundleSettings {
             },
             linux_exec_args: self.linux_exec_args.or(other.linux_exec_args),
             linux_use_terminal: self.linux_use_terminal.or(other.linux_use_terminal),
+            linux_files: if self.linux_files.is_empty() {
+                other.linux_files
+            } else {
+                self.linux_files
+            },
+            linux_man_pages: if self.linux_man_pages.is_empty() {
+                other.linux_man_pages
+            } else {
+                self.linux_man_pages
+            },
+            linux_completions: if self.linux_completions.is_empty() {
+                other.linux_completions
+            } else {
+                self.linux_completions
+            },
+            appimage_bundle_libs: self.appimage_bundle_libs.or(other.appimage_bundle_libs),
+            appimage_compression: self.appimage_compression.or(other.appimage_compression),
+            appimage_no_integration: self
+                .appimage_no_integration
+                .or(other.appimage_no_integration),
             deb_depends: if self.deb_depends.is_empty() {
                 other.deb_depends
             } else {
                 self.deb_depends
             },
+            deb_recommends: if self.deb_recommends.is_empty() {
+                other.deb_recommends
+            } else {
+                self.deb_recommends
+            },
+            deb_suggests: if self.deb_suggests.is_empty() {
+                other.deb_suggests
+            } else {
+                self.deb_suggests
+            },
+            deb_conflicts: if self.deb_conflicts.is_empty() {
+                other.deb_conflicts
+            } else {
+                self.deb_conflicts
+            },
+            deb_provides: if self.deb_provides.is_empty() {
+                other.deb_provides
+            } else {
+                self.deb_provides
+            },
+            deb_replaces: if self.deb_replaces.is_empty() {
+                other.deb_replaces
+            } else {
+                self.deb_replaces
+            },
+            deb_conffiles: if self.deb_conffiles.is_empty() {
+                other.deb_conffiles
+            } else {
+                self.deb_conffiles
+            },
+            deb_sign_key: self.deb_sign_key.or(other.deb_sign_key),
+            deb_changelog: self.deb_changelog.or(other.deb_changelog),
+            deb_copyright_file: self.deb_copyright_file.or(other.deb_copyright_file),
+            deb_section: self.deb_section.or(other.deb_section),
+            deb_priority: self.deb_priority.or(other.deb_priority),
+            deb_auto_depends: self.deb_auto_depends.or(other.deb_auto_depends),
+            rpm_sign_key: self.rpm_sign_key.or(other.rpm_sign_key),
+            rpm_group: self.rpm_group.or(other.rpm_group),
+            rpm_pre: self.rpm_pre.or(other.rpm_pre),
+            rpm_post: self.rpm_post.or(other.rpm_post),
+            rpm_preun: self.rpm_preun.or(other.rpm_preun),
+            rpm_postun: self.rpm_postun.or(other.rpm_postun),
+            msi_create_desktop_shortcut: self
+                .msi_create_desktop_shortcut
+                .or(other.msi_create_desktop_shortcut),
+            msi_start_menu_folder: self.msi_start_menu_folder.or(other.msi_start_menu_folder),
+            msi_license_rtf: self.msi_license_rtf.or(other.msi_license_rtf),
+            ios_provisioning_profile: self
+                .ios_provisioning_profile
+                .or(other.ios_provisioning_profile),
+            ios_signing_identity: self.ios_signing_identity.or(other.ios_signing_identity),
+            ios_minimum_os_version: self.ios_minimum_os_version.or(other.ios_minimum_os_version),
+            ios_device_family: self.ios_device_family.or(other.ios_device_family),
+            ios_supported_orientations: self
+                .ios_supported_orientations
+                .or(other.ios_supported_orientations),
             osx_frameworks: if self.osx_frameworks.is_empty() {
                 other.osx_frameworks
             } else {
@@ -83,10 +437,106 @@ impl BundleSettings {
             osx_minimum_system_version: self
                 .osx_minimum_system_version
                 .or(other.osx_minimum_system_version),
+            osx_bundle_dylibs: self.osx_bundle_dylibs.or(other.osx_bundle_dylibs),
+            osx_additional_binaries: if self.osx_additional_binaries.is_empty() {
+                other.osx_additional_binaries
+            } else {
+                self.osx_additional_binaries
+            },
+            osx_high_resolution_capable: self
+                .osx_high_resolution_capable
+                .or(other.osx_high_resolution_capable),
+            osx_requires_aqua_system_appearance: self
+                .osx_requires_aqua_system_appearance
+                .or(other.osx_requires_aqua_system_appearance),
             osx_url_schemes: self.osx_url_schemes.or(other.osx_url_schemes),
             osx_info_plist_exts: self.osx_info_plist_exts.or(other.osx_info_plist_exts),
+            osx_info_plist_keys: self.osx_info_plist_keys.or(other.osx_info_plist_keys),
+            osx_localizations: self.osx_localizations.or(other.osx_localizations),
+            osx_display_name: self.osx_display_name.or(other.osx_display_name),
+            osx_localized_display_names: self
+                .osx_localized_display_names
+                .or(other.osx_localized_display_names),
+            osx_signing_identity: self.osx_signing_identity.or(other.osx_signing_identity),
+            osx_adhoc_sign: self.osx_adhoc_sign.or(other.osx_adhoc_sign),
+            osx_notarize_keychain_profile: self
+                .osx_notarize_keychain_profile
+                .or(other.osx_notarize_keychain_profile),
             bin: self.bin.into_iter().chain(other.bin).collect(),
             example: self.example.into_iter().chain(other.example).collect(),
         }
     }
 }
+
+/// The sections of the Debian archive.  See
+/// <https://www.debian.org/doc/debian-policy/ch-archive.html#sections>
+const DEBIAN_SECTIONS: &[&str] = &[
+    "admin",
+    "cli-mono",
+    "comm",
+    "database",
+    "debug",
+    "devel",
+    "doc",
+    "editors",
+    "education",
+    "electronics",
+    "embedded",
+    "fonts",
+    "games",
+    "gnome",
+    "gnu-r",
+    "gnustep",
+    "graphics",
+    "hamradio",
+    "haskell",
+    "httpd",
+    "interpreters",
+    "introspection",
+    "java",
+    "javascript",
+    "kde",
+    "kernel",
+    "libdevel",
+    "libs",
+    "lisp",
+    "localization",
+    "mail",
+    "math",
+    "metapackages",
+    "misc",
+    "net",
+    "news",
+    "ocaml",
+    "oldlibs",
+    "otherosfs",
+    "perl",
+    "php",
+    "python",
+    "ruby",
+    "rust",
+    "science",
+    "shells",
+    "sound",
+    "tasks",
+    "tex",
+    "text",
+    "utils",
+    "vcs",
+    "video",
+    "web",
+    "x11",
+    "xfce",
+    "zope",
+];
+
+/// Returns true if `section` is a Debian archive section, optionally prefixed
+/// with its archive area, e.g. `contrib/games`.
+fn is_debian_section(section: &str) -> bool {
+    let section = match section.split_once('/') {
+        Some(("contrib" | "non-free" | "non-free-firmware", section)) => section,
+        Some(_) => return false,
+        None => section,
+    };
+    DEBIAN_SECTIONS.contains(&section)
+}
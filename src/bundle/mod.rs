@@ -1,13 +1,26 @@
+mod archive;
 pub mod category;
 mod common;
 mod ios_bundle;
+mod ios_ipa;
 mod linux;
+mod manifest;
 pub mod metadata;
 mod msi_bundle;
 mod osx_bundle;
+mod plist;
 mod settings;
 pub mod target_info;
+mod watch;
+mod windows_zip;
 mod wxsmsi_bundle;
 
-pub use self::common::{print_error, print_finished};
-pub use self::settings::{BuildArtifact, PackageType, Settings};
+pub use self::common::{
+    log_verbose, print_error, print_finished, print_warning, remove_bundle_dir,
+    reveal_in_file_manager, set_strict, set_verbosity, strict_warnings, verbosity, write_checksum,
+};
+pub use self::manifest::manifest_entries;
+pub use self::settings::{
+    ARCHES, BuildArtifact, PackageType, Settings, SettingsBuilder, UNIVERSAL_TARGETS,
+};
+pub use self::watch::{Snapshot, print_watch_status, wait_for_changes};
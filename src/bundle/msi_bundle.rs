@@ -1,5 +1,5 @@
 use super::common;
-use super::settings::Settings;
+use super::settings::{MsiPlatform, Settings};
 use anyhow::Context;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
@@ -23,6 +23,9 @@ const FILE_ATTR_VITAL: u16 = 0x200;
 // The name of the installer package's sole Feature:
 const MAIN_FEATURE_NAME: &str = "MainFeature";
 
+// Component table attribute indicating that a component is 64-bit:
+const COMPONENT_ATTR_64BIT: i32 = 0x100;
+
 // A v4 UUID that was generated specifically for cargo-bundle, to be used as a
 // namespace for generating v5 UUIDs from bundle identifier strings.
 const UUID_NAMESPACE: [u8; 16] = [
@@ -67,36 +70,41 @@ struct CabinetInfo {
 }
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    common::print_warning("MSI bundle support is still experimental.")?;
+    common::print_note("MSI bundle support is still experimental.")?;
+    let platform = settings.msi_platform()?;
 
-    let msi_name = format!("{}.msi", settings.bundle_name());
+    let msi_name = format!(
+        "{}.msi",
+        settings.artifact_name(settings.binary_arch(), settings.bundle_name())
+    );
     common::print_bundling(&msi_name)?;
-    let base_dir = settings.get_target_dir().join("bundle/msi");
+    let base_dir = settings.bundle_dir(crate::bundle::PackageType::WindowsMsi);
     let msi_path = base_dir.join(&msi_name);
+    common::prepare_output_path(&msi_path, settings.force())?;
     let mut package =
         new_empty_package(&msi_path).with_context(|| "Failed to initialize MSI package")?;
 
     // Generate package metadata:
     let guid = generate_package_guid(settings);
-    set_summary_info(&mut package, guid, settings);
+    set_summary_info(&mut package, guid, settings, platform);
     create_property_table(&mut package, guid, settings)
         .with_context(|| "Failed to generate Property table")?;
 
     // Copy resource files into package:
     let mut resources = collect_resource_info(settings)
         .with_context(|| "Failed to collect resource file information")?;
-    let directories = collect_directory_info(settings, &mut resources)
+    let directories = collect_directory_info(settings, platform, &mut resources)
         .with_context(|| "Failed to collect resource directory information")?;
     let cabinets = divide_resources_into_cabinets(resources);
     generate_resource_cabinets(&mut package, &cabinets)
         .with_context(|| "Failed to generate resource cabinets")?;
 
     // Set up installer database tables:
-    create_directory_table(&mut package, &directories)
+    create_directory_table(&mut package, platform, &directories)
         .with_context(|| "Failed to generate Directory table")?;
     create_feature_table(&mut package, settings)
         .with_context(|| "Failed to generate Feature table")?;
-    create_component_table(&mut package, guid, &directories)
+    create_component_table(&mut package, guid, platform, &directories)
         .with_context(|| "Failed to generate Component table")?;
     create_feature_components_table(&mut package, &directories)
         .with_context(|| "Failed to generate FeatureComponents table")?;
@@ -133,9 +141,13 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         let mut stream = package.write_stream(&stream_name)?;
         create_app_icon(&mut stream, settings)?;
     }
-    package.insert_rows(
-        msi::Insert::into("Icon").row(vec![msi::Value::Str(icon_name), msi::Value::from("Name")]),
-    )?;
+    package.insert_rows(msi::Insert::into("Icon").row(vec![
+        msi::Value::Str(icon_name.clone()),
+        msi::Value::from("Name"),
+    ]))?;
+
+    create_shortcuts(&mut package, guid, settings, &cabinets, &icon_name)
+        .with_context(|| "Failed to generate Shortcut table")?;
 
     package.flush()?;
     Ok(vec![msi_path])
@@ -164,9 +176,15 @@ fn generate_package_guid(settings: &Settings) -> Uuid {
 }
 
 // Populates the summary metadata for the package from the bundle settings.
-fn set_summary_info(package: &mut Package, package_guid: Uuid, settings: &Settings) {
+fn set_summary_info(
+    package: &mut Package,
+    package_guid: Uuid,
+    settings: &Settings,
+    platform: MsiPlatform,
+) {
     let summary_info = package.summary_info_mut();
     summary_info.set_creation_time_to_now();
+    summary_info.set_arch(platform.summary_name());
     summary_info.set_subject(settings.bundle_name().to_string());
     summary_info.set_uuid(package_guid);
     summary_info.set_comments(settings.short_description().to_string());
@@ -236,6 +254,19 @@ fn create_property_table(
                 msi::Value::from("installed"),
             ]),
     )?;
+    if let Some(build_info) = settings.build_info() {
+        package.insert_rows(
+            msi::Insert::into("Property")
+                .row(vec![
+                    msi::Value::from("BuildGitCommit"),
+                    msi::Value::Str(build_info.git_commit),
+                ])
+                .row(vec![
+                    msi::Value::from("BuildTimestamp"),
+                    msi::Value::Str(build_info.timestamp),
+                ]),
+        )?;
+    }
     Ok(())
 }
 
@@ -277,6 +308,7 @@ fn collect_resource_info(settings: &Settings) -> crate::Result<Vec<ResourceInfo>
 // with.
 fn collect_directory_info(
     settings: &Settings,
+    platform: MsiPlatform,
     resources: &mut [ResourceInfo],
 ) -> crate::Result<Vec<DirectoryInfo>> {
     let mut dir_map = BTreeMap::<PathBuf, DirectoryInfo>::new();
@@ -285,7 +317,7 @@ fn collect_directory_info(
         PathBuf::new(),
         DirectoryInfo {
             key: "INSTALLDIR".to_string(),
-            parent_key: "ProgramFilesFolder".to_string(),
+            parent_key: platform.program_files_folder().to_string(),
             name: settings.bundle_name().to_string(),
             files: Vec::new(),
         },
@@ -396,6 +428,7 @@ fn generate_resource_cabinets(
 // Creates and populates the `Directory` database table for the package.
 fn create_directory_table(
     package: &mut Package,
+    platform: MsiPlatform,
     directories: &[DirectoryInfo],
 ) -> crate::Result<()> {
     package.create_table(
@@ -427,7 +460,7 @@ fn create_directory_table(
                 msi::Value::from("SourceDir"),
             ])
             .row(vec![
-                msi::Value::from("ProgramFilesFolder"),
+                msi::Value::from(platform.program_files_folder()),
                 msi::Value::from("TARGETDIR"),
                 msi::Value::from("."),
             ])
@@ -519,6 +552,7 @@ fn make_identifier(dir_key: &str, filename: &str) -> String {
 fn create_component_table(
     package: &mut Package,
     package_guid: Uuid,
+    platform: MsiPlatform,
     directories: &[DirectoryInfo],
 ) -> crate::Result<()> {
     package.create_table(
@@ -541,6 +575,11 @@ fn create_component_table(
             msi::Column::build("KeyPath").nullable().id_string(72),
         ],
     )?;
+    let attributes = if platform.is_64bit() {
+        COMPONENT_ATTR_64BIT
+    } else {
+        0
+    };
     let mut rows = Vec::new();
     for directory in directories.iter() {
         if let Some(first) = directory.files.first() {
@@ -551,7 +590,7 @@ fn create_component_table(
                 msi::Value::Str(directory.key.clone()),
                 msi::Value::from(uuid),
                 msi::Value::Str(directory.key.clone()),
-                msi::Value::Int(0),
+                msi::Value::Int(attributes),
                 msi::Value::Null,
                 msi::Value::Str(keypath_id),
             ]);
@@ -687,6 +726,193 @@ fn create_file_table(package: &mut Package, cabinets: &[CabinetInfo]) -> crate::
     Ok(())
 }
 
+// Creates the Start Menu shortcut (and, if enabled, the desktop shortcut) to
+// the main executable.  The shortcuts belong to their own component in the
+// Start Menu folder, which also removes that folder on uninstall.
+fn create_shortcuts(
+    package: &mut Package,
+    package_guid: Uuid,
+    settings: &Settings,
+    cabinets: &[CabinetInfo],
+    icon_name: &str,
+) -> crate::Result<()> {
+    let exe_id = cabinets
+        .iter()
+        .find_map(|cabinet| {
+            cabinet
+                .resources
+                .iter()
+                .find(|resource| resource.dest_path == Path::new(&settings.binary_name()))
+                .map(|resource| make_identifier(&cabinet.name, &resource.filename))
+        })
+        .with_context(|| "Main executable is missing from the package")?;
+    let mut directories = msi::Insert::into("Directory")
+        .row(vec![
+            msi::Value::from("ProgramMenuFolder"),
+            msi::Value::from("TARGETDIR"),
+            msi::Value::from("."),
+        ])
+        .row(vec![
+            msi::Value::from("ApplicationProgramsFolder"),
+            msi::Value::from("ProgramMenuFolder"),
+            msi::Value::Str(settings.msi_start_menu_folder()),
+        ]);
+    if settings.msi_create_desktop_shortcut() {
+        directories = directories.row(vec![
+            msi::Value::from("DesktopFolder"),
+            msi::Value::from("TARGETDIR"),
+            msi::Value::from("."),
+        ]);
+    }
+    package.insert_rows(directories)?;
+    // A per-user component needs a per-user KeyPath, so key it on a registry
+    // value under HKCU, as the WiX installer does for its shortcuts.
+    package.create_table(
+        "Registry",
+        vec![
+            msi::Column::build("Registry").primary_key().id_string(72),
+            msi::Column::build("Root").range(-1, 3).int16(),
+            msi::Column::build("Key")
+                .localizable()
+                .category(msi::Category::RegPath)
+                .string(255),
+            msi::Column::build("Name")
+                .nullable()
+                .localizable()
+                .category(msi::Category::Formatted)
+                .string(255),
+            msi::Column::build("Value")
+                .nullable()
+                .localizable()
+                .category(msi::Category::Formatted)
+                .string(0),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+        ],
+    )?;
+    let manufacturer = settings.authors_comma_separated().unwrap_or_default();
+    package.insert_rows(msi::Insert::into("Registry").row(vec![
+        msi::Value::from("ApplicationShortcutsInstalled"),
+        msi::Value::Int(1), // HKEY_CURRENT_USER
+        msi::Value::Str(format!(
+            "Software\\{}\\{}",
+            manufacturer.to_lowercase(),
+            settings.bundle_name()
+        )),
+        msi::Value::from("installed"),
+        msi::Value::from("#1"),
+        msi::Value::from("ApplicationShortcuts"),
+    ]))?;
+    let component_uuid = Uuid::new_v5(&package_guid, b"ApplicationShortcuts");
+    // Attributes 4 (msidbComponentAttributesRegistryKeyPath) makes the
+    // KeyPath refer to a row of the Registry table.
+    package.insert_rows(msi::Insert::into("Component").row(vec![
+        msi::Value::from("ApplicationShortcuts"),
+        msi::Value::from(component_uuid),
+        msi::Value::from("ApplicationProgramsFolder"),
+        msi::Value::Int(4),
+        msi::Value::Null,
+        msi::Value::from("ApplicationShortcutsInstalled"),
+    ]))?;
+    package.insert_rows(msi::Insert::into("FeatureComponents").row(vec![
+        msi::Value::from(MAIN_FEATURE_NAME),
+        msi::Value::from("ApplicationShortcuts"),
+    ]))?;
+
+    package.create_table(
+        "RemoveFile",
+        vec![
+            msi::Column::build("FileKey").primary_key().id_string(72),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+            msi::Column::build("FileName")
+                .nullable()
+                .category(msi::Category::WildCardFilename)
+                .string(255),
+            msi::Column::build("DirProperty")
+                .category(msi::Category::Identifier)
+                .string(72),
+            msi::Column::build("InstallMode").range(1, 3).int16(),
+        ],
+    )?;
+    // InstallMode 2 removes the folder on uninstall.
+    package.insert_rows(msi::Insert::into("RemoveFile").row(vec![
+        msi::Value::from("RemoveApplicationProgramsFolder"),
+        msi::Value::from("ApplicationShortcuts"),
+        msi::Value::Null,
+        msi::Value::from("ApplicationProgramsFolder"),
+        msi::Value::Int(2),
+    ]))?;
+
+    package.create_table(
+        "Shortcut",
+        vec![
+            msi::Column::build("Shortcut").primary_key().id_string(72),
+            msi::Column::build("Directory_")
+                .foreign_key("Directory", 1)
+                .id_string(72),
+            msi::Column::build("Name")
+                .category(msi::Category::Filename)
+                .string(128),
+            msi::Column::build("Component_")
+                .foreign_key("Component", 1)
+                .id_string(72),
+            msi::Column::build("Target")
+                .category(msi::Category::Shortcut)
+                .string(72),
+            msi::Column::build("Arguments")
+                .nullable()
+                .category(msi::Category::Formatted)
+                .string(255),
+            msi::Column::build("Description")
+                .nullable()
+                .text_string(255),
+            msi::Column::build("Hotkey")
+                .nullable()
+                .range(0, 0x7fff)
+                .int16(),
+            msi::Column::build("Icon_")
+                .nullable()
+                .foreign_key("Icon", 1)
+                .id_string(72),
+            msi::Column::build("IconIndex")
+                .nullable()
+                .range(-0x7fff, 0x7fff)
+                .int16(),
+            msi::Column::build("ShowCmd").nullable().int16(),
+            msi::Column::build("WkDir")
+                .nullable()
+                .category(msi::Category::Identifier)
+                .string(72),
+        ],
+    )?;
+    let mut shortcut_dirs = vec![("ApplicationStartMenuShortcut", "ApplicationProgramsFolder")];
+    if settings.msi_create_desktop_shortcut() {
+        shortcut_dirs.push(("DesktopShortcut", "DesktopFolder"));
+    }
+    let mut rows = Vec::new();
+    for (shortcut_id, directory) in shortcut_dirs {
+        rows.push(vec![
+            msi::Value::from(shortcut_id),
+            msi::Value::from(directory),
+            msi::Value::Str(settings.bundle_name()),
+            msi::Value::from("ApplicationShortcuts"),
+            msi::Value::Str(format!("[#{exe_id}]")),
+            msi::Value::Null,
+            msi::Value::from(settings.short_description()),
+            msi::Value::Null,
+            msi::Value::from(icon_name),
+            msi::Value::Int(0),
+            msi::Value::Null,
+            msi::Value::from("INSTALLDIR"),
+        ]);
+    }
+    package.insert_rows(msi::Insert::into("Shortcut").rows(rows))?;
+    Ok(())
+}
+
 fn create_install_execute_sequence_table(
     package: &mut Package,
     _cabinets: &[CabinetInfo],
@@ -706,7 +932,7 @@ fn create_install_execute_sequence_table(
         ],
     )?;
     let mut rows = Vec::new();
-    let actions: [(&str, &str, i32); 24] = [
+    let actions: [(&str, &str, i32); 28] = [
         //("LaunchConditions", "", 100), // Requires a LaunchCondition table
         //("FindRelatedProducts", "", 200), // Requires an Upgrade table
         //("AppSearch", "", 400), // Requires a Signature table
@@ -731,13 +957,13 @@ fn create_install_execute_sequence_table(
         //("UnregisterTypeLibraries", "", 2300), // Requires a TypeLib table
         //("RemoveODBC", "", 2400), // Requires an ODBC* table
         //("UnregisterFonts", "", 2500), // Requires a Font table
-        //("RemoveRegistryValues", "", 2600), // Requires a Registry table
+        ("RemoveRegistryValues", "", 2600),
         //("UnregisterClassInfo", "", 2700), // Requires a Class table
         //("UnregisterExtensionInfo", "", 2800), // Requires an Extension table
         //("UnregisterProgIdInfo", "", 2900), // Requires ProgId, Extension or Class table
         //("UnregisterMIMEInfo", "", 3000), // Requires a MIME table
         //("RemoveIniValues", "", 3100), // Requires an IniFile table
-        //("RemoveShortcuts", "", 3200), // Requires a Shortcut table
+        ("RemoveShortcuts", "", 3200),
         //("RemoveEnvironmentStrings", "", 3300), // Requires an Environment table
         //("RemoveDuplicateFiles", "", 3400), // Requires a DuplicateFile table
         ("RemoveFiles", "", 3500),
@@ -748,12 +974,12 @@ fn create_install_execute_sequence_table(
         //("PatchFiles", "", 4090), // Requires a Patch table
         //("DuplicateFiles", "", 4210), // Requires a DuplicateFile table
         //("BindImage", "", 4300), // Requires a BindImage table
-        //("CreateShortcuts", "", 4500), // Requires a Shortcut table
+        ("CreateShortcuts", "", 4500),
         //("RegisterClassInfo", "", 4600), // Requires a Class table
         //("RegisterExtensionInfo", "", 4700), // Requires an Extension table
         //("RegisterProgIdInfo", "", 4800), // Requires a ProgId table
         //("RegisterMIMEInfo", "", 4900), // Requires a MIME table
-        //("WriteRegistryValues", "", 5000), // Requires a Registry table
+        ("WriteRegistryValues", "", 5000),
         //("WriteIniValues", "", 5100), // Requires an IniFile table
         //("WriteEnvironmentStrings", "", 5200), // Requires an Environment table
         //("RegisterFonts", "", 5300), // Requires a Font table
@@ -1202,6 +1428,71 @@ fn create_app_icon<W: Write>(writer: &mut W, settings: &Settings) -> crate::Resu
             return Ok(());
         }
     }
+    // Otherwise, generate one from the PNG files.
+    let icon_paths = settings
+        .icon_files()
+        .collect::<crate::Result<Vec<PathBuf>>>()?;
+    common::write_ico_from_pngs(&icon_paths, writer)?;
     // TODO: Convert from other formats.
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::bundle::{PackageType, Settings, target_info::BundleTargetInfo};
+    use crate::test_utils::write_fixture_package;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn shortcuts_component_is_keyed_on_a_user_registry_value() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_fixture_package(tmp.path(), "authors = [\"Foo Corp\"]\n");
+        let binary = tmp.path().join("prebuilt/foo.exe");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, b"MZ").unwrap();
+        #[cfg(unix)]
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::WindowsMsi, None).unwrap();
+        let settings = Settings::builder(info)
+            .prebuilt_binary(&binary)
+            .build()
+            .unwrap();
+
+        let output_paths = super::bundle_project(&settings).unwrap();
+        let mut package = msi::open(&output_paths[0]).unwrap();
+        let component: Vec<msi::Row> =
+            package
+                .select_rows(msi::Select::table("Component").with(
+                    msi::Expr::col("Component").eq(msi::Expr::string("ApplicationShortcuts")),
+                ))
+                .unwrap()
+                .collect();
+        assert_eq!(component.len(), 1);
+        assert_eq!(component[0]["Attributes"], msi::Value::Int(4));
+        assert_eq!(
+            component[0]["KeyPath"],
+            msi::Value::from("ApplicationShortcutsInstalled")
+        );
+        let registry: Vec<msi::Row> = package
+            .select_rows(msi::Select::table("Registry"))
+            .unwrap()
+            .collect();
+        assert_eq!(registry.len(), 1);
+        assert_eq!(
+            registry[0]["Registry"],
+            msi::Value::from("ApplicationShortcutsInstalled")
+        );
+        assert_eq!(registry[0]["Root"], msi::Value::Int(1));
+        assert_eq!(
+            registry[0]["Key"],
+            msi::Value::from("Software\\foo corp\\foo")
+        );
+        assert_eq!(registry[0]["Name"], msi::Value::from("installed"));
+        assert_eq!(registry[0]["Value"], msi::Value::from("#1"));
+        assert_eq!(
+            registry[0]["Component_"],
+            msi::Value::from("ApplicationShortcuts")
+        );
+    }
+}
@@ -10,6 +10,7 @@
 //             en.lproj        # Folder containing english translation strings/data
 //         Frameworks     # A directory containing private frameworks (shared libraries)
 //         PlugIns        # A directory containing Plugins
+//         _CodeSignature # Added by codesign (optional)
 //         ...            # Any other optional files the developer wants to place here
 //
 // See https://developer.apple.com/go/?id=bundle-structure for a full
@@ -19,28 +20,31 @@
 // files into the `Contents` directory of the bundle.
 
 use super::common::{self, read_file};
+use super::plist;
 use crate::Settings;
 use anyhow::Context;
 use image::imageops::FilterType::Lanczos3;
 use image::{self, GenericImageView};
 use std::cmp::min;
+use std::collections::HashSet;
 use std::ffi::OsStr;
 use std::fs::{self, File};
 use std::io::prelude::*;
 use std::io::{self, BufWriter};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    let app_bundle_name = format!("{}.app", settings.bundle_name());
+    let app_bundle_name = format!(
+        "{}.app",
+        settings.artifact_name(settings.binary_arch(), settings.bundle_name())
+    );
     common::print_bundling(&app_bundle_name)?;
     let app_bundle_path = settings
-        .get_target_dir()
-        .join("bundle/osx")
+        .bundle_dir(crate::bundle::PackageType::OsxBundle)
         .join(&app_bundle_name);
-    if app_bundle_path.exists() {
-        fs::remove_dir_all(&app_bundle_path)
-            .with_context(|| format!("Failed to remove old {app_bundle_name}"))?;
-    }
+    common::prepare_output_path(&app_bundle_path, settings.force())?;
     let bundle_directory = app_bundle_path.join("Contents");
     fs::create_dir_all(&bundle_directory)
         .with_context(|| format!("Failed to create bundle directory at {bundle_directory:?}"))?;
@@ -60,10 +64,9 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
     copy_plugins_to_bundle(&bundle_directory, settings)
         .with_context(|| "Failed to bundle plugins")?;
 
-    for (src, dst) in settings.resources_paths(resources_dir.as_path()) {
-        common::copy_file(&src, &dst)
-            .with_context(|| format!("Failed to copy resource file {src:?} to {dst:?}"))?;
-    }
+    common::copy_resources(settings, &resources_dir)?;
+    copy_localizations(&resources_dir, settings)
+        .with_context(|| "Failed to copy localized resources")?;
 
     copy_binary_to_bundle(&bundle_directory, settings).with_context(|| {
         format!(
@@ -76,7 +79,78 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         add_rpath(&bundle_directory, settings)?;
     }
 
-    Ok(vec![app_bundle_path])
+    if settings.osx_bundle_dylibs() {
+        bundle_dylibs(&bundle_directory, settings)
+            .with_context(|| "Failed to bundle dynamic libraries")?;
+    }
+
+    sign_bundle(&app_bundle_path, settings).with_context(|| "Failed to sign the app bundle")?;
+    if settings.notarize()
+        && let Some(keychain_profile) = settings.osx_notarize_keychain_profile()
+    {
+        common::notarize(&app_bundle_path, keychain_profile)?;
+    }
+
+    let mut output_paths = vec![app_bundle_path];
+    if settings.include_debug_symbols() {
+        let dsym_path = generate_dsym(&output_paths[0], settings)
+            .with_context(|| "Failed to generate debug symbols")?;
+        output_paths.push(dsym_path);
+    }
+    Ok(output_paths)
+}
+
+/// Returns the `codesign` identity to sign the bundle with: the
+/// `osx_signing_identity`, or else `-` for ad-hoc signing if enabled.
+fn signing_identity(settings: &Settings) -> Option<&str> {
+    match settings.osx_signing_identity() {
+        Some(identity) => Some(identity),
+        None if settings.osx_adhoc_sign() => Some("-"),
+        None => None,
+    }
+}
+
+/// Signs the app bundle and the code inside it with `codesign`, if a
+/// signing identity is configured or ad-hoc signing is enabled.
+fn sign_bundle(app_bundle_path: &Path, settings: &Settings) -> crate::Result<()> {
+    let Some(identity) = signing_identity(settings) else {
+        return Ok(());
+    };
+    if identity == "-" {
+        common::print_warning(
+            "No osx_signing_identity set, ad-hoc signing the bundle so that it runs on this \
+             Mac; ad-hoc signed apps can't be distributed to other Macs",
+        )?;
+    }
+    let mut codesign = std::process::Command::new("codesign");
+    codesign.args(["--force", "--deep", "--sign"]).arg(identity);
+    if settings.notarize() {
+        // Apple only notarizes apps with the hardened runtime and a secure
+        // timestamp.
+        codesign.args(["--options", "runtime", "--timestamp"]);
+    }
+    codesign.arg(app_bundle_path);
+    common::run_command(&mut codesign, common::TOOL_TIMEOUT)?;
+    Ok(())
+}
+
+/// Runs `dsymutil` on the binary to collect its debug symbols into a `.dSYM`
+/// bundle next to the app bundle.
+fn generate_dsym(app_bundle_path: &Path, settings: &Settings) -> crate::Result<PathBuf> {
+    let mut dsym_name = app_bundle_path.file_name().unwrap_or_default().to_owned();
+    dsym_name.push(".dSYM");
+    let dsym_path = app_bundle_path.with_file_name(dsym_name);
+    if dsym_path.exists() {
+        fs::remove_dir_all(&dsym_path)
+            .with_context(|| format!("Failed to remove old {dsym_path:?}"))?;
+    }
+    let mut dsymutil = std::process::Command::new("dsymutil");
+    dsymutil
+        .arg(settings.binary_path(crate::bundle::PackageType::OsxBundle))
+        .arg("-o")
+        .arg(&dsym_path);
+    common::run_command(&mut dsymutil, common::TOOL_TIMEOUT)?;
+    Ok(dsym_path)
 }
 
 #[allow(dead_code)]
@@ -89,11 +163,9 @@ struct DylibInfo {
 impl DylibInfo {
     fn inspect(dylib_path: &Path) -> crate::Result<Self> {
         use std::process::Command;
-        let out = Command::new("otool").arg("-l").arg(dylib_path).output()?;
-
-        if !out.status.success() {
-            anyhow::bail!("otool command failed with status: {}", out.status);
-        }
+        let mut otool = Command::new("otool");
+        otool.arg("-l").arg(dylib_path);
+        let out = common::run_command(&mut otool, common::TOOL_TIMEOUT)?;
 
         let mut dylibs = Vec::new();
         let mut rpaths = Vec::new();
@@ -169,18 +241,94 @@ fn copy_binary_to_bundle(bundle_directory: &Path, settings: &Settings) -> crate:
             .binary_path(super::PackageType::OsxBundle)
             .as_path(),
         &dest_dir.join(settings.binary_name()),
-    )
+    )?;
+    for name in settings.osx_additional_binaries() {
+        let src = settings.additional_binary_path(name);
+        if !src.is_file() {
+            anyhow::bail!("Additional binary {src:?} does not exist; was it built?");
+        }
+        common::copy_file(&src, &dest_dir.join(name))?;
+    }
+    Ok(())
 }
-trait PlistEntryFormatter {
-    fn format_plist_entry(&self) -> String;
+
+/// Parses the output of `otool -L`, returning the paths of the libraries it
+/// lists.
+fn parse_otool_libraries(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let line = line.trim();
+            let end = line.find(" (compatibility version")?;
+            Some(PathBuf::from(&line[..end]))
+        })
+        .collect()
+}
+
+/// Returns true for libraries that are part of macOS itself, or that are
+/// already referenced relative to the app, and so must not be copied.
+fn is_system_dylib(path: &Path) -> bool {
+    path.starts_with("/usr/lib")
+        || path.starts_with("/System")
+        || path.to_string_lossy().starts_with('@')
 }
 
-impl<T: AsRef<str>> PlistEntryFormatter for T {
-    fn format_plist_entry(&self) -> String {
-        let input = self.as_ref();
-        input.replace("&", "&amp;")
-        // add other necessary modifications here...
+fn run_install_name_tool(args: &[&OsStr]) -> crate::Result<()> {
+    let mut install_name_tool = std::process::Command::new("install_name_tool");
+    install_name_tool.args(args);
+    common::run_command(&mut install_name_tool, common::TOOL_TIMEOUT)?;
+    Ok(())
+}
+
+/// Copies the non-system dylibs the binary links against (and, transitively,
+/// the ones those link against) into `Contents/Frameworks`, and rewrites the
+/// install names so that they are loaded from there.
+fn bundle_dylibs(bundle_directory: &Path, settings: &Settings) -> crate::Result<()> {
+    let frameworks_dir = bundle_directory.join("Frameworks");
+    let binary = bundle_directory.join("MacOS").join(settings.binary_name());
+    let mut pending = vec![binary];
+    let mut copied = HashSet::new();
+    while let Some(object) = pending.pop() {
+        let mut otool = std::process::Command::new("otool");
+        otool.arg("-L").arg(&object);
+        let out = common::run_command(&mut otool, common::TOOL_TIMEOUT)?;
+        for dylib in parse_otool_libraries(&String::from_utf8_lossy(&out.stdout)) {
+            if is_system_dylib(&dylib) {
+                continue;
+            }
+            let file_name = dylib
+                .file_name()
+                .with_context(|| format!("Invalid library path {dylib:?}"))?;
+            let install_name = format!(
+                "@executable_path/../Frameworks/{}",
+                file_name.to_string_lossy()
+            );
+            run_install_name_tool(&[
+                OsStr::new("-change"),
+                dylib.as_os_str(),
+                OsStr::new(&install_name),
+                object.as_os_str(),
+            ])?;
+            if copied.insert(dylib.clone()) {
+                let dest = frameworks_dir.join(file_name);
+                common::copy_file(&dylib, &dest)?;
+                // Libraries installed by package managers are often read-only.
+                #[allow(unused_mut)]
+                let mut perms = fs::metadata(&dest)?.permissions();
+                #[cfg(unix)]
+                perms.set_mode(0o644);
+                fs::set_permissions(&dest, perms)?;
+                run_install_name_tool(&[
+                    OsStr::new("-id"),
+                    OsStr::new(&install_name),
+                    dest.as_os_str(),
+                ])?;
+                pending.push(dest);
+            }
+        }
     }
+    Ok(())
 }
 
 const FRAMEWORKS_RPATH: &str = "@executable_path/../Frameworks";
@@ -195,15 +343,12 @@ fn add_rpath(bundle_directory: &Path, settings: &Settings) -> crate::Result<()>
         return Ok(());
     }
 
-    if !std::process::Command::new("install_name_tool")
+    let mut install_name_tool = std::process::Command::new("install_name_tool");
+    install_name_tool
         .arg("-add_rpath")
         .arg(FRAMEWORKS_RPATH)
-        .arg(bin)
-        .status()?
-        .success()
-    {
-        anyhow::bail!("failed to execute install_name_tool");
-    }
+        .arg(bin);
+    common::run_command(&mut install_name_tool, common::TOOL_TIMEOUT)?;
 
     Ok(())
 }
@@ -214,131 +359,139 @@ fn create_info_plist(
     settings: &Settings,
 ) -> crate::Result<()> {
     let build_number = chrono::Utc::now().format("%Y%m%d.%H%M%S");
-    let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
-    write!(
-        file,
-        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
-            <!DOCTYPE plist PUBLIC \"-//Apple Computer//DTD PLIST 1.0//EN\" \
-            \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
-            <plist version=\"1.0\">\n\
-            <dict>\n"
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleDevelopmentRegion</key>\n  \
-            <string>English</string>\n"
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleDisplayName</key>\n  <string>{}</string>\n",
-        settings.bundle_name().format_plist_entry()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleExecutable</key>\n  <string>{}</string>\n",
-        settings.binary_name()
-    )?;
+    let mut dict = plist::Dictionary::new();
+    let mut insert = |key: &str, value: plist::Value| {
+        dict.insert(key.to_string(), value);
+    };
+    insert("CFBundleDevelopmentRegion", "English".into());
+    insert("CFBundleDisplayName", settings.osx_display_name().into());
+    insert("CFBundleExecutable", settings.binary_name().into());
     if let Some(path) = bundle_icon_file {
-        write!(
-            file,
-            "  <key>CFBundleIconFile</key>\n  <string>{}</string>\n",
-            path.file_name().unwrap().to_string_lossy()
-        )?;
+        insert(
+            "CFBundleIconFile",
+            path.file_name().unwrap().to_string_lossy().as_ref().into(),
+        );
     }
-    write!(
-        file,
-        "  <key>CFBundleIdentifier</key>\n  <string>{}</string>\n",
-        settings.bundle_identifier()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleInfoDictionaryVersion</key>\n  \
-            <string>6.0</string>\n"
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleName</key>\n  <string>{}</string>\n",
-        settings.bundle_name().format_plist_entry()
-    )?;
-    write!(
-        file,
-        "  <key>CFBundlePackageType</key>\n  <string>APPL</string>\n"
-    )?;
-    write!(
-        file,
-        "  <key>CFBundleShortVersionString</key>\n  <string>{}</string>\n",
-        settings.version_string()
-    )?;
+    insert(
+        "CFBundleIdentifier",
+        settings.bundle_identifier().into_owned().into(),
+    );
+    insert("CFBundleInfoDictionaryVersion", "6.0".into());
+    let localizations = settings.osx_localizations();
+    if !localizations.is_empty() {
+        insert(
+            "CFBundleLocalizations",
+            plist::Value::Array(
+                localizations
+                    .iter()
+                    .map(|(locale, _)| (*locale).into())
+                    .collect(),
+            ),
+        );
+    }
+    insert("CFBundleName", settings.bundle_name().into());
+    insert("CFBundlePackageType", "APPL".into());
+    insert(
+        "CFBundleShortVersionString",
+        settings.version_string().to_string().into(),
+    );
     if !settings.osx_url_schemes().is_empty() {
-        write!(
-            file,
-            "  <key>CFBundleURLTypes</key>\n  \
-               <array>\n    \
-                   <dict>\n      \
-                       <key>CFBundleURLName</key>\n      \
-                       <string>{}</string>\n      \
-                       <key>CFBundleTypeRole</key>\n      \
-                       <string>Viewer</string>\n      \
-                       <key>CFBundleURLSchemes</key>\n      \
-                       <array>\n",
-            settings.bundle_name().format_plist_entry()
-        )?;
-        for scheme in settings.osx_url_schemes() {
-            writeln!(
-                file,
-                "        <string>{}</string>",
-                scheme.format_plist_entry()
-            )?;
-        }
-        write!(
-            file,
-            "      </array>\n    \
-                </dict>\n  \
-             </array>\n"
-        )?;
+        let url_type = plist::Dictionary::from([
+            ("CFBundleURLName".to_string(), settings.bundle_name().into()),
+            ("CFBundleTypeRole".to_string(), "Viewer".into()),
+            (
+                "CFBundleURLSchemes".to_string(),
+                plist::Value::Array(
+                    settings
+                        .osx_url_schemes()
+                        .iter()
+                        .map(|scheme| scheme.as_str().into())
+                        .collect(),
+                ),
+            ),
+        ]);
+        insert(
+            "CFBundleURLTypes",
+            plist::Value::Array(vec![plist::Value::Dictionary(url_type)]),
+        );
     }
-    write!(
-        file,
-        "  <key>CFBundleVersion</key>\n  <string>{build_number}</string>\n"
-    )?;
-    write!(file, "  <key>CSResourcesFileMapped</key>\n  <true/>\n")?;
+    insert("CFBundleVersion", build_number.to_string().into());
+    if let Some(build_info) = settings.build_info() {
+        insert("BuildGitCommit", build_info.git_commit.into());
+        insert("BuildTimestamp", build_info.timestamp.into());
+    }
+    insert("CSResourcesFileMapped", true.into());
     if let Some(category) = settings.app_category() {
-        write!(
-            file,
-            "  <key>LSApplicationCategoryType</key>\n  \
-                <string>{}</string>\n",
-            category
-                .osx_application_category_type()
-                .format_plist_entry()
-        )?;
+        insert(
+            "LSApplicationCategoryType",
+            category.osx_application_category_type().into(),
+        );
     }
     if let Some(version) = settings.osx_minimum_system_version() {
-        write!(
-            file,
-            "  <key>LSMinimumSystemVersion</key>\n  \
-                <string>{version}</string>\n"
-        )?;
+        insert("LSMinimumSystemVersion", version.into());
     }
-    write!(file, "  <key>LSRequiresCarbon</key>\n  <true/>\n")?;
-    write!(file, "  <key>NSHighResolutionCapable</key>\n  <true/>\n")?;
+    insert("LSRequiresCarbon", true.into());
+    insert(
+        "NSHighResolutionCapable",
+        settings.osx_high_resolution_capable().into(),
+    );
     if let Some(copyright) = settings.copyright_string() {
-        write!(
-            file,
-            "  <key>NSHumanReadableCopyright</key>\n  \
-                <string>{}</string>\n",
-            copyright.format_plist_entry()
-        )?;
+        insert("NSHumanReadableCopyright", copyright.into());
     }
-    for plist in settings.osx_info_plist_exts() {
-        let plist = plist?;
-        let contents = read_file(&plist)?;
-        write!(file, "{:}", contents.format_plist_entry())?
+    if let Some(requires_aqua) = settings.osx_requires_aqua_system_appearance() {
+        insert("NSRequiresAquaSystemAppearance", requires_aqua.into());
     }
-    write!(file, "</dict>\n</plist>\n")?;
+    // The partial plists are merged in the order they are listed, and the
+    // explicit keys are merged last so that they take precedence.
+    for path in settings.osx_info_plist_exts() {
+        let path = path?;
+        let ext = plist::parse_dictionary(&read_file(&path)?)
+            .with_context(|| format!("Failed to parse plist {path:?}"))?;
+        plist::merge(&mut dict, ext);
+    }
+    plist::merge(
+        &mut dict,
+        plist::dictionary_from_toml(settings.osx_info_plist_keys())?,
+    );
+
+    let file = &mut common::create_file(&bundle_dir.join("Info.plist"))?;
+    write!(file, "{}", plist::HEADER)?;
+    plist::write_entries(file, &dict)?;
+    write!(file, "{}", plist::FOOTER)?;
     file.flush()?;
     Ok(())
 }
 
+/// Copies the resource files of each locale into `<locale>.lproj` under
+/// `resources_dir`, along with an `InfoPlist.strings` file if the display
+/// name is localized for it.
+fn copy_localizations(resources_dir: &Path, settings: &Settings) -> crate::Result<()> {
+    for (locale, files) in settings.osx_localizations() {
+        let lproj_dir = resources_dir.join(format!("{locale}.lproj"));
+        fs::create_dir_all(&lproj_dir)
+            .with_context(|| format!("Failed to create {lproj_dir:?}"))?;
+        for file in files {
+            let file = file?;
+            common::copy_file(&file, &lproj_dir.join(file.file_name().unwrap()))?;
+        }
+        if let Some(display_name) = settings.osx_localized_display_name(locale) {
+            let file = &mut common::create_file(&lproj_dir.join("InfoPlist.strings"))?;
+            writeln!(
+                file,
+                "\"CFBundleDisplayName\" = \"{}\";",
+                escape_strings_value(display_name)
+            )?;
+            file.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Escapes `value` for use in a double-quoted string of a `.strings` file.
+fn escape_strings_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 fn copy_framework_from(dest_dir: &Path, framework: &str, src_dir: &Path) -> crate::Result<bool> {
     let src_name = format!("{framework}.framework");
     let src_path = src_dir.join(&src_name);
@@ -360,6 +513,7 @@ fn copy_frameworks_to_bundle(bundle_directory: &Path, settings: &Settings) -> cr
     fs::create_dir_all(bundle_directory)
         .with_context(|| format!("Failed to create Frameworks directory at {dest_dir:?}"))?;
     for framework in frameworks.iter() {
+        let framework = &common::expand_path(framework);
         if framework.ends_with(".framework") {
             let src_path = PathBuf::from(framework);
             let src_name = src_path.file_name().unwrap();
@@ -413,7 +567,7 @@ fn copy_plugins_to_bundle(bundle_directory: &Path, settings: &Settings) -> crate
     fs::create_dir_all(bundle_directory)
         .with_context(|| format!("Failed to create PlugIns directory at {dest_dir:?}"))?;
     for plugin in plugins.iter() {
-        let src_path = PathBuf::from(plugin);
+        let src_path = PathBuf::from(common::expand_path(plugin));
         let src_name = src_path.file_name().unwrap();
         common::copy_dir(&src_path, &dest_dir.join(src_name))?;
     }
@@ -535,3 +689,303 @@ fn make_icns_image(img: image::DynamicImage) -> io::Result<icns::Image> {
     };
     icns::Image::from_data(pixel_format, img.width(), img.height(), img.into_bytes())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        copy_localizations, create_info_plist, is_system_dylib, parse_otool_libraries,
+        signing_identity,
+    };
+    use crate::test_utils::{fixture_package, write_fixture_package};
+    use crate::{
+        BundleSettings, Cli,
+        bundle::{PackageType, Settings, target_info::BundleTargetInfo},
+    };
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn parse_otool_output() {
+        let output = "\
+target/release/foo:
+\t/opt/homebrew/opt/sdl2/lib/libSDL2-2.0.0.dylib (compatibility version 3201.0.0, current version 3201.4.0)
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1345.100.2)
+";
+        assert_eq!(
+            parse_otool_libraries(output),
+            vec![
+                PathBuf::from("/opt/homebrew/opt/sdl2/lib/libSDL2-2.0.0.dylib"),
+                PathBuf::from("/usr/lib/libSystem.B.dylib"),
+            ]
+        );
+    }
+
+    #[test]
+    fn system_dylibs() {
+        assert!(is_system_dylib(Path::new("/usr/lib/libSystem.B.dylib")));
+        assert!(is_system_dylib(Path::new(
+            "/System/Library/Frameworks/AppKit.framework/Versions/C/AppKit"
+        )));
+        assert!(is_system_dylib(Path::new("@rpath/libfoo.dylib")));
+        assert!(!is_system_dylib(Path::new("/usr/local/lib/libfoo.dylib")));
+    }
+
+    #[test]
+    fn info_plist_application_category() {
+        for (category, expected) in [
+            ("Puzzle Game", "public.app-category.puzzle-games"),
+            ("Developer Tool", "public.app-category.developer-tools"),
+            ("Graphics and Design", "public.app-category.graphics-design"),
+            ("Utility", "public.app-category.utilities"),
+        ] {
+            let tmp = tempfile::tempdir().unwrap();
+            write_fixture_package(
+                tmp.path(),
+                &format!(
+                    "\n\
+                     [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n\
+                     category = \"{category}\"\n"
+                ),
+            );
+            let cli = <Cli as clap::Parser>::parse_from([
+                "cargo-bundler",
+                "--target-dir",
+                tmp.path().join("target").to_str().unwrap(),
+                tmp.path().to_str().unwrap(),
+            ]);
+            let info = BundleTargetInfo::try_from((&cli, PackageType::OsxBundle, None)).unwrap();
+            let settings = Settings::new(&info, &cli).unwrap();
+
+            create_info_plist(tmp.path(), None, &settings).unwrap();
+            let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+            assert!(
+                plist.contains(&format!(
+                    "<key>LSApplicationCategoryType</key>\n  <string>{expected}</string>"
+                )),
+                "{plist}"
+            );
+        }
+    }
+
+    #[test]
+    fn signing_identities() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n",
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+        let settings_with = |overrides: &[&str]| {
+            let overrides: Vec<String> = overrides.iter().map(|o| o.to_string()).collect();
+            Settings::builder(info.clone())
+                .overrides(BundleSettings::from_overrides(&overrides).unwrap())
+                .build()
+                .unwrap()
+        };
+
+        let settings = settings_with(&[]);
+        let host_default = cfg!(target_os = "macos") && settings.binary_arch() == "aarch64";
+        assert_eq!(settings.osx_adhoc_sign(), host_default);
+        let settings = settings_with(&["osx_adhoc_sign=true"]);
+        assert_eq!(signing_identity(&settings), Some("-"));
+        let settings = settings_with(&["osx_adhoc_sign=false"]);
+        assert_eq!(signing_identity(&settings), None);
+        let settings = settings_with(&[
+            "osx_adhoc_sign=true",
+            "osx_signing_identity=Developer ID Application: Foo",
+        ]);
+        assert_eq!(
+            signing_identity(&settings),
+            Some("Developer ID Application: Foo")
+        );
+
+        let notarize = |overrides: &[&str]| {
+            let overrides: Vec<String> = overrides.iter().map(|o| o.to_string()).collect();
+            Settings::builder(info.clone())
+                .overrides(BundleSettings::from_overrides(&overrides).unwrap())
+                .notarize(true)
+                .build()
+                .map_err(|error| error.to_string())
+        };
+        let error = notarize(&["osx_notarize_keychain_profile=notary"]).unwrap_err();
+        assert!(error.contains("needs an `osx_signing_identity`"), "{error}");
+        let error = notarize(&["osx_signing_identity=Developer ID Application: Foo"]).unwrap_err();
+        assert!(error.contains("`osx_notarize_keychain_profile`"), "{error}");
+        let settings = notarize(&[
+            "osx_signing_identity=Developer ID Application: Foo",
+            "osx_notarize_keychain_profile=notary",
+        ])
+        .unwrap();
+        assert!(settings.notarize());
+        assert_eq!(settings.osx_notarize_keychain_profile(), Some("notary"));
+    }
+
+    #[test]
+    fn info_plist_display_keys() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n",
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+
+        let settings = Settings::builder(info.clone()).build().unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains("<key>NSHighResolutionCapable</key>\n  <true/>"));
+        assert!(!plist.contains("NSRequiresAquaSystemAppearance"));
+
+        let overrides = BundleSettings::from_overrides(&[
+            "osx_high_resolution_capable=false".to_string(),
+            "osx_requires_aqua_system_appearance=true".to_string(),
+        ])
+        .unwrap();
+        let settings = Settings::builder(info)
+            .overrides(overrides)
+            .build()
+            .unwrap();
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(plist.contains("<key>NSHighResolutionCapable</key>\n  <false/>"));
+        assert!(plist.contains("<key>NSRequiresAquaSystemAppearance</key>\n  <true/>"));
+    }
+
+    #[test]
+    fn info_plist_extra_keys() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n\
+             \n\
+             [package.metadata.bundle.osx_info_plist_keys]\n\
+             NSCameraUsageDescription = \"Scans QR codes\"\n\
+             LSUIElement = true\n\
+             NSHighResolutionCapable = false\n",
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(
+            plist
+                .contains("<key>NSCameraUsageDescription</key>\n  <string>Scans QR codes</string>"),
+            "{plist}"
+        );
+        assert!(plist.contains("<key>LSUIElement</key>\n  <true/>"));
+        assert!(plist.contains("<key>NSHighResolutionCapable</key>\n  <false/>"));
+        assert_eq!(plist.matches("NSHighResolutionCapable").count(), 1);
+    }
+
+    #[test]
+    fn localized_resources() {
+        let tmp = tempfile::tempdir().unwrap();
+        for locale in ["en", "fr"] {
+            let dir = tmp.path().join("i18n").join(locale);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("Localizable.strings"), locale).unwrap();
+        }
+        write_fixture_package(
+            tmp.path(),
+            &format!(
+                "\n\
+                 [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n\
+                 \n\
+                 [package.metadata.bundle.osx_localizations]\n\
+                 fr = [{:?}]\nen = [{:?}]\n",
+                tmp.path().join("i18n/fr/*.strings"),
+                tmp.path().join("i18n/en/*.strings"),
+            ),
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(
+            plist.contains(
+                "<key>CFBundleLocalizations</key>\n  <array>\n    <string>en</string>\n    \
+                 <string>fr</string>\n  </array>"
+            ),
+            "{plist}"
+        );
+        let resources_dir = tmp.path().join("Resources");
+        copy_localizations(&resources_dir, &settings).unwrap();
+        for locale in ["en", "fr"] {
+            let strings = resources_dir.join(format!("{locale}.lproj/Localizable.strings"));
+            assert_eq!(std::fs::read_to_string(strings).unwrap(), locale);
+        }
+    }
+
+    #[test]
+    fn localized_display_names() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nname = \"Foo\"\nidentifier = \"com.example.foo\"\n\
+             osx_display_name = \"Foo Viewer\"\n\
+             \n\
+             [package.metadata.bundle.osx_localized_display_names]\n\
+             fr = \"Visionneuse \\\"Foo\\\"\"\n",
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(
+            plist.contains("<key>CFBundleDisplayName</key>\n  <string>Foo Viewer</string>"),
+            "{plist}"
+        );
+        assert!(plist.contains("<key>CFBundleName</key>\n  <string>Foo</string>"));
+        assert!(
+            plist.contains("<key>CFBundleLocalizations</key>\n  <array>\n    <string>fr</string>"),
+            "{plist}"
+        );
+        let resources_dir = tmp.path().join("Resources");
+        copy_localizations(&resources_dir, &settings).unwrap();
+        let strings =
+            std::fs::read_to_string(resources_dir.join("fr.lproj/InfoPlist.strings")).unwrap();
+        assert_eq!(
+            strings,
+            "\"CFBundleDisplayName\" = \"Visionneuse \\\"Foo\\\"\";\n"
+        );
+    }
+
+    #[test]
+    fn info_plist_exts_merge_in_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = tmp.path().join("first.plist");
+        let second = tmp.path().join("second.plist");
+        std::fs::write(
+            &first,
+            "<key>NSCameraUsageDescription</key>\n<string>First</string>\n\
+             <key>LSUIElement</key>\n<true/>\n\
+             <key>CFBundleDevelopmentRegion</key>\n<string>fr</string>\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &second,
+            "<key>NSCameraUsageDescription</key>\n<string>Second</string>\n\
+             <key>LSUIElement</key>\n<false/>\n",
+        )
+        .unwrap();
+        write_fixture_package(
+            tmp.path(),
+            &format!(
+                "\n\
+                 [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n\
+                 osx_info_plist_exts = [{first:?}, {second:?}]\n\
+                 \n\
+                 [package.metadata.bundle.osx_info_plist_keys]\nLSUIElement = true\n",
+            ),
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+        let settings = Settings::builder(info).build().unwrap();
+
+        create_info_plist(tmp.path(), None, &settings).unwrap();
+        let plist = std::fs::read_to_string(tmp.path().join("Info.plist")).unwrap();
+        assert!(
+            plist.contains("<key>NSCameraUsageDescription</key>\n  <string>Second</string>"),
+            "{plist}"
+        );
+        assert!(plist.contains("<key>CFBundleDevelopmentRegion</key>\n  <string>fr</string>"));
+        assert!(plist.contains("<key>LSUIElement</key>\n  <true/>"));
+        assert_eq!(plist.matches("NSCameraUsageDescription").count(), 1);
+    }
+}
@@ -0,0 +1,362 @@
+// A minimal model of XML property lists, used to generate the `Info.plist`
+// files of macOS and iOS bundles.  Dictionaries keep their keys sorted, so
+// the generated files don't depend on the order settings were applied in.
+
+use anyhow::Context;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use quick_xml::name::QName;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+pub const HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+    <!DOCTYPE plist PUBLIC \"-//Apple Computer//DTD PLIST 1.0//EN\" \
+    \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+    <plist version=\"1.0\">\n\
+    <dict>\n";
+
+pub const FOOTER: &str = "</dict>\n</plist>\n";
+
+pub type Dictionary = BTreeMap<String, Value>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Boolean(bool),
+    Integer(i64),
+    Real(f64),
+    Date(String),
+    Data(String),
+    Array(Vec<Value>),
+    Dictionary(Dictionary),
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl TryFrom<&toml::Value> for Value {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &toml::Value) -> crate::Result<Self> {
+        Ok(match value {
+            toml::Value::String(string) => Value::String(string.clone()),
+            toml::Value::Integer(integer) => Value::Integer(*integer),
+            toml::Value::Float(real) => Value::Real(*real),
+            toml::Value::Boolean(boolean) => Value::Boolean(*boolean),
+            toml::Value::Array(array) => Value::Array(
+                array
+                    .iter()
+                    .map(Value::try_from)
+                    .collect::<crate::Result<_>>()?,
+            ),
+            toml::Value::Table(table) => Value::Dictionary(
+                table
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), Value::try_from(value)?)))
+                    .collect::<crate::Result<_>>()?,
+            ),
+            toml::Value::Datetime(datetime) => {
+                anyhow::bail!("Dates are not supported as plist values: {datetime}")
+            }
+        })
+    }
+}
+
+/// Converts user-provided TOML values, e.g. the `osx_info_plist_keys`
+/// setting, to a plist dictionary.
+pub fn dictionary_from_toml<'a>(
+    values: impl IntoIterator<Item = (&'a String, &'a toml::Value)>,
+) -> crate::Result<Dictionary> {
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            let value = Value::try_from(value)
+                .with_context(|| format!("Invalid value for Info.plist key {key:?}"))?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Merges `overrides` into `base`.  Dictionaries present in both are merged
+/// recursively, and any other value in `overrides` replaces the one in `base`.
+pub fn merge(base: &mut Dictionary, overrides: Dictionary) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Dictionary(base_dict)), Value::Dictionary(dict)) => merge(base_dict, dict),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Parses the top-level dictionary of a plist file.  Partial plists, holding
+/// just the `<key>` and value pairs of the dictionary, are accepted too.
+pub fn parse_dictionary(xml: &str) -> crate::Result<Dictionary> {
+    let xml = escape_bare_ampersands(xml);
+    let document = if xml.contains("<plist") {
+        xml
+    } else {
+        Cow::Owned(format!("<dict>{xml}</dict>"))
+    };
+    let mut reader = Reader::from_str(&document);
+    reader.config_mut().trim_text(true);
+    loop {
+        match next_element(&mut reader)? {
+            Element::Start(name) if name == "dict" => return parse_dict(&mut reader),
+            Element::Empty(name) if name == "dict" => return Ok(Dictionary::new()),
+            Element::Start(name) if name == "plist" => {}
+            Element::Start(name) | Element::Empty(name) => {
+                anyhow::bail!("Expected a <dict> at the top of the plist, found <{name}>")
+            }
+            Element::End | Element::Eof => anyhow::bail!("No <dict> found in the plist"),
+        }
+    }
+}
+
+enum Element {
+    Start(String),
+    Empty(String),
+    End,
+    Eof,
+}
+
+/// Returns the next element event, skipping the XML declaration, doctype and
+/// comments.
+fn next_element(reader: &mut Reader<&[u8]>) -> crate::Result<Element> {
+    loop {
+        let element = match reader.read_event()? {
+            Event::Start(start) => Element::Start(element_name(start.name())),
+            Event::Empty(start) => Element::Empty(element_name(start.name())),
+            Event::End(_) => Element::End,
+            Event::Eof => Element::Eof,
+            Event::Text(text) => {
+                anyhow::bail!("Unexpected text in plist: {:?}", text.decode()?)
+            }
+            _ => continue,
+        };
+        return Ok(element);
+    }
+}
+
+fn element_name(name: QName) -> String {
+    String::from_utf8_lossy(name.as_ref()).into_owned()
+}
+
+fn read_text(reader: &mut Reader<&[u8]>, name: &str) -> crate::Result<String> {
+    let raw = reader.read_text(QName(name.as_bytes()))?;
+    Ok(quick_xml::escape::unescape(&raw)?.into_owned())
+}
+
+fn parse_dict(reader: &mut Reader<&[u8]>) -> crate::Result<Dictionary> {
+    let mut dict = Dictionary::new();
+    loop {
+        let key = match next_element(reader)? {
+            Element::End => return Ok(dict),
+            Element::Start(name) if name == "key" => read_text(reader, "key")?,
+            Element::Start(name) | Element::Empty(name) => {
+                anyhow::bail!("Expected a <key> in plist <dict>, found <{name}>")
+            }
+            Element::Eof => anyhow::bail!("Unterminated <dict> in plist"),
+        };
+        let value = match next_element(reader)? {
+            Element::Start(name) => parse_value(reader, &name, false),
+            Element::Empty(name) => parse_value(reader, &name, true),
+            Element::End | Element::Eof => anyhow::bail!("Missing value for plist key {key:?}"),
+        }
+        .with_context(|| format!("Invalid value for plist key {key:?}"))?;
+        dict.insert(key, value);
+    }
+}
+
+fn parse_value(reader: &mut Reader<&[u8]>, name: &str, empty: bool) -> crate::Result<Value> {
+    let text = |reader: &mut Reader<&[u8]>| -> crate::Result<String> {
+        if empty {
+            Ok(String::new())
+        } else {
+            read_text(reader, name)
+        }
+    };
+    Ok(match name {
+        "true" | "false" => {
+            text(reader)?;
+            Value::Boolean(name == "true")
+        }
+        "string" => Value::String(text(reader)?),
+        "integer" => Value::Integer(text(reader)?.trim().parse()?),
+        "real" => Value::Real(text(reader)?.trim().parse()?),
+        "date" => Value::Date(text(reader)?.trim().to_string()),
+        "data" => Value::Data(text(reader)?.trim().to_string()),
+        "array" if empty => Value::Array(Vec::new()),
+        "array" => {
+            let mut array = Vec::new();
+            loop {
+                match next_element(reader)? {
+                    Element::End => break,
+                    Element::Start(name) => array.push(parse_value(reader, &name, false)?),
+                    Element::Empty(name) => array.push(parse_value(reader, &name, true)?),
+                    Element::Eof => anyhow::bail!("Unterminated <array> in plist"),
+                }
+            }
+            Value::Array(array)
+        }
+        "dict" if empty => Value::Dictionary(Dictionary::new()),
+        "dict" => Value::Dictionary(parse_dict(reader)?),
+        other => anyhow::bail!("Unsupported plist element <{other}>"),
+    })
+}
+
+/// Escapes any `&` that doesn't start an XML entity reference.  Partial plist
+/// files used to be pasted into `Info.plist` with their `&`s escaped, so they
+/// may contain bare ones.
+fn escape_bare_ampersands(xml: &str) -> Cow<'_, str> {
+    let is_reference = |rest: &str| {
+        let Some(end) = rest.find(';') else {
+            return false;
+        };
+        let name = &rest[1..end];
+        matches!(name, "amp" | "lt" | "gt" | "quot" | "apos")
+            || name
+                .strip_prefix("#x")
+                .is_some_and(|hex| !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            || name
+                .strip_prefix('#')
+                .is_some_and(|dec| !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit()))
+    };
+    if !xml
+        .match_indices('&')
+        .any(|(i, _)| !is_reference(&xml[i..]))
+    {
+        return Cow::Borrowed(xml);
+    }
+    let mut output = String::with_capacity(xml.len());
+    for (i, c) in xml.char_indices() {
+        if c == '&' && !is_reference(&xml[i..]) {
+            output.push_str("&amp;");
+        } else {
+            output.push(c);
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Writes the entries of a top-level dictionary, i.e. everything between the
+/// [`HEADER`] and the [`FOOTER`].
+pub fn write_entries<W: Write>(writer: &mut W, dict: &Dictionary) -> crate::Result<()> {
+    for (key, value) in dict {
+        writeln!(writer, "  <key>{}</key>", escape(key))?;
+        write_value(writer, value, 1)?;
+    }
+    Ok(())
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value, depth: usize) -> crate::Result<()> {
+    let indent = "  ".repeat(depth);
+    match value {
+        Value::String(string) => writeln!(writer, "{indent}<string>{}</string>", escape(string))?,
+        Value::Boolean(boolean) => writeln!(writer, "{indent}<{boolean}/>")?,
+        Value::Integer(integer) => writeln!(writer, "{indent}<integer>{integer}</integer>")?,
+        Value::Real(real) => writeln!(writer, "{indent}<real>{real}</real>")?,
+        Value::Date(date) => writeln!(writer, "{indent}<date>{}</date>", escape(date))?,
+        Value::Data(data) => writeln!(writer, "{indent}<data>{}</data>", escape(data))?,
+        Value::Array(array) => {
+            writeln!(writer, "{indent}<array>")?;
+            for value in array {
+                write_value(writer, value, depth + 1)?;
+            }
+            writeln!(writer, "{indent}</array>")?;
+        }
+        Value::Dictionary(dict) => {
+            writeln!(writer, "{indent}<dict>")?;
+            for (key, value) in dict {
+                writeln!(writer, "{indent}  <key>{}</key>", escape(key))?;
+                write_value(writer, value, depth + 1)?;
+            }
+            writeln!(writer, "{indent}</dict>")?;
+        }
+    }
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dictionary, Value, dictionary_from_toml, merge, parse_dictionary, write_entries};
+
+    #[test]
+    fn write_nested_values() {
+        let table: toml::Table = toml::from_str(
+            "name = \"Tom & Jerry\"\nagent = true\ncount = 3\n\
+             fonts = [\"a.ttf\"]\nnested = { ratio = 0.5 }\n",
+        )
+        .unwrap();
+        let dict = dictionary_from_toml(&table).unwrap();
+        let mut output = Vec::new();
+        write_entries(&mut output, &dict).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "  <key>agent</key>\n  <true/>\n  \
+             <key>count</key>\n  <integer>3</integer>\n  \
+             <key>fonts</key>\n  <array>\n    <string>a.ttf</string>\n  </array>\n  \
+             <key>name</key>\n  <string>Tom &amp; Jerry</string>\n  \
+             <key>nested</key>\n  <dict>\n    <key>ratio</key>\n    <real>0.5</real>\n  </dict>\n"
+        );
+
+        let date = toml::Value::Datetime("1979-05-27".parse().unwrap());
+        assert!(Value::try_from(&date).is_err());
+    }
+
+    #[test]
+    fn parse_and_merge_partial_plists() {
+        let mut dict = parse_dictionary(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <plist version=\"1.0\">\n<dict>\n\
+               <key>Name</key><string>Tom & Jerry</string>\n\
+               <key>Agent</key><false/>\n\
+               <key>Nested</key><dict><key>A</key><integer>1</integer></dict>\n\
+             </dict>\n</plist>\n",
+        )
+        .unwrap();
+        assert_eq!(dict["Name"], Value::from("Tom & Jerry"));
+
+        let overrides = parse_dictionary(
+            "<!-- A partial plist -->\n\
+             <key>Agent</key>\n<true/>\n\
+             <key>Nested</key>\n<dict><key>B</key><array><real>0.5</real></array></dict>\n",
+        )
+        .unwrap();
+        merge(&mut dict, overrides);
+        assert_eq!(dict["Agent"], Value::Boolean(true));
+        assert_eq!(
+            dict["Nested"],
+            Value::Dictionary(Dictionary::from([
+                ("A".to_string(), Value::Integer(1)),
+                ("B".to_string(), Value::Array(vec![Value::Real(0.5)])),
+            ]))
+        );
+
+        assert!(parse_dictionary("<key>Orphan</key>").is_err());
+        assert!(parse_dictionary("<key>Bad</key><widget/>").is_err());
+    }
+}
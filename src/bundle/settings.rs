@@ -4,7 +4,9 @@ use crate::bundle::target_info::BundleTargetInfo;
 
 use super::category::AppCategory;
 use super::common::print_warning;
+use anyhow::Context;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
 
@@ -12,23 +14,37 @@ use std::path::{Path, PathBuf};
 pub enum PackageType {
     OsxBundle,
     IosBundle,
+    IosIpa,
     WindowsMsi,
     WxsMsi,
+    WindowsZip,
     Deb,
     Rpm,
     AppImage,
+    TarGz,
+    TarXz,
 }
 
 impl PackageType {
     pub fn bundle_project(&self, settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+        common::check_tools(
+            self.required_tools(),
+            self.short_name(),
+            std::env::var_os("PATH").as_deref(),
+        )?;
         match self {
             PackageType::OsxBundle => super::osx_bundle::bundle_project(settings),
             PackageType::IosBundle => super::ios_bundle::bundle_project(settings),
+            PackageType::IosIpa => super::ios_ipa::bundle_project(settings),
             PackageType::WindowsMsi => super::msi_bundle::bundle_project(settings),
             PackageType::WxsMsi => super::wxsmsi_bundle::bundle_project(settings),
+            PackageType::WindowsZip => super::windows_zip::bundle_project(settings),
             PackageType::Deb => super::linux::deb_bundle::bundle_project(settings),
             PackageType::Rpm => super::linux::rpm_bundle::bundle_project(settings),
             PackageType::AppImage => super::linux::appimage_bundle::bundle_project(settings),
+            PackageType::TarGz | PackageType::TarXz => {
+                super::archive::bundle_project(settings, *self)
+            }
         }
     }
 }
@@ -73,11 +89,15 @@ impl PackageType {
         match name {
             "deb" => Some(PackageType::Deb),
             "ios" => Some(PackageType::IosBundle),
+            "ipa" => Some(PackageType::IosIpa),
             "msi" => Some(PackageType::WindowsMsi),
             "wxsmsi" => Some(PackageType::WxsMsi),
+            "zip" => Some(PackageType::WindowsZip),
             "osx" => Some(PackageType::OsxBundle),
             "rpm" => Some(PackageType::Rpm),
             "appimage" => Some(PackageType::AppImage),
+            "targz" => Some(PackageType::TarGz),
+            "tarxz" => Some(PackageType::TarXz),
             _ => None,
         }
     }
@@ -86,16 +106,110 @@ impl PackageType {
         match *self {
             PackageType::Deb => "deb",
             PackageType::IosBundle => "ios",
+            PackageType::IosIpa => "ipa",
             PackageType::WindowsMsi => "msi",
             PackageType::WxsMsi => "wxsmsi",
+            PackageType::WindowsZip => "zip",
             PackageType::OsxBundle => "osx",
             PackageType::Rpm => "rpm",
             PackageType::AppImage => "appimage",
+            PackageType::TarGz => "targz",
+            PackageType::TarXz => "tarxz",
+        }
+    }
+
+    /// Returns the external tools that bundling this format always runs.
+    /// Tools only needed for optional steps, like signing, aren't included.
+    pub const fn required_tools(&self) -> &'static [&'static str] {
+        match *self {
+            PackageType::WxsMsi => &["dotnet"],
+            PackageType::Rpm => &["rpmbuild"],
+            PackageType::AppImage => &["mksquashfs"],
+            PackageType::TarXz => &["xz"],
+            PackageType::OsxBundle
+            | PackageType::IosBundle
+            | PackageType::IosIpa
+            | PackageType::WindowsMsi
+            | PackageType::WindowsZip
+            | PackageType::Deb
+            | PackageType::TarGz => &[],
         }
     }
 
     pub const fn all() -> &'static [&'static str] {
-        &["deb", "ios", "msi", "wxsmsi", "osx", "rpm", "appimage"]
+        &[
+            "deb", "ios", "ipa", "msi", "wxsmsi", "zip", "osx", "rpm", "appimage", "targz", "tarxz",
+        ]
+    }
+}
+
+/// Build metadata recorded in bundles when `embed_build_info` is set.
+#[derive(Clone, Debug)]
+pub struct BuildInfo {
+    /// The `git describe` output for the package, or `"unknown"`.
+    pub git_commit: String,
+    /// The time of bundling, in RFC 3339 format.
+    pub timestamp: String,
+}
+
+impl BuildInfo {
+    /// Describes the package's git checkout and the current time.
+    fn collect(target: &BundleTargetInfo) -> BuildInfo {
+        let git_commit = target
+            .package
+            .manifest_path
+            .parent()
+            .and_then(|dir| common::git_describe(dir.as_std_path()))
+            .unwrap_or_else(|| "unknown".to_string());
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        BuildInfo {
+            git_commit,
+            timestamp,
+        }
+    }
+}
+
+/// The processor architecture an MSI installer targets.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MsiPlatform {
+    X86,
+    X64,
+    Arm64,
+}
+
+impl MsiPlatform {
+    /// Returns the platform name WiX expects, e.g. for `InstallerPlatform`.
+    pub fn wix_name(self) -> &'static str {
+        match self {
+            MsiPlatform::X86 => "x86",
+            MsiPlatform::X64 => "x64",
+            MsiPlatform::Arm64 => "arm64",
+        }
+    }
+
+    /// Returns the platform name used in the `Template` summary property.
+    pub fn summary_name(self) -> &'static str {
+        match self {
+            MsiPlatform::X86 => "Intel",
+            MsiPlatform::X64 => "x64",
+            MsiPlatform::Arm64 => "Arm64",
+        }
+    }
+
+    /// Returns true if the installer writes to the 64-bit parts of the
+    /// filesystem and registry.
+    pub fn is_64bit(self) -> bool {
+        self != MsiPlatform::X86
+    }
+
+    /// Returns the standard directory that programs for this platform are
+    /// installed under.
+    pub fn program_files_folder(self) -> &'static str {
+        if self.is_64bit() {
+            "ProgramFiles64Folder"
+        } else {
+            "ProgramFilesFolder"
+        }
     }
 }
 
@@ -113,15 +227,478 @@ pub struct Settings {
     build_artifact: BuildArtifact,
     all_features: bool,
     no_default_features: bool,
+    no_build: bool,
+    prebuilt_binary: Option<PathBuf>,
+    checksums: bool,
+    include_debug_symbols: bool,
+    progress: bool,
+    allow_dirty: bool,
+    force: bool,
+    clean: bool,
+    strict: bool,
+    no_appimage_integration: bool,
+    universal: bool,
+    notarize: bool,
+    arch: Option<String>,
+    sign_key: Option<String>,
+    manifest_out: Option<PathBuf>,
+    jobs: Option<u32>,
+    build_args: Vec<String>,
+    cargo_config: Vec<String>,
     bundle_settings: BundleSettings,
     binary_name: String,
+    executable_name: String,
+    build_info: Option<BuildInfo>,
+}
+
+/// Builds [`Settings`] for a package without going through the command line.
+/// Unless set explicitly, the bundle settings and binary name are read from
+/// the package's `Cargo.toml`.
+#[derive(Clone, Debug)]
+pub struct SettingsBuilder {
+    target: BundleTargetInfo,
+    features: Option<String>,
+    build_artifact: BuildArtifact,
+    all_features: bool,
+    no_default_features: bool,
+    no_build: bool,
+    prebuilt_binary: Option<PathBuf>,
+    checksums: bool,
+    include_debug_symbols: bool,
+    progress: bool,
+    allow_dirty: bool,
+    force: bool,
+    clean: bool,
+    strict: bool,
+    no_appimage_integration: bool,
+    no_default_icons: bool,
+    universal: bool,
+    notarize: bool,
+    arch: Option<String>,
+    sign_key: Option<String>,
+    manifest_out: Option<PathBuf>,
+    jobs: Option<u32>,
+    build_args: Vec<String>,
+    cargo_config: Vec<String>,
+    bundle_settings: Option<BundleSettings>,
+    overrides: Option<BundleSettings>,
+    binary_name: Option<String>,
+}
+
+impl SettingsBuilder {
+    /// Sets the crate features to build with, space or comma separated.
+    pub fn features(mut self, features: impl Into<String>) -> Self {
+        self.features = Some(features.into());
+        self
+    }
+
+    /// Sets which binary or example of the package to bundle.
+    pub fn build_artifact(mut self, build_artifact: BuildArtifact) -> Self {
+        self.build_artifact = build_artifact;
+        self
+    }
+
+    pub fn all_features(mut self, all_features: bool) -> Self {
+        self.all_features = all_features;
+        self
+    }
+
+    pub fn no_default_features(mut self, no_default_features: bool) -> Self {
+        self.no_default_features = no_default_features;
+        self
+    }
+
+    pub fn no_build(mut self, no_build: bool) -> Self {
+        self.no_build = no_build;
+        self
+    }
+
+    /// Sets an already-built executable to bundle instead of the cargo build
+    /// output.  This implies `no_build`.
+    pub fn prebuilt_binary(mut self, prebuilt_binary: impl Into<PathBuf>) -> Self {
+        self.prebuilt_binary = Some(prebuilt_binary.into());
+        self
+    }
+
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    pub fn include_debug_symbols(mut self, include_debug_symbols: bool) -> Self {
+        self.include_debug_symbols = include_debug_symbols;
+        self
+    }
+
+    /// Sets whether progress is always shown while copying resources.
+    pub fn progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn allow_dirty(mut self, allow_dirty: bool) -> Self {
+        self.allow_dirty = allow_dirty;
+        self
+    }
+
+    /// Sets whether existing bundle outputs are overwritten instead of being
+    /// an error.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Sets whether the format's bundle output directory is removed before
+    /// bundling.  As the packages of a workspace share that directory, it is
+    /// up to the caller to remove it with `remove_bundle_dir`, once before
+    /// bundling any of them.
+    pub fn clean(mut self, clean: bool) -> Self {
+        self.clean = clean;
+        self
+    }
+
+    /// Sets whether `icon` and `resources` entries matching no files are an
+    /// error rather than a warning.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether the AppImage opts out of desktop integration, regardless
+    /// of the `appimage_no_integration` setting.
+    pub fn no_appimage_integration(mut self, no_appimage_integration: bool) -> Self {
+        self.no_appimage_integration = no_appimage_integration;
+        self
+    }
+
+    /// Sets whether the package directory is left unsearched for an icon
+    /// when no `icon` is configured.
+    pub fn no_default_icons(mut self, no_default_icons: bool) -> Self {
+        self.no_default_icons = no_default_icons;
+        self
+    }
+
+    /// Sets whether a universal macOS binary is bundled, merged with `lipo`
+    /// from builds for each of the `UNIVERSAL_TARGETS`.
+    pub fn universal(mut self, universal: bool) -> Self {
+        self.universal = universal;
+        self
+    }
+
+    /// Sets whether macOS bundles are notarized by Apple and stapled after
+    /// signing.
+    pub fn notarize(mut self, notarize: bool) -> Self {
+        self.notarize = notarize;
+        self
+    }
+
+    /// Sets the architecture the bundle is labelled with, without changing
+    /// the build.  A target triple's architecture takes precedence.
+    pub fn arch(mut self, arch: impl Into<String>) -> Self {
+        self.arch = Some(arch.into());
+        self
+    }
+
+    /// Sets the path to write a listing of the bundled files to.
+    pub fn manifest_out(mut self, manifest_out: impl Into<PathBuf>) -> Self {
+        self.manifest_out = Some(manifest_out.into());
+        self
+    }
+
+    /// Sets the GPG key to sign deb and rpm packages with.
+    pub fn sign_key(mut self, sign_key: impl Into<String>) -> Self {
+        self.sign_key = Some(sign_key.into());
+        self
+    }
+
+    pub fn jobs(mut self, jobs: u32) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Sets extra arguments to pass to `cargo build`.
+    pub fn build_args(mut self, build_args: Vec<String>) -> Self {
+        self.build_args = build_args;
+        self
+    }
+
+    /// Sets the cargo configuration overrides to pass to `cargo build` with
+    /// `--config`, each either `KEY=VALUE` or the path of a TOML file.
+    pub fn cargo_config(mut self, cargo_config: Vec<String>) -> Self {
+        self.cargo_config = cargo_config;
+        self
+    }
+
+    /// Uses `bundle_settings` instead of the ones from `Cargo.toml`.
+    pub fn bundle_settings(mut self, bundle_settings: BundleSettings) -> Self {
+        self.bundle_settings = Some(bundle_settings);
+        self
+    }
+
+    /// Overrides individual bundle settings; any setting given here takes
+    /// precedence over the ones from `Cargo.toml` or `bundle_settings`.
+    pub fn overrides(mut self, overrides: BundleSettings) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    /// Sets the file name the binary is given in the bundle, instead of the
+    /// name of the selected `bin` target.  The built binary is still looked
+    /// up by the target's name.
+    pub fn binary_name(mut self, binary_name: impl Into<String>) -> Self {
+        self.binary_name = Some(binary_name.into());
+        self
+    }
+
+    /// Resolves and validates the settings.
+    pub fn build(mut self) -> crate::Result<Settings> {
+        if let BuildArtifact::Example(example) = &self.build_artifact {
+            self.build_artifact = BuildArtifact::Example(self.target.example_target_name(example)?);
+        }
+        let (package_settings, bundle_name) =
+            self.target.get_bundle_settings(&self.build_artifact)?;
+        let mut bundle_settings = self.bundle_settings.unwrap_or(package_settings);
+        if let Some(overrides) = self.overrides {
+            bundle_settings = overrides.merge(bundle_settings);
+        }
+        if bundle_settings.identifier.is_none() {
+            bundle_settings.identifier =
+                default_identifier(&self.build_artifact, &self.target.package.name);
+        }
+        if bundle_settings.icon.is_empty()
+            && !self.no_default_icons
+            && let Some(package_dir) = self.target.package.manifest_path.parent()
+            && let Some(icon) = default_icon(package_dir.as_std_path(), self.target.package_type)
+        {
+            print_warning(&format!(
+                "No `icon` is set for package \"{}\"; using {} (pass --no-default-icons to bundle without one)",
+                self.target.package.name,
+                icon.display()
+            ))?;
+            bundle_settings.icon = vec![icon.display().to_string()];
+        }
+        bundle_settings.validate(self.target.package_type)?;
+        // The target name is also the file name cargo gives the built binary.
+        let executable_name = if bundle_name.is_empty() {
+            self.target.package.name.to_string()
+        } else {
+            bundle_name
+        };
+        let binary_name = self.binary_name.unwrap_or_else(|| executable_name.clone());
+        if binary_name.is_empty() {
+            anyhow::bail!("The binary name must not be empty");
+        }
+        if binary_name.contains(['/', '\\']) {
+            anyhow::bail!("The binary name {binary_name:?} must be a file name, not a path");
+        }
+        if self.universal {
+            if self.target.package_type != PackageType::OsxBundle {
+                anyhow::bail!(
+                    "--universal can only be used with the osx format, not {}",
+                    self.target.package_type
+                );
+            }
+            if let Some(triple) = &self.target.target_triple {
+                anyhow::bail!("--universal can't be combined with a target triple ({triple})");
+            }
+        }
+        if self.notarize && self.target.package_type == PackageType::OsxBundle {
+            if bundle_settings.osx_signing_identity.is_none() {
+                anyhow::bail!(
+                    "--notarize needs an `osx_signing_identity`, as Apple only notarizes apps \
+                     signed with a Developer ID"
+                );
+            }
+            if bundle_settings.osx_notarize_keychain_profile.is_none() {
+                anyhow::bail!(
+                    "--notarize needs an `osx_notarize_keychain_profile`; create one with \
+                     `xcrun notarytool store-credentials`"
+                );
+            }
+        }
+        if let Some(arch) = &self.arch
+            && !ARCHES.contains(&arch.as_str())
+        {
+            anyhow::bail!(
+                "Unknown architecture {arch:?}; expected one of {}",
+                ARCHES.join(", ")
+            );
+        }
+        if let Some(config) = self
+            .cargo_config
+            .iter()
+            .find(|config| !config.contains('=') && !config.ends_with(".toml"))
+        {
+            anyhow::bail!(
+                "Invalid cargo config override {config:?}; expected KEY=VALUE (e.g. \
+                 `net.git-fetch-with-cli=true`) or the path of a TOML file"
+            );
+        }
+        if self.jobs == Some(0) {
+            anyhow::bail!("The number of jobs must be at least 1");
+        }
+        if let Some(sign_key) = &self.sign_key
+            && sign_key.trim().is_empty()
+        {
+            anyhow::bail!("The signing key must not be empty");
+        }
+        if let Some(binary) = &self.prebuilt_binary {
+            let metadata = std::fs::metadata(binary)
+                .with_context(|| format!("The prebuilt binary {binary:?} does not exist"))?;
+            if !metadata.is_file() || !is_executable(&metadata) {
+                anyhow::bail!("The prebuilt binary {binary:?} is not an executable file");
+            }
+        }
+        for name in &bundle_settings.osx_additional_binaries {
+            if !self.target.has_bin_target(name) {
+                anyhow::bail!(
+                    "osx_additional_binaries: no `bin` target named '{name}' is found in package '{}'",
+                    self.target.package.name
+                );
+            }
+            if *name == binary_name {
+                anyhow::bail!(
+                    "osx_additional_binaries: '{name}' is already the binary being bundled"
+                );
+            }
+        }
+        if let Some(version) = &bundle_settings.ios_minimum_os_version
+            && !is_os_version(version)
+        {
+            anyhow::bail!(
+                "Invalid ios_minimum_os_version {version:?}; expected a version like \"13.0\""
+            );
+        }
+        if let Some(family) = bundle_settings
+            .ios_device_family
+            .iter()
+            .flatten()
+            .find(|family| !matches!(family, 1 | 2))
+        {
+            anyhow::bail!("Invalid ios_device_family {family}; expected 1 (iPhone) or 2 (iPad)");
+        }
+        if let Some(orientation) = bundle_settings
+            .ios_supported_orientations
+            .iter()
+            .flatten()
+            .find(|orientation| !IOS_ORIENTATIONS.contains(&orientation.as_str()))
+        {
+            anyhow::bail!(
+                "Invalid ios_supported_orientations entry {orientation:?}; expected one of {}",
+                IOS_ORIENTATIONS.join(", ")
+            );
+        }
+        if let Some(compression) = &bundle_settings.appimage_compression
+            && !APPIMAGE_COMPRESSIONS.contains(&compression.as_str())
+        {
+            anyhow::bail!(
+                "Invalid appimage_compression {compression:?}; expected one of {}",
+                APPIMAGE_COMPRESSIONS.join(", ")
+            );
+        }
+        if let Some(priority) = &bundle_settings.deb_priority
+            && !DEB_PRIORITIES.contains(&priority.as_str())
+        {
+            anyhow::bail!(
+                "Invalid deb_priority {priority:?}; expected one of {}",
+                DEB_PRIORITIES.join(", ")
+            );
+        }
+        // Collected once, so that every bundle of the run records the same
+        // commit and time.
+        let build_info = bundle_settings
+            .embed_build_info
+            .unwrap_or(false)
+            .then(|| BuildInfo::collect(&self.target));
+        let settings = Settings {
+            target: self.target,
+            features: self.features,
+            build_artifact: self.build_artifact,
+            all_features: self.all_features,
+            no_default_features: self.no_default_features,
+            no_build: self.no_build || std::env::var_os("CARGO_BUNDLE_SKIP_BUILD").is_some(),
+            prebuilt_binary: self.prebuilt_binary,
+            checksums: self.checksums,
+            include_debug_symbols: self.include_debug_symbols,
+            progress: self.progress,
+            allow_dirty: self.allow_dirty,
+            force: self.force,
+            clean: self.clean,
+            strict: self.strict,
+            no_appimage_integration: self.no_appimage_integration,
+            universal: self.universal,
+            notarize: self.notarize,
+            arch: self.arch,
+            sign_key: self.sign_key,
+            manifest_out: self.manifest_out,
+            jobs: self.jobs,
+            build_args: self.build_args,
+            cargo_config: self.cargo_config,
+            bundle_settings,
+            binary_name,
+            executable_name,
+            build_info,
+        };
+        settings.validate_identifier()?;
+        Ok(settings)
+    }
 }
 
 impl Settings {
+    /// Returns a builder for the settings of bundling `target`.
+    pub fn builder(target: BundleTargetInfo) -> SettingsBuilder {
+        SettingsBuilder {
+            target,
+            features: None,
+            build_artifact: BuildArtifact::Main,
+            all_features: false,
+            no_default_features: false,
+            no_build: false,
+            prebuilt_binary: None,
+            checksums: false,
+            include_debug_symbols: false,
+            progress: false,
+            allow_dirty: false,
+            force: false,
+            clean: false,
+            strict: false,
+            no_appimage_integration: false,
+            no_default_icons: false,
+            universal: false,
+            notarize: false,
+            arch: None,
+            sign_key: None,
+            manifest_out: None,
+            jobs: None,
+            build_args: Vec::new(),
+            cargo_config: Vec::new(),
+            bundle_settings: None,
+            overrides: None,
+            binary_name: None,
+        }
+    }
+
     pub fn get_target_dir(&self) -> PathBuf {
-        self.target.get_target_dir(&self.build_artifact)
+        if self.universal {
+            self.target
+                .target_dir_for(Some(UNIVERSAL_TARGET_DIR), &self.build_artifact)
+        } else {
+            self.target.get_target_dir(&self.build_artifact)
+        }
+    }
+
+    /// Returns the directory that bundles of the `package_type` format are
+    /// written to, under `bundle/` in the target directory.
+    pub fn bundle_dir(&self, package_type: PackageType) -> PathBuf {
+        let format_dir = match package_type {
+            PackageType::WxsMsi => "wsxmsi",
+            other => other.short_name(),
+        };
+        self.get_target_dir().join("bundle").join(format_dir)
     }
 
+    /// Returns the settings for bundling `bundle_info` as requested on the
+    /// command line.
     pub fn new(bundle_info: &BundleTargetInfo, cli: &crate::Cli) -> crate::Result<Self> {
         let build_artifact = if let Some(bin) = cli.bin.as_ref() {
             BuildArtifact::Bin(bin.to_string())
@@ -130,51 +707,181 @@ impl Settings {
         } else {
             BuildArtifact::Main
         };
-        let all_features = cli.all_features;
-        let no_default_features = cli.no_default_features;
-        let features = cli.features.as_ref().map(|features| features.into());
-        let (bundle_settings, bundle_name) = bundle_info.get_bundle_settings(&build_artifact);
+        let mut overrides = BundleSettings::from_overrides(&cli.config)?;
+        if let Some(command) = cli.before_build_command.as_ref() {
+            overrides.before_build_command = Some(command.clone());
+        }
+        if !cli.build_env.is_empty() {
+            let mut build_env = HashMap::new();
+            for entry in &cli.build_env {
+                match entry.split_once('=') {
+                    Some((key, value)) if !key.is_empty() => {
+                        build_env.insert(key.to_string(), value.to_string());
+                    }
+                    _ => anyhow::bail!("Invalid --build-env value {entry:?}, expected KEY=VALUE"),
+                }
+            }
+            overrides.build_env = Some(build_env);
+        }
+        let mut builder = Settings::builder(bundle_info.clone())
+            .build_artifact(build_artifact)
+            .all_features(cli.all_features)
+            .no_default_features(cli.no_default_features)
+            .no_build(cli.no_build)
+            .checksums(cli.checksums)
+            .include_debug_symbols(cli.include_debug_symbols)
+            .progress(cli.progress)
+            .allow_dirty(cli.allow_dirty)
+            // Rebundling while watching replaces the previous bundles.
+            .force(cli.force || cli.watch)
+            .clean(cli.clean)
+            .strict(cli.strict)
+            .no_appimage_integration(cli.no_appimage_integration)
+            .no_default_icons(cli.no_default_icons)
+            .universal(cli.universal)
+            .notarize(cli.notarize)
+            .build_args(cli.build_args.clone())
+            .cargo_config(cli.cargo_config.clone())
+            .overrides(overrides);
+        if let Some(features) = cli.features.as_ref() {
+            builder = builder.features(features);
+        }
+        if let Some(sign_key) = cli.sign_key.as_ref() {
+            builder = builder.sign_key(sign_key);
+        }
+        if let Some(binary_path) = cli.binary_path.as_ref() {
+            builder = builder.prebuilt_binary(binary_path);
+        }
+        if let Some(manifest_out) = cli.manifest_out.as_ref() {
+            builder = builder.manifest_out(manifest_out);
+        }
+        if let Some(arch) = cli.arch.as_ref() {
+            builder = builder.arch(arch);
+        }
+        if let Some(jobs) = cli.jobs {
+            builder = builder.jobs(jobs);
+        }
+        builder.build()
+    }
 
-        let binary_name = if bundle_name.is_empty() {
-            bundle_info.package.name.to_string()
-        } else {
-            bundle_name
+    /// Checks that the bundle identifier is in reverse-DNS form.  Apple
+    /// platforms refuse to sign or install bundles with malformed
+    /// identifiers, so this is an error for `osx` and `ios` bundles and a
+    /// warning for everything else.
+    pub fn validate_identifier(&self) -> crate::Result<()> {
+        let Some(msg) = self.identifier_problem() else {
+            return Ok(());
         };
-        Ok(Settings {
-            target: bundle_info.clone(),
-            features,
-            build_artifact,
-            all_features,
-            no_default_features,
-            bundle_settings,
-            binary_name,
-        })
+        match self.target.package_type {
+            PackageType::OsxBundle | PackageType::IosBundle | PackageType::IosIpa => {
+                anyhow::bail!(msg)
+            }
+            _ => print_warning(&msg),
+        }
+    }
+
+    /// Describes what is wrong with the bundle identifier, if anything.  Only
+    /// Apple bundles need one, so for other formats a missing identifier is
+    /// fine.
+    fn identifier_problem(&self) -> Option<String> {
+        let apple = matches!(
+            self.target.package_type,
+            PackageType::OsxBundle | PackageType::IosBundle | PackageType::IosIpa
+        );
+        if !apple && self.bundle_settings.identifier.is_none() {
+            return None;
+        }
+        let identifier = self.bundle_identifier();
+        if is_reverse_dns(&identifier) {
+            return None;
+        }
+        Some(format!(
+            "Bundle identifier {identifier:?} is not in reverse-DNS form (e.g. \"com.example.app\")"
+        ))
     }
 
     /// Returns the architecture for the binary being bundled (e.g. "arm" or
-    /// "x86" or "x86_64").
+    /// "x86" or "x86_64"): the one of the target triple, or else the `--arch`
+    /// override, or else the host's.
     pub fn binary_arch(&self) -> &str {
-        if let Some(ref info) = self.target.target_info {
+        if self.universal {
+            "universal"
+        } else if let Some(ref info) = self.target.target_info {
             info.target_arch()
+        } else if let Some(arch) = &self.arch {
+            arch
         } else {
             std::env::consts::ARCH
         }
     }
 
+    /// Returns the MSI platform matching the binary's architecture, failing
+    /// for architectures Windows Installer doesn't support.
+    pub fn msi_platform(&self) -> crate::Result<MsiPlatform> {
+        match self.binary_arch() {
+            "x86" => Ok(MsiPlatform::X86),
+            "x86_64" => Ok(MsiPlatform::X64),
+            "aarch64" => Ok(MsiPlatform::Arm64),
+            arch => anyhow::bail!("Unsupported architecture {arch:?} for MSI installers"),
+        }
+    }
+
     /// Returns the file name of the binary being bundled.
     pub fn binary_name(&self) -> String {
         self.binary_name.clone()
     }
 
-    /// Returns the path to the binary being bundled.
+    /// Returns the path of the executable to bundle: the prebuilt binary if
+    /// one was given, or else the cargo build output, which is named after the
+    /// selected target rather than `binary_name`.
     pub fn binary_path(&self, target: PackageType) -> PathBuf {
-        let binary_name = self.binary_name();
+        if let Some(binary) = &self.prebuilt_binary {
+            return binary.clone();
+        }
         match target {
-            PackageType::WindowsMsi | PackageType::WxsMsi => {
-                self.get_target_dir().join(format!("{}.exe", binary_name))
-            }
-            _ => self.get_target_dir().join(&binary_name),
+            PackageType::WindowsMsi | PackageType::WxsMsi | PackageType::WindowsZip => self
+                .get_target_dir()
+                .join(format!("{}.exe", self.executable_name)),
+            _ => self.get_target_dir().join(&self.executable_name),
+        }
+    }
+
+    /// Returns the effective bundle settings, after merging in the
+    /// format-, bin- or example-specific sections and any overrides.
+    pub fn bundle_settings(&self) -> &BundleSettings {
+        &self.bundle_settings
+    }
+
+    /// Returns the path to write a listing of the bundled files to, if any.
+    pub fn manifest_out(&self) -> Option<&Path> {
+        self.manifest_out.as_deref()
+    }
+
+    /// Fails with a helpful message, naming the profile and target it should
+    /// have been built for, if the binary to bundle hasn't been built.
+    pub fn validate_binary_exists(&self) -> crate::Result<()> {
+        let binary_path = self.binary_path(self.target.package_type);
+        if binary_path.is_file() {
+            return Ok(());
+        }
+        if self.prebuilt_binary.is_some() {
+            anyhow::bail!("Binary {binary_path:?} given with --binary-path is not a file");
         }
+        let target = match self.target_triple() {
+            Some(triple) => format!("target {triple}"),
+            None if self.universal => format!("targets {}", UNIVERSAL_TARGETS.join(" and ")),
+            None => "the host target".to_string(),
+        };
+        anyhow::bail!(
+            "Binary {binary_path:?} does not exist; it should have been built with the `{}` \
+             profile for {target}{}",
+            self.build_profile(),
+            if self.no_build() {
+                ", but the build was skipped"
+            } else {
+                ""
+            }
+        );
     }
 
     /// If the bundle is being cross-compiled, returns the target triple string
@@ -187,6 +894,25 @@ impl Settings {
         }
     }
 
+    /// Returns the file name (without extension) to give the bundle, rendered
+    /// from the `filename_template` setting, or `default` if there is none.
+    /// `arch` is the architecture name as spelled by the bundle format.
+    pub fn artifact_name(&self, arch: &str, default: String) -> String {
+        match self.bundle_settings.filename_template {
+            Some(ref template) => common::render_filename(
+                template,
+                &[
+                    ("name", &self.bundle_name()),
+                    ("version", &self.version_string().to_string()),
+                    ("arch", arch),
+                    ("target", self.target_triple().unwrap_or(arch)),
+                    ("format", self.target.package_type.short_name()),
+                ],
+            ),
+            None => default,
+        }
+    }
+
     pub fn features(&self) -> Option<&str> {
         match self.features {
             Some(ref features) => Some(features.as_str()),
@@ -212,6 +938,164 @@ impl Settings {
         self.no_default_features
     }
 
+    /// Returns true if `cargo build` should be skipped, which is always the
+    /// case when bundling a prebuilt binary or when `CARGO_BUNDLE_SKIP_BUILD`
+    /// is set.
+    pub fn no_build(&self) -> bool {
+        self.no_build || self.prebuilt_binary.is_some()
+    }
+
+    /// Returns true if a SHA-256 checksum file should be written next to each
+    /// produced bundle.
+    pub fn emit_checksums(&self) -> bool {
+        self.checksums || self.bundle_settings.emit_checksums.unwrap_or(false)
+    }
+
+    /// Returns true if the binary's debug symbols should be written to a
+    /// `.dSYM` bundle (macOS) or `.debug` file (Linux) next to each bundle.
+    pub fn include_debug_symbols(&self) -> bool {
+        self.include_debug_symbols
+    }
+
+    /// Returns true if progress should be shown while copying resources even
+    /// if there are only a few of them or stderr isn't a terminal.
+    pub fn progress(&self) -> bool {
+        self.progress
+    }
+
+    /// Returns true if existing bundle outputs should be overwritten rather
+    /// than causing an error.
+    pub fn force(&self) -> bool {
+        self.force
+    }
+
+    /// Returns true if `icon` and `resources` entries matching no files are an
+    /// error rather than a warning.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Returns true if a universal macOS binary is bundled.
+    pub fn universal(&self) -> bool {
+        self.universal
+    }
+
+    /// Returns true if macOS bundles are notarized and stapled.
+    pub fn notarize(&self) -> bool {
+        self.notarize
+    }
+
+    /// Fails with a hint on how to install them if any of the
+    /// `UNIVERSAL_TARGETS` isn't installed.  The check is skipped if rustup
+    /// isn't available to tell.
+    pub fn check_universal_targets(&self) -> crate::Result<()> {
+        let mut rustup = std::process::Command::new("rustup");
+        rustup.args(["target", "list", "--installed"]);
+        let Ok(output) = common::run_command(&mut rustup, common::TOOL_TIMEOUT) else {
+            return Ok(());
+        };
+        let installed = String::from_utf8_lossy(&output.stdout);
+        for triple in UNIVERSAL_TARGETS {
+            if !installed.lines().any(|line| line.trim() == triple) {
+                anyhow::bail!(
+                    "Target {triple} is not installed, but --universal needs it; install it \
+                     with `rustup target add {triple}`"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the binaries built for each of the `UNIVERSAL_TARGETS` into
+    /// universal binaries with `lipo`.
+    pub fn merge_universal_binaries(&self) -> crate::Result<()> {
+        let mut binaries = vec![(self.build_artifact.clone(), self.executable_name.clone())];
+        for name in self.osx_additional_binaries() {
+            binaries.push((BuildArtifact::Main, name.clone()));
+        }
+        for (build_artifact, name) in binaries {
+            let output = self
+                .target
+                .target_dir_for(Some(UNIVERSAL_TARGET_DIR), &build_artifact)
+                .join(&name);
+            std::fs::create_dir_all(output.parent().unwrap())
+                .with_context(|| format!("Failed to create a directory for {output:?}"))?;
+            let mut lipo = std::process::Command::new("lipo");
+            lipo.arg("-create").arg("-output").arg(&output);
+            for triple in UNIVERSAL_TARGETS {
+                lipo.arg(
+                    self.target
+                        .target_dir_for(Some(triple), &build_artifact)
+                        .join(&name),
+                );
+            }
+            common::run_command(&mut lipo, common::TOOL_TIMEOUT).map_err(|error| {
+                let not_found = error
+                    .root_cause()
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|error| error.kind() == std::io::ErrorKind::NotFound);
+                if not_found {
+                    anyhow::anyhow!(
+                        "`lipo` was not found, but --universal needs it to merge the binaries; \
+                         install the Xcode command line tools with `xcode-select --install`"
+                    )
+                } else {
+                    error.context(format!("Failed to create the universal binary {output:?}"))
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if previous outputs of the format being bundled should be
+    /// removed before bundling, see `SettingsBuilder::clean`.
+    pub fn clean(&self) -> bool {
+        self.clean
+    }
+
+    /// Returns true if bundling should fail when the git working tree has
+    /// uncommitted changes, i.e. `require_clean_tree` is set and
+    /// `--allow-dirty` isn't.
+    pub fn require_clean_tree(&self) -> bool {
+        !self.allow_dirty && self.bundle_settings.require_clean_tree.unwrap_or(false)
+    }
+
+    /// Returns the git commit and build time to record in the bundle, if the
+    /// `embed_build_info` setting is enabled.
+    pub fn build_info(&self) -> Option<BuildInfo> {
+        self.build_info.clone()
+    }
+
+    /// Returns the number of parallel jobs to pass to `cargo build`, if set.
+    pub fn jobs(&self) -> Option<u32> {
+        self.jobs
+    }
+
+    /// Returns the shell command to run before `cargo build`, if any.
+    pub fn before_build_command(&self) -> Option<&str> {
+        self.bundle_settings.before_build_command.as_deref()
+    }
+
+    /// Returns the environment variables to set for `cargo build`.
+    pub fn build_env(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.bundle_settings
+            .build_env
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the extra arguments to pass through to `cargo build`.
+    pub fn build_args(&self) -> &[String] {
+        self.build_args.as_slice()
+    }
+
+    /// Returns the cargo configuration overrides to pass to `cargo build`
+    /// with `--config`.
+    pub fn cargo_config(&self) -> &[String] {
+        self.cargo_config.as_slice()
+    }
+
     pub fn bundle_name(&self) -> String {
         if self.bundle_settings.name.is_empty() {
             self.binary_name()
@@ -221,44 +1105,109 @@ impl Settings {
     }
 
     pub fn bundle_identifier(&self) -> Cow<'_, str> {
-        if let Some(identifier) = &self.bundle_settings.identifier {
-            identifier.into()
-        } else {
-            match &self.build_artifact {
-                BuildArtifact::Main => "".into(),
-                BuildArtifact::Bin(name) => format!("{name}.{}", self.target.package.name).into(),
-                BuildArtifact::Example(name) => {
-                    format!("{name}.example.{}", self.target.package.name).into()
-                }
-            }
-        }
+        self.bundle_settings
+            .identifier
+            .as_deref()
+            .unwrap_or_default()
+            .into()
     }
 
     /// Returns an iterator over the icon files to be used for this bundle.
-    pub fn icon_files(&self) -> ResourcePaths<'_> {
+    pub fn icon_files(&self) -> ResourcePaths {
         ResourcePaths::new(self.bundle_settings.icon.as_slice(), false)
     }
 
+    /// Returns the `icon` and `resources` entries that match no files, which
+    /// is usually a typo in the path.
+    pub fn unmatched_patterns(&self) -> Vec<&str> {
+        let icons = self.bundle_settings.icon.iter().map(|icon| (icon, false));
+        let resources = self
+            .bundle_settings
+            .resources_mapping
+            .iter()
+            .map(|(pattern, _)| (pattern, true));
+        icons
+            .chain(resources)
+            .filter(|(pattern, allow_walk)| {
+                ResourcePaths::new(std::slice::from_ref(*pattern), *allow_walk)
+                    .next()
+                    .is_none()
+            })
+            .map(|(pattern, _)| pattern.as_str())
+            .collect()
+    }
+
+    /// Warns about each `icon` and `resources` entry that matches no files,
+    /// or fails listing them in strict mode.
+    pub fn check_patterns_match(&self) -> crate::Result<()> {
+        let unmatched = self.unmatched_patterns();
+        if self.strict && !unmatched.is_empty() {
+            anyhow::bail!(
+                "These `icon` and `resources` entries match no files: {}",
+                unmatched.join(", ")
+            );
+        }
+        for pattern in unmatched {
+            print_warning(&format!(
+                "{pattern:?} matches no files, so nothing is bundled for it"
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Warns about each bundle in `output_paths` that is larger than the
+    /// `max_bundle_size` setting, or fails listing them in strict mode.
+    pub fn check_bundle_size(&self, output_paths: &[PathBuf]) -> crate::Result<()> {
+        let Some(max_size) = self.bundle_settings.max_bundle_size else {
+            return Ok(());
+        };
+        let mut oversized = Vec::new();
+        for path in output_paths {
+            let size = common::bundle_size(path)?;
+            if size > max_size {
+                oversized.push(format!(
+                    "{} is {} ({size} bytes)",
+                    path.display(),
+                    common::format_size(size)
+                ));
+            }
+        }
+        if self.strict && !oversized.is_empty() {
+            anyhow::bail!(
+                "Bundles exceed the max_bundle_size of {max_size} bytes: {}",
+                oversized.join(", ")
+            );
+        }
+        for bundle in oversized {
+            print_warning(&format!(
+                "{bundle}, over the max_bundle_size of {max_size} bytes"
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the resource files as pairs of their source path and their
+    /// destination below `output_base`.  A single file is copied to exactly
+    /// its destination unless that ends with a path separator; otherwise the
+    /// destination is a directory the files are put in.
     pub fn resources_paths(&self, output_base: &Path) -> Vec<(PathBuf, PathBuf)> {
         let mut output = Vec::new();
         for (base_src, dst) in &self.bundle_settings.resources_mapping {
-            // Parse the base pattern to find the base directory
-            let base_pattern = Path::new(base_src);
-            let base_dir = if base_src.contains('*') {
-                // For glob patterns like "build/static/*", get the parent directory
-                base_pattern.parent().unwrap_or(Path::new(""))
-            } else {
-                // For literal paths, use the path itself if it's a directory
-                base_pattern
-            };
+            let pattern = common::expand_path(base_src);
+            if is_file_mapping(&pattern, dst) {
+                output.push((PathBuf::from(pattern), output_base.join(dst)));
+                continue;
+            }
+            let base_dir = canonical_path(&glob_base_dir(&pattern));
 
-            for src in ResourcePaths::new(std::slice::from_ref(base_src), true).flatten() {
+            for src in ResourcePaths::expanded(vec![pattern], true).flatten() {
                 // Calculate the relative path from the base directory to preserve subdirectory structure
-                let relative_path = if let Ok(rel) = src.strip_prefix(base_dir) {
-                    rel
-                } else {
-                    // Fallback to just the filename if strip_prefix fails
-                    Path::new(src.file_name().unwrap_or_default())
+                let canonical_src = canonical_path(&src);
+                let relative_path = match canonical_src.strip_prefix(&base_dir) {
+                    Ok(rel) if !rel.as_os_str().is_empty() => rel,
+                    // Fallback to just the filename if strip_prefix fails or
+                    // the pattern names the file itself
+                    _ => Path::new(src.file_name().unwrap_or_default()),
                 };
                 let destination = if dst.is_empty() {
                     output_base.join(common::resource_relpath(&src))
@@ -272,6 +1221,36 @@ impl Settings {
         output
     }
 
+    /// Returns the files and directories whose changes `--watch` rebundles
+    /// for: the package manifest and `src/` directory, the directory of the
+    /// bundled target's source, and those holding the icons and resources.
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        let manifest_path = self.target.package.manifest_path.as_std_path();
+        let mut paths = vec![manifest_path.to_path_buf()];
+        if let Some(package_dir) = manifest_path.parent() {
+            paths.push(package_dir.join("src"));
+        }
+        if let Some(target) = self.target.package.targets.iter().find(|target| {
+            target.name == self.executable_name && (target.is_bin() || target.is_example())
+        }) && let Some(source_dir) = target.src_path.parent()
+        {
+            paths.push(source_dir.into());
+        }
+        let patterns = self.bundle_settings.icon.iter().chain(
+            self.bundle_settings
+                .resources_mapping
+                .iter()
+                .map(|(src, _)| src),
+        );
+        for pattern in patterns {
+            paths.push(glob_base_dir(&common::expand_path(pattern)));
+        }
+        let mut paths: Vec<_> = paths.iter().map(|path| canonical_path(path)).collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
     pub fn version_string(&self) -> &dyn Display {
         match self.bundle_settings.version.as_ref() {
             Some(v) => v,
@@ -315,14 +1294,13 @@ impl Settings {
         self.bundle_settings.long_description.as_deref()
     }
 
+    /// Returns the text of the package's license: the contents of its
+    /// `license-file`, or else of a conventionally named license file such as
+    /// `LICENSE` or `COPYING` next to the package or at the workspace root.
     pub fn license_content(&self) -> Option<String> {
-        self.target
-            .package
-            .license_file
-            .as_ref()
-            .and_then(|license_file| {
-                let license_path = self.target.get_project_dir().join(license_file);
-                match std::fs::read_to_string(&license_path) {
+        self.license_path()
+            .and_then(
+                |license_path| match std::fs::read_to_string(&license_path) {
                     Ok(content) => Some(content),
                     Err(err) => {
                         print_warning(&format!(
@@ -331,15 +1309,117 @@ impl Settings {
                         .ok();
                         None
                     }
-                }
+                },
+            )
+            .or_else(|| {
+                let package_dir = self.target.package.manifest_path.parent()?;
+                [package_dir.as_std_path(), &self.target.workspace_root]
+                    .iter()
+                    .flat_map(|dir| LICENSE_FILE_NAMES.iter().map(move |name| dir.join(name)))
+                    .find_map(|path| std::fs::read_to_string(path).ok())
             })
-            .or_else(|| self.target.package.license.as_ref().map(|s| s.to_string()))
+    }
+
+    /// Returns the path of the package's `readme`, if it has one that exists.
+    pub fn readme_path(&self) -> Option<PathBuf> {
+        let readme = self.target.package.readme.as_ref()?;
+        let package_dir = self.target.package.manifest_path.parent()?;
+        let path = package_dir.as_std_path().join(readme);
+        path.is_file().then_some(path)
+    }
+
+    /// Returns the SPDX license expression of the package, e.g.
+    /// `MIT OR Apache-2.0`, if it has one.
+    pub fn license_expression(&self) -> Option<&str> {
+        self.target.package.license.as_deref()
+    }
+
+    /// Returns the path of the package's `license-file`.  Like cargo, it is
+    /// looked up relative to the package directory first, but a license at
+    /// the workspace root is also accepted, with a warning.
+    fn license_path(&self) -> Option<PathBuf> {
+        let license_file = self.target.package.license_file.as_ref()?;
+        let package_dir = self.target.package.manifest_path.parent()?;
+        let package_license = package_dir.as_std_path().join(license_file);
+        if package_license.is_file() {
+            return Some(package_license);
+        }
+        let workspace_license = self.target.workspace_root.join(license_file);
+        if workspace_license.is_file() {
+            print_warning(&format!(
+                "License file {license_file:?} is not in the package directory, using \
+                 {workspace_license:?} from the workspace root"
+            ))
+            .ok();
+            return Some(workspace_license);
+        }
+        Some(package_license)
+    }
+
+    /// Returns true if the shared libraries the binary links against should
+    /// be copied into the AppImage.
+    pub fn appimage_bundle_libs(&self) -> bool {
+        self.bundle_settings.appimage_bundle_libs.unwrap_or(false)
+    }
+
+    /// Returns true if the AppImage should ask desktop integration tools such
+    /// as appimaged and AppImageLauncher not to integrate it into the system
+    /// menu.  The `--no-appimage-integration` flag takes precedence over the
+    /// `appimage_no_integration` setting.
+    pub fn appimage_no_integration(&self) -> bool {
+        self.no_appimage_integration
+            || self
+                .bundle_settings
+                .appimage_no_integration
+                .unwrap_or(false)
+    }
+
+    /// Returns the compression to make the AppImage's squashfs with, or
+    /// `None` to use the `mksquashfs` default.
+    pub fn appimage_compression(&self) -> Option<&str> {
+        self.bundle_settings.appimage_compression.as_deref()
+    }
+
+    /// Returns true if the shared library dependencies of the binary should
+    /// be detected with `dpkg-shlibdeps` and added to the deb's `Depends:`.
+    pub fn deb_auto_depends(&self) -> bool {
+        self.bundle_settings.deb_auto_depends.unwrap_or(false)
     }
 
     pub fn debian_dependencies(&self) -> &[String] {
         self.bundle_settings.deb_depends.as_slice()
     }
 
+    /// Returns the packages listed in the `Recommends:` field of the deb
+    /// control file.
+    pub fn deb_recommends(&self) -> &[String] {
+        self.bundle_settings.deb_recommends.as_slice()
+    }
+
+    /// Returns the packages listed in the `Suggests:` field of the deb
+    /// control file.
+    pub fn deb_suggests(&self) -> &[String] {
+        self.bundle_settings.deb_suggests.as_slice()
+    }
+
+    /// Returns the packages listed in the `Conflicts:` field of the deb
+    /// control file.
+    pub fn deb_conflicts(&self) -> &[String] {
+        self.bundle_settings.deb_conflicts.as_slice()
+    }
+
+    /// Returns the packages listed in the `Provides:` field of the deb
+    /// control file.
+    pub fn deb_provides(&self) -> &[String] {
+        self.bundle_settings.deb_provides.as_slice()
+    }
+
+    /// Returns the packages listed in the `Replaces:` field of the deb
+    /// control file.
+    pub fn deb_replaces(&self) -> &[String] {
+        self.bundle_settings.deb_replaces.as_slice()
+    }
+
     pub fn linux_mime_types(&self) -> &[String] {
         self.bundle_settings.linux_mime_types.as_slice()
     }
@@ -352,6 +1432,207 @@ impl Settings {
         self.bundle_settings.linux_exec_args.as_deref()
     }
 
+    /// Returns the extra files to install on Linux, mapping each source path
+    /// to the absolute path it should be installed at.
+    pub fn linux_files(&self) -> &HashMap<String, String> {
+        &self.bundle_settings.linux_files
+    }
+
+    /// Returns the man page source files to install on Linux.
+    pub fn linux_man_pages(&self) -> &[String] {
+        self.bundle_settings.linux_man_pages.as_slice()
+    }
+
+    /// Returns the shell completion files to install on Linux, keyed by shell
+    /// name (`bash`, `zsh` or `fish`).
+    pub fn linux_completions(&self) -> &HashMap<String, String> {
+        &self.bundle_settings.linux_completions
+    }
+
+    /// Returns the absolute install paths of the files in the deb package that
+    /// dpkg should treat as configuration files.
+    pub fn deb_conffiles(&self) -> &[String] {
+        self.bundle_settings.deb_conffiles.as_slice()
+    }
+
+    /// Returns the path to the changelog to install in deb packages, if one
+    /// was given.  Relative paths are resolved against the project directory.
+    pub fn deb_changelog(&self) -> Option<PathBuf> {
+        self.bundle_settings.deb_changelog.as_ref().map(|path| {
+            self.target
+                .get_project_dir()
+                .join(common::expand_path(path))
+        })
+    }
+
+    /// Returns the path to the copyright file to install in deb packages
+    /// instead of a generated one, if any.  Relative paths are resolved
+    /// against the project directory.
+    pub fn deb_copyright_file(&self) -> Option<PathBuf> {
+        self.bundle_settings
+            .deb_copyright_file
+            .as_ref()
+            .map(|path| {
+                self.target
+                    .get_project_dir()
+                    .join(common::expand_path(path))
+            })
+    }
+
+    /// Returns the GPG key ID to sign deb packages with, if any.  The
+    /// `--sign-key` flag takes precedence over the `deb_sign_key` setting.
+    pub fn deb_sign_key(&self) -> Option<&str> {
+        self.sign_key
+            .as_deref()
+            .or(self.bundle_settings.deb_sign_key.as_deref())
+    }
+
+    /// Returns the section of the Debian archive the package belongs in,
+    /// derived from the app category if `deb_section` isn't set.
+    pub fn deb_section(&self) -> &str {
+        match (&self.bundle_settings.deb_section, self.app_category()) {
+            (Some(section), _) => section,
+            (None, Some(category)) => category.debian_section(),
+            (None, None) => "misc",
+        }
+    }
+
+    /// Returns the Debian priority of the package, defaulting to `optional`.
+    pub fn deb_priority(&self) -> &str {
+        self.bundle_settings
+            .deb_priority
+            .as_deref()
+            .unwrap_or("optional")
+    }
+
+    /// Returns the `Group:` of the rpm package: the `rpm_group` setting, or
+    /// else the group matching the app category, or `Unspecified`.
+    pub fn rpm_group(&self) -> &str {
+        match (&self.bundle_settings.rpm_group, self.app_category()) {
+            (Some(group), _) => group,
+            (None, Some(category)) => category.rpm_group(),
+            (None, None) => "Unspecified",
+        }
+    }
+
+    /// Returns the GPG key ID to sign RPM packages with, if any.  The
+    /// `--sign-key` flag takes precedence over the `rpm_sign_key` setting.
+    pub fn rpm_sign_key(&self) -> Option<&str> {
+        self.sign_key
+            .as_deref()
+            .or(self.bundle_settings.rpm_sign_key.as_deref())
+    }
+
+    /// Returns the path to the RPM `%pre` scriptlet, if any.
+    pub fn rpm_pre(&self) -> Option<String> {
+        self.bundle_settings
+            .rpm_pre
+            .as_deref()
+            .map(common::expand_path)
+    }
+
+    /// Returns the path to the RPM `%post` scriptlet, if any.
+    pub fn rpm_post(&self) -> Option<String> {
+        self.bundle_settings
+            .rpm_post
+            .as_deref()
+            .map(common::expand_path)
+    }
+
+    /// Returns the path to the RPM `%preun` scriptlet, if any.
+    pub fn rpm_preun(&self) -> Option<String> {
+        self.bundle_settings
+            .rpm_preun
+            .as_deref()
+            .map(common::expand_path)
+    }
+
+    /// Returns the path to the RPM `%postun` scriptlet, if any.
+    pub fn rpm_postun(&self) -> Option<String> {
+        self.bundle_settings
+            .rpm_postun
+            .as_deref()
+            .map(common::expand_path)
+    }
+
+    /// Returns true if MSI installers should put a shortcut to the app on the
+    /// desktop.  Defaults to true.
+    pub fn msi_create_desktop_shortcut(&self) -> bool {
+        self.bundle_settings
+            .msi_create_desktop_shortcut
+            .unwrap_or(true)
+    }
+
+    /// Returns the name of the Start Menu folder MSI installers put the app's
+    /// shortcut in, defaulting to the bundle name.
+    pub fn msi_start_menu_folder(&self) -> String {
+        match self.bundle_settings.msi_start_menu_folder {
+            Some(ref folder) => folder.clone(),
+            None => self.bundle_name(),
+        }
+    }
+
+    /// Returns the path to the RTF license that MSI installers ask the user to
+    /// accept, if one was given.  Relative paths are resolved against the
+    /// project directory.
+    pub fn msi_license_rtf(&self) -> Option<PathBuf> {
+        self.bundle_settings.msi_license_rtf.as_ref().map(|path| {
+            self.target
+                .get_project_dir()
+                .join(common::expand_path(path))
+        })
+    }
+
+    /// Returns the path to the provisioning profile to embed in iOS bundles,
+    /// if any.  Relative paths are resolved against the project directory.
+    pub fn ios_provisioning_profile(&self) -> Option<PathBuf> {
+        self.bundle_settings
+            .ios_provisioning_profile
+            .as_ref()
+            .map(|path| {
+                self.target
+                    .get_project_dir()
+                    .join(common::expand_path(path))
+            })
+    }
+
+    /// Returns the `codesign` identity to sign iOS bundles with, if any.
+    pub fn ios_signing_identity(&self) -> Option<&str> {
+        self.bundle_settings.ios_signing_identity.as_deref()
+    }
+
+    /// Returns the oldest iOS version the app runs on, if set.
+    pub fn ios_minimum_os_version(&self) -> Option<&str> {
+        self.bundle_settings.ios_minimum_os_version.as_deref()
+    }
+
+    /// Returns the `UIDeviceFamily` values of the devices the app runs on: 1
+    /// for iPhone and 2 for iPad.  Defaults to both.
+    pub fn ios_device_family(&self) -> &[u8] {
+        match self.bundle_settings.ios_device_family {
+            Some(ref family) => family.as_slice(),
+            None => &[1, 2],
+        }
+    }
+
+    /// Returns the `UISupportedInterfaceOrientations` of the app, if set.
+    pub fn ios_supported_orientations(&self) -> &[String] {
+        match self.bundle_settings.ios_supported_orientations {
+            Some(ref orientations) => orientations.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Returns the `UIRequiredDeviceCapabilities` entry matching the binary's
+    /// architecture, or `None` for simulator builds.
+    pub fn ios_required_device_capability(&self) -> Option<&'static str> {
+        match self.binary_arch() {
+            "aarch64" => Some("arm64"),
+            "arm" => Some("armv7"),
+            _ => None,
+        }
+    }
+
     pub fn osx_frameworks(&self) -> &[String] {
         self.bundle_settings.osx_frameworks.as_slice()
     }
@@ -367,6 +1648,44 @@ impl Settings {
         self.bundle_settings.osx_minimum_system_version.as_deref()
     }
 
+    /// Returns the names of the extra `bin` targets of the package to build
+    /// and place in `Contents/MacOS` next to the main binary.
+    pub fn osx_additional_binaries(&self) -> &[String] {
+        self.bundle_settings.osx_additional_binaries.as_slice()
+    }
+
+    /// Returns the path of the built `bin` target called `name`.
+    pub fn additional_binary_path(&self, name: &str) -> PathBuf {
+        let triple = if self.universal {
+            Some(UNIVERSAL_TARGET_DIR)
+        } else {
+            self.target_triple()
+        };
+        self.target
+            .target_dir_for(triple, &BuildArtifact::Main)
+            .join(name)
+    }
+
+    /// Returns true if non-system dylibs the binary links against should be
+    /// copied into the app bundle.
+    pub fn osx_bundle_dylibs(&self) -> bool {
+        self.bundle_settings.osx_bundle_dylibs.unwrap_or(false)
+    }
+
+    /// Returns true if the app supports high-resolution (Retina) displays.
+    /// Defaults to true.
+    pub fn osx_high_resolution_capable(&self) -> bool {
+        self.bundle_settings
+            .osx_high_resolution_capable
+            .unwrap_or(true)
+    }
+
+    /// Returns whether the app should always use the light (Aqua) appearance
+    /// rather than following the system's dark mode setting, if specified.
+    pub fn osx_requires_aqua_system_appearance(&self) -> Option<bool> {
+        self.bundle_settings.osx_requires_aqua_system_appearance
+    }
+
     pub fn osx_url_schemes(&self) -> &[String] {
         match self.bundle_settings.osx_url_schemes {
             Some(ref urlosx_url_schemes) => urlosx_url_schemes.as_slice(),
@@ -374,8 +1693,86 @@ impl Settings {
         }
     }
 
+    /// Returns the extra keys to set in the `Info.plist` of macOS and iOS
+    /// bundles, overriding the generated ones.
+    pub fn osx_info_plist_keys(&self) -> &HashMap<String, toml::Value> {
+        static EMPTY: std::sync::LazyLock<HashMap<String, toml::Value>> =
+            std::sync::LazyLock::new(HashMap::new);
+        self.bundle_settings
+            .osx_info_plist_keys
+            .as_ref()
+            .unwrap_or(&EMPTY)
+    }
+
+    /// Returns the locales the app is localized for, sorted, each with an
+    /// iterator over its resource files.  Locales that only have a localized
+    /// display name are included, without files.
+    pub fn osx_localizations(&self) -> Vec<(&str, ResourcePaths)> {
+        let mut localizations: Vec<_> = self
+            .bundle_settings
+            .osx_localizations
+            .iter()
+            .flatten()
+            .map(|(locale, files)| (locale.as_str(), ResourcePaths::new(files, false)))
+            .collect();
+        for locale in self
+            .bundle_settings
+            .osx_localized_display_names
+            .iter()
+            .flatten()
+            .map(|(locale, _)| locale.as_str())
+        {
+            if !localizations.iter().any(|(other, _)| *other == locale) {
+                localizations.push((locale, ResourcePaths::new(&[], false)));
+            }
+        }
+        localizations.sort_by_key(|(locale, _)| *locale);
+        localizations
+    }
+
+    /// Returns the `codesign` identity to sign macOS bundles with, if any.
+    pub fn osx_signing_identity(&self) -> Option<&str> {
+        self.bundle_settings.osx_signing_identity.as_deref()
+    }
+
+    /// Returns the name of the `notarytool` keychain profile holding the
+    /// credentials to notarize macOS bundles with, if any.
+    pub fn osx_notarize_keychain_profile(&self) -> Option<&str> {
+        self.bundle_settings
+            .osx_notarize_keychain_profile
+            .as_deref()
+    }
+
+    /// Returns true if a macOS bundle without an `osx_signing_identity`
+    /// should be ad-hoc signed, so that it runs on the Mac it was made on.
+    /// Defaults to true when bundling an Apple Silicon binary on macOS, as
+    /// Gatekeeper kills unsigned arm64 binaries.
+    pub fn osx_adhoc_sign(&self) -> bool {
+        self.bundle_settings.osx_adhoc_sign.unwrap_or_else(|| {
+            cfg!(target_os = "macos") && matches!(self.binary_arch(), "aarch64" | "universal")
+        })
+    }
+
+    /// Returns the name shown for the app in the Finder and Dock
+    /// (`CFBundleDisplayName`): `osx_display_name`, or else the bundle name.
+    pub fn osx_display_name(&self) -> String {
+        match &self.bundle_settings.osx_display_name {
+            Some(name) => name.clone(),
+            None => self.bundle_name(),
+        }
+    }
+
+    /// Returns the display name of the app for `locale`, if it is localized.
+    pub fn osx_localized_display_name(&self, locale: &str) -> Option<&str> {
+        self.bundle_settings
+            .osx_localized_display_names
+            .as_ref()?
+            .get(locale)
+            .map(String::as_str)
+    }
+
     /// Returns an iterator over the plist files for this bundle
-    pub fn osx_info_plist_exts(&self) -> ResourcePaths<'_> {
+    pub fn osx_info_plist_exts(&self) -> ResourcePaths {
         match self.bundle_settings.osx_info_plist_exts {
             Some(ref paths) => ResourcePaths::new(paths.as_slice(), false),
             None => ResourcePaths::new(&[], false),
@@ -383,17 +1780,204 @@ impl Settings {
     }
 }
 
-pub struct ResourcePaths<'a> {
-    pattern_iter: std::slice::Iter<'a, String>,
+/// Returns true if the file's permissions allow executing it.  Anything can be
+/// executed as far as other platforms are concerned.
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        true
+    }
+}
+
+/// Returns true for OS versions made of two or three numbers, like `13.0` or
+/// `12.4.1`.
+fn is_os_version(version: &str) -> bool {
+    let parts: Vec<_> = version.split('.').collect();
+    (2..=3).contains(&parts.len())
+        && parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// The values `ios_supported_orientations` entries can have.
+const IOS_ORIENTATIONS: &[&str] = &[
+    "UIInterfaceOrientationPortrait",
+    "UIInterfaceOrientationPortraitUpsideDown",
+    "UIInterfaceOrientationLandscapeLeft",
+    "UIInterfaceOrientationLandscapeRight",
+];
+
+/// The names of the license files `license_content` looks for when the
+/// package has no `license-file`, in order of preference.
+const LICENSE_FILE_NAMES: &[&str] = &[
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE.txt",
+    "LICENSE-MIT",
+    "LICENSE_MIT",
+    "License_MIT.md",
+    "LICENSE-APACHE",
+    "LICENSE_APACHE",
+    "License_Apache.md",
+    "COPYING",
+];
+
+/// The architecture names accepted by `--arch`, spelled as in target triples.
+pub const ARCHES: &[&str] = &[
+    "x86",
+    "x86_64",
+    "arm",
+    "aarch64",
+    "riscv64",
+    "powerpc64",
+    "s390x",
+    "loongarch64",
+];
+
+/// The targets whose builds are merged into a universal macOS binary.
+pub const UNIVERSAL_TARGETS: [&str; 2] = ["x86_64-apple-darwin", "aarch64-apple-darwin"];
+
+/// The directory below the cargo target directory, in place of a target
+/// triple, that universal macOS binaries are written to.
+const UNIVERSAL_TARGET_DIR: &str = "universal-apple-darwin";
+
+/// The compressions `appimage_compression` can be set to, as accepted by
+/// `mksquashfs -comp`.
+const APPIMAGE_COMPRESSIONS: &[&str] = &["gzip", "xz", "zstd", "lz4"];
+
+/// The priorities a Debian package can have.  See
+/// <https://www.debian.org/doc/debian-policy/ch-archive.html#priorities>
+const DEB_PRIORITIES: &[&str] = &["required", "important", "standard", "optional", "extra"];
+
+/// Returns the identifier of bundles of `build_artifact` without one set:
+/// none for the main binary, and one derived from the package name for the
+/// others.
+fn default_identifier(build_artifact: &BuildArtifact, package_name: &str) -> Option<String> {
+    match build_artifact {
+        BuildArtifact::Main => None,
+        BuildArtifact::Bin(name) => Some(format!("{name}.{package_name}")),
+        BuildArtifact::Example(name) => Some(format!("{name}.example.{package_name}")),
+    }
+}
+
+/// Returns true if `identifier` matches `[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)+`.
+fn is_reverse_dns(identifier: &str) -> bool {
+    let mut parts = 0;
+    for part in identifier.split('.') {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return false;
+        }
+        parts += 1;
+    }
+    parts >= 2
+}
+
+/// Locations in the package directory that are searched for an icon when no
+/// `icon` is configured, without an extension.
+const DEFAULT_ICON_STEMS: &[&str] = &[
+    "icon",
+    "assets/icon",
+    "resources/icon",
+    "app",
+    "assets/app",
+    "resources/app",
+];
+
+/// Returns the first conventionally placed icon file in `package_dir`,
+/// preferring the image format `package_type` uses natively.
+fn default_icon(package_dir: &Path, package_type: PackageType) -> Option<PathBuf> {
+    let extensions: &[&str] = match package_type {
+        PackageType::OsxBundle => &["icns", "png"],
+        PackageType::WindowsMsi | PackageType::WxsMsi | PackageType::WindowsZip => &["ico", "png"],
+        _ => &["png"],
+    };
+    extensions.iter().find_map(|extension| {
+        DEFAULT_ICON_STEMS
+            .iter()
+            .map(|stem| package_dir.join(stem).with_extension(extension))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Returns true if `resources_mapping` maps the literal file `src` to the file
+/// name `dst`, rather than into the directory `dst`.  A single file is renamed
+/// unless the destination is empty or ends with a path separator.
+fn is_file_mapping(src: &str, dst: &str) -> bool {
+    !src.contains(['*', '?', '['])
+        && Path::new(src).is_file()
+        && !dst.is_empty()
+        && !dst.ends_with(['/', '\\'])
+}
+
+/// Returns the directory a resource pattern is relative to: the leading
+/// components before the first one containing glob metacharacters, or the
+/// whole path for a literal pattern.
+fn glob_base_dir(pattern: &str) -> PathBuf {
+    let path = Path::new(pattern);
+    if !pattern.contains(['*', '?', '[']) {
+        return path.to_path_buf();
+    }
+    path.components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
+/// Returns `path` as an absolute path with symlinks and `..` components
+/// resolved.  Only the parent directory is canonicalized so that a symlinked
+/// file keeps its own name, and paths that can't be canonicalized are just
+/// made absolute.
+fn canonical_path(path: &Path) -> PathBuf {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    let canonical = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if path.is_file() => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent.canonicalize().map(|parent| parent.join(name))
+        }
+        _ => path.canonicalize(),
+    };
+    canonical.unwrap_or_else(|_| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+pub struct ResourcePaths {
+    pattern_iter: std::vec::IntoIter<String>,
     glob_iter: Option<glob::Paths>,
     walk_iter: Option<walkdir::IntoIter>,
     allow_walk: bool,
 }
 
-impl<'a> ResourcePaths<'a> {
-    fn new(patterns: &'a [String], allow_walk: bool) -> ResourcePaths<'a> {
+impl ResourcePaths {
+    /// Iterates over the files matching `patterns`, after expanding `~` and
+    /// environment variables in them.
+    pub(super) fn new(patterns: &[String], allow_walk: bool) -> ResourcePaths {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| common::expand_path(pattern))
+            .collect();
+        ResourcePaths::expanded(patterns, allow_walk)
+    }
+
+    fn expanded(patterns: Vec<String>, allow_walk: bool) -> ResourcePaths {
         ResourcePaths {
-            pattern_iter: patterns.iter(),
+            pattern_iter: patterns.into_iter(),
             glob_iter: None,
             walk_iter: None,
             allow_walk,
@@ -401,7 +1985,7 @@ impl<'a> ResourcePaths<'a> {
     }
 }
 
-impl Iterator for ResourcePaths<'_> {
+impl Iterator for ResourcePaths {
     type Item = crate::Result<PathBuf>;
 
     fn next(&mut self) -> Option<crate::Result<PathBuf>> {
@@ -440,7 +2024,7 @@ impl Iterator for ResourcePaths<'_> {
             }
             self.glob_iter = None;
             if let Some(pattern) = self.pattern_iter.next() {
-                let glob = match glob::glob(pattern) {
+                let glob = match glob::glob(&pattern) {
                     Ok(glob) => glob,
                     Err(error) => return Some(Err(anyhow::Error::from(error))),
                 };
@@ -454,7 +2038,885 @@ impl Iterator for ResourcePaths<'_> {
 
 #[cfg(test)]
 mod tests {
-    use super::{AppCategory, BundleSettings};
+    use super::{
+        AppCategory, BuildArtifact, BundleSettings, MsiPlatform, PackageType, Settings,
+        is_reverse_dns,
+    };
+    use crate::test_utils::{fixture_package, write_fixture_package, write_named_fixture_package};
+    use crate::{Cli, bundle::target_info::BundleTargetInfo};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn lib_and_bin_with_same_name() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nname = \"Foo App\"\nidentifier = \"com.example.foo\"\n",
+        );
+        std::fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--target-dir",
+            tmp.path().join("target").to_str().unwrap(),
+            tmp.path().to_str().unwrap(),
+        ]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        let settings = Settings::new(&info, &cli).unwrap();
+        assert_eq!(settings.binary_name(), "foo");
+        assert_eq!(settings.bundle_name(), "Foo App");
+        let binary_path = tmp.path().join("target/debug/foo");
+        assert_eq!(settings.binary_path(PackageType::Deb), binary_path);
+
+        let error = settings.validate_binary_exists().unwrap_err().to_string();
+        assert!(
+            error.contains(&format!("{binary_path:?}"))
+                && error.contains("`dev` profile for the host target"),
+            "{error}"
+        );
+        std::fs::create_dir_all(binary_path.parent().unwrap()).unwrap();
+        std::fs::write(&binary_path, b"").unwrap();
+        settings.validate_binary_exists().unwrap();
+    }
+
+    #[test]
+    fn missing_binary_with_skipped_build() {
+        let tmp = fixture_package("");
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--release",
+            "--no-build",
+            "--target",
+            "aarch64-unknown-linux-gnu",
+            tmp.path().to_str().unwrap(),
+        ]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        let settings = Settings::new(&info, &cli).unwrap();
+        crate::build_project_if_unbuilt(&settings).unwrap();
+        let error = settings.validate_binary_exists().unwrap_err().to_string();
+        let binary_path = settings.binary_path(PackageType::Deb);
+        assert!(error.contains(&format!("{binary_path:?}")), "{error}");
+        assert!(
+            error.contains("`release` profile for target aarch64-unknown-linux-gnu"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn cargo_config_is_forwarded() {
+        let tmp = fixture_package("");
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--cargo-config",
+            "profile.release.lto=true",
+            "--cargo-config",
+            "ci.toml",
+            "--config",
+            "copyright=Copyright (c) Foo",
+            tmp.path().to_str().unwrap(),
+        ]);
+        assert_eq!(cli.config, ["copyright=Copyright (c) Foo"]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        let settings = Settings::new(&info, &cli).unwrap();
+        assert_eq!(
+            settings.cargo_config(),
+            ["profile.release.lto=true", "ci.toml"]
+        );
+
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--cargo-config",
+            "lto",
+            tmp.path().to_str().unwrap(),
+        ]);
+        let error = Settings::new(&info, &cli).unwrap_err().to_string();
+        assert!(
+            error.contains("Invalid cargo config override \"lto\""),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn build_env_reaches_build_scripts() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle.build_env]\nFOO_FLAVOR = \"plain\"\nFOO_SIZE = \"large\"\n",
+        );
+        std::fs::write(
+            tmp.path().join("build.rs"),
+            "fn main() {\n\
+             \x20   assert_eq!(std::env::var(\"FOO_FLAVOR\").as_deref(), Ok(\"vanilla\"));\n\
+             \x20   assert_eq!(std::env::var(\"FOO_SIZE\").as_deref(), Ok(\"large\"));\n\
+             }\n",
+        )
+        .unwrap();
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--build-env",
+            "FOO_FLAVOR=vanilla",
+            tmp.path().to_str().unwrap(),
+        ]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        let settings = Settings::new(&info, &cli).unwrap();
+        crate::build_project_if_unbuilt(&settings).unwrap();
+
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--build-env",
+            "FOO_FLAVOR",
+            tmp.path().to_str().unwrap(),
+        ]);
+        let error = Settings::new(&info, &cli).unwrap_err().to_string();
+        assert!(error.contains("Invalid --build-env value"), "{error}");
+    }
+
+    #[test]
+    fn msi_platform_follows_target_arch() {
+        let tmp = fixture_package("");
+        let platform = |target: &str| {
+            let cli = <Cli as clap::Parser>::parse_from([
+                "cargo-bundler",
+                "--target",
+                target,
+                tmp.path().to_str().unwrap(),
+            ]);
+            let info = BundleTargetInfo::try_from((&cli, PackageType::WindowsMsi, None)).unwrap();
+            Settings::new(&info, &cli).unwrap().msi_platform()
+        };
+        assert_eq!(platform("i686-pc-windows-msvc").unwrap(), MsiPlatform::X86);
+        assert_eq!(
+            platform("x86_64-pc-windows-msvc").unwrap(),
+            MsiPlatform::X64
+        );
+        assert_eq!(
+            platform("aarch64-pc-windows-msvc").unwrap(),
+            MsiPlatform::Arm64
+        );
+        assert!(platform("riscv64gc-unknown-linux-gnu").is_err());
+
+        assert_eq!(
+            MsiPlatform::X86.program_files_folder(),
+            "ProgramFilesFolder"
+        );
+        assert_eq!(
+            MsiPlatform::Arm64.program_files_folder(),
+            "ProgramFiles64Folder"
+        );
+        assert_eq!(MsiPlatform::Arm64.wix_name(), "arm64");
+    }
+
+    #[test]
+    fn builder_without_cli() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nname = \"Foo App\"\nidentifier = \"com.example.foo\"\n",
+        );
+
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None)
+            .unwrap()
+            .with_target("x86_64-unknown-linux-gnu")
+            .unwrap();
+        let settings = Settings::builder(info.clone())
+            .features("f1 f2")
+            .jobs(4)
+            .build()
+            .unwrap();
+        assert_eq!(settings.binary_name(), "foo");
+        assert_eq!(settings.bundle_name(), "Foo App");
+        assert_eq!(settings.features(), Some("f1 f2"));
+        assert_eq!(settings.jobs(), Some(4));
+        assert_eq!(settings.binary_arch(), "x86_64");
+        let settings = Settings::builder(info.clone())
+            .arch("aarch64")
+            .build()
+            .unwrap();
+        assert_eq!(settings.binary_arch(), "x86_64");
+        assert_eq!(settings.target_triple(), Some("x86_64-unknown-linux-gnu"));
+        let host = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let settings = Settings::builder(host.clone())
+            .arch("aarch64")
+            .build()
+            .unwrap();
+        assert_eq!(settings.binary_arch(), "aarch64");
+        assert_eq!(settings.target_triple(), None);
+        let error = Settings::builder(host)
+            .arch("amd64")
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Unknown architecture \"amd64\""), "{error}");
+
+        let bundle_settings: BundleSettings =
+            toml::from_str("name = \"Other App\"\nidentifier = \"com.example.other\"\n").unwrap();
+        let settings = Settings::builder(info)
+            .bundle_settings(bundle_settings)
+            .binary_name("other")
+            .build()
+            .unwrap();
+        assert_eq!(settings.binary_name(), "other");
+        assert_eq!(settings.bundle_name(), "Other App");
+    }
+
+    #[test]
+    fn binary_path_uses_target_name() {
+        let tmp = fixture_package(
+            "\n[[bin]]\nname = \"foo-cli\"\npath = \"src/main.rs\"\n\
+             \n\
+             [package.metadata.bundle]\nname = \"Foo App\"\n",
+        );
+        let mut info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        info.target_dir = Some(tmp.path().join("target"));
+        let binary = tmp.path().join("target/debug/foo-cli");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, "").unwrap();
+
+        let settings = Settings::builder(info.clone()).build().unwrap();
+        assert_eq!(settings.bundle_name(), "Foo App");
+        assert_eq!(settings.binary_name(), "foo-cli");
+        assert_eq!(settings.binary_path(PackageType::Deb), binary);
+        settings.validate_binary_exists().unwrap();
+
+        let settings = Settings::builder(info).binary_name("foo").build().unwrap();
+        assert_eq!(settings.binary_name(), "foo");
+        assert_eq!(settings.binary_path(PackageType::Deb), binary);
+        settings.validate_binary_exists().unwrap();
+    }
+
+    #[test]
+    fn default_icons_are_discovered() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n",
+        );
+        std::fs::create_dir_all(tmp.path().join("assets")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("resources")).unwrap();
+        for icon in ["icon.png", "assets/icon.icns", "resources/app.ico"] {
+            std::fs::write(tmp.path().join(icon), "icon").unwrap();
+        }
+        let icon_of = |package_type, no_default_icons| {
+            let info = BundleTargetInfo::load(tmp.path(), package_type, None).unwrap();
+            let settings = Settings::builder(info)
+                .no_default_icons(no_default_icons)
+                .build()
+                .unwrap();
+            settings
+                .bundle_settings()
+                .icon
+                .iter()
+                .map(|icon| {
+                    let icon = Path::new(icon).strip_prefix(tmp.path()).unwrap();
+                    icon.to_string_lossy().replace('\\', "/")
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(icon_of(PackageType::OsxBundle, false), ["assets/icon.icns"]);
+        assert_eq!(
+            icon_of(PackageType::WindowsMsi, false),
+            ["resources/app.ico"]
+        );
+        assert_eq!(icon_of(PackageType::Deb, false), ["icon.png"]);
+        assert!(icon_of(PackageType::Deb, true).is_empty());
+
+        // A configured icon is never replaced.
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let configured = tmp.path().join("resources/app.ico").display().to_string();
+        let settings = Settings::builder(info)
+            .bundle_settings(BundleSettings {
+                icon: vec![configured.clone()],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        assert_eq!(settings.bundle_settings().icon, [configured]);
+    }
+
+    #[test]
+    fn watch_paths_cover_sources_and_resources() {
+        let tmp = tempfile::tempdir().unwrap();
+        let tmp_dir = tmp.path().canonicalize().unwrap();
+        std::fs::create_dir_all(tmp_dir.join("examples")).unwrap();
+        std::fs::create_dir_all(tmp_dir.join("assets/images")).unwrap();
+        write_fixture_package(&tmp_dir, "");
+        std::fs::write(tmp_dir.join("examples/demo.rs"), "fn main() {}\n").unwrap();
+        let info = BundleTargetInfo::load(&tmp_dir, PackageType::Deb, None).unwrap();
+        let bundle_settings = BundleSettings {
+            icon: vec![tmp_dir.join("assets/images/*.png").display().to_string()],
+            resources_mapping: vec![(
+                tmp_dir.join("assets/**/*").display().to_string(),
+                "assets".to_string(),
+            )],
+            ..Default::default()
+        };
+        let settings = Settings::builder(info.clone())
+            .bundle_settings(bundle_settings)
+            .build()
+            .unwrap();
+        assert_eq!(
+            settings.watch_paths(),
+            [
+                tmp_dir.join("Cargo.toml"),
+                tmp_dir.join("assets"),
+                tmp_dir.join("assets/images"),
+                tmp_dir.join("src"),
+            ]
+        );
+
+        let settings = Settings::builder(info)
+            .build_artifact(BuildArtifact::Example("demo".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            settings.watch_paths(),
+            [
+                tmp_dir.join("Cargo.toml"),
+                tmp_dir.join("examples"),
+                tmp_dir.join("src"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resources_rename_single_file() {
+        let tmp = fixture_package("");
+        let sample = tmp.path().join("config.sample.toml");
+        std::fs::write(&sample, "key = 1\n").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let sample_src = sample.display().to_string();
+        let bundle_settings = BundleSettings {
+            resources_mapping: vec![
+                (sample_src.clone(), "config.toml".to_string()),
+                (sample_src.clone(), "etc/app.toml".to_string()),
+                (sample_src.clone(), "defaults/".to_string()),
+                (sample_src.clone(), "defaults".to_string()),
+                (sample_src.clone(), "lib/python3.11/".to_string()),
+                (sample_src.clone(), "share/app.d/".to_string()),
+                (
+                    tmp.path().join("config.*.toml").display().to_string(),
+                    "globbed.toml".to_string(),
+                ),
+            ],
+            ..Default::default()
+        };
+        let settings = Settings::builder(info)
+            .bundle_settings(bundle_settings)
+            .build()
+            .unwrap();
+        let destinations: Vec<_> = settings
+            .resources_paths(Path::new("/out"))
+            .into_iter()
+            .map(|(src, dst)| {
+                assert_eq!(src, sample);
+                dst
+            })
+            .collect();
+        assert_eq!(
+            destinations,
+            vec![
+                PathBuf::from("/out/config.toml"),
+                PathBuf::from("/out/etc/app.toml"),
+                PathBuf::from("/out/defaults/config.sample.toml"),
+                PathBuf::from("/out/defaults"),
+                PathBuf::from("/out/lib/python3.11/config.sample.toml"),
+                PathBuf::from("/out/share/app.d/config.sample.toml"),
+                PathBuf::from("/out/globbed.toml/config.sample.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn resources_keep_nested_structure() {
+        let tmp = fixture_package("");
+        std::fs::create_dir_all(tmp.path().join("assets/sub")).unwrap();
+        std::fs::write(tmp.path().join("assets/top.txt"), "top").unwrap();
+        std::fs::write(tmp.path().join("assets/sub/nested.txt"), "nested").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+
+        let assets = tmp.path().join("assets");
+        for pattern in [
+            format!("{}/*", assets.display()),
+            format!("{}/src/../assets/*", tmp.path().display()),
+        ] {
+            let bundle_settings = BundleSettings {
+                resources_mapping: vec![(pattern.clone(), "data".to_string())],
+                ..Default::default()
+            };
+            let settings = Settings::builder(info.clone())
+                .bundle_settings(bundle_settings)
+                .build()
+                .unwrap();
+            let mut destinations: Vec<_> = settings
+                .resources_paths(Path::new("/out"))
+                .into_iter()
+                .map(|(_, dst)| dst)
+                .collect();
+            destinations.sort();
+            assert_eq!(
+                destinations,
+                vec![
+                    PathBuf::from("/out/data/sub/nested.txt"),
+                    PathBuf::from("/out/data/top.txt"),
+                ],
+                "{pattern}"
+            );
+        }
+    }
+
+    #[test]
+    fn manifest_lists_bundled_files() {
+        let tmp = fixture_package("authors = [\"Jane Doe <jane@example.com>\"]\n");
+        image::RgbaImage::new(32, 32)
+            .save(tmp.path().join("icon.png"))
+            .unwrap();
+        std::fs::create_dir_all(tmp.path().join("assets/sub")).unwrap();
+        std::fs::write(tmp.path().join("assets/sub/data.txt"), "data!").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+
+        let bundle_settings = BundleSettings {
+            icon: vec![tmp.path().join("icon.png").display().to_string()],
+            resources_mapping: vec![(
+                tmp.path().join("assets/*").display().to_string(),
+                "data".to_string(),
+            )],
+            ..Default::default()
+        };
+        let settings = Settings::builder(info)
+            .bundle_settings(bundle_settings)
+            .manifest_out("manifest.txt")
+            .build()
+            .unwrap();
+        assert_eq!(settings.manifest_out(), Some(Path::new("manifest.txt")));
+        let binary_path = settings.binary_path(PackageType::Deb);
+        std::fs::create_dir_all(binary_path.parent().unwrap()).unwrap();
+        std::fs::write(&binary_path, "binary").unwrap();
+        let output_paths = crate::bundle(&settings, PackageType::Deb).unwrap();
+        let bundles: Vec<_> = output_paths
+            .into_iter()
+            .map(|path| (PackageType::Deb, path))
+            .collect();
+        let manifest_path = tmp.path().join("manifest.txt");
+        crate::write_manifest(&manifest_path, &bundles).unwrap();
+        let manifest = std::fs::read_to_string(manifest_path).unwrap();
+        // The generated desktop file and icon are listed as they were written.
+        assert!(manifest.contains("deb/usr/bin/foo\t6\n"), "{manifest}");
+        assert!(
+            manifest.contains("deb/usr/lib/foo/data/sub/data.txt\t5\n"),
+            "{manifest}"
+        );
+        assert!(
+            manifest.contains("deb/usr/share/applications/foo.desktop\t"),
+            "{manifest}"
+        );
+        assert!(manifest.contains("deb/usr/share/icons/"), "{manifest}");
+    }
+
+    #[test]
+    fn clean_removes_only_bundle_outputs() {
+        let tmp = fixture_package("");
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let settings = Settings::builder(info).clean(true).build().unwrap();
+        assert!(settings.clean());
+
+        let deb_dir = settings.bundle_dir(PackageType::Deb);
+        assert_eq!(deb_dir, settings.get_target_dir().join("bundle/deb"));
+        let stray = deb_dir.join("foo_0.0.9_amd64.deb");
+        let other_format = settings.bundle_dir(PackageType::Rpm).join("foo.rpm");
+        let binary_path = settings.binary_path(PackageType::Deb);
+        for path in [&stray, &other_format, &binary_path] {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"old").unwrap();
+        }
+
+        crate::bundle::remove_bundle_dir(&deb_dir).unwrap();
+        assert!(!deb_dir.exists());
+        assert!(other_format.exists());
+        assert!(binary_path.exists());
+        crate::bundle::remove_bundle_dir(&deb_dir).unwrap();
+    }
+
+    #[test]
+    fn osx_additional_binaries() {
+        let tmp = fixture_package("");
+        std::fs::create_dir_all(tmp.path().join("src/bin")).unwrap();
+        std::fs::write(tmp.path().join("src/bin/helper.rs"), "fn main() {}\n").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+
+        let with_binaries = |names: &[&str]| {
+            Settings::builder(info.clone()).bundle_settings(BundleSettings {
+                identifier: Some("com.example.foo".to_string()),
+                osx_additional_binaries: names.iter().map(|name| name.to_string()).collect(),
+                ..Default::default()
+            })
+        };
+        let settings = with_binaries(&["helper"]).build().unwrap();
+        assert_eq!(settings.osx_additional_binaries(), ["helper"]);
+        assert_eq!(
+            settings.additional_binary_path("helper"),
+            settings.get_target_dir().join("helper")
+        );
+        let error = with_binaries(&["missing"]).build().unwrap_err().to_string();
+        assert!(error.contains("no `bin` target named 'missing'"), "{error}");
+        let error = with_binaries(&["foo"]).build().unwrap_err().to_string();
+        assert!(error.contains("already the binary"), "{error}");
+    }
+
+    #[test]
+    fn universal_binaries() {
+        let tmp = fixture_package("");
+        std::fs::create_dir_all(tmp.path().join("src/bin")).unwrap();
+        std::fs::write(tmp.path().join("src/bin/helper.rs"), "fn main() {}\n").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::OsxBundle, None).unwrap();
+        let settings = Settings::builder(info.clone())
+            .bundle_settings(BundleSettings {
+                identifier: Some("com.example.foo".to_string()),
+                osx_additional_binaries: vec!["helper".to_string()],
+                ..Default::default()
+            })
+            .universal(true)
+            .build()
+            .unwrap();
+        assert_eq!(settings.binary_arch(), "universal");
+        let universal_dir =
+            info.target_dir_for(Some("universal-apple-darwin"), &BuildArtifact::Main);
+        assert_eq!(
+            settings.binary_path(PackageType::OsxBundle),
+            universal_dir.join("foo")
+        );
+        assert_eq!(
+            settings.additional_binary_path("helper"),
+            universal_dir.join("helper")
+        );
+
+        let error = Settings::builder(info.with_target("x86_64-apple-darwin").unwrap())
+            .bundle_settings(BundleSettings {
+                identifier: Some("com.example.foo".to_string()),
+                ..Default::default()
+            })
+            .universal(true)
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("can't be combined with a target triple"),
+            "{error}"
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let error = Settings::builder(info)
+            .universal(true)
+            .build()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("only be used with the osx format"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn ios_device_keys() {
+        let tmp = fixture_package("");
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::IosBundle, None).unwrap();
+        let build = |bundle_settings: BundleSettings| {
+            Settings::builder(info.clone())
+                .bundle_settings(BundleSettings {
+                    identifier: Some("com.example.foo".to_string()),
+                    ..bundle_settings
+                })
+                .build()
+        };
+
+        let settings = build(BundleSettings::default()).unwrap();
+        assert_eq!(settings.ios_device_family(), [1, 2]);
+        assert!(settings.ios_supported_orientations().is_empty());
+
+        let settings = build(BundleSettings {
+            ios_device_family: Some(vec![2]),
+            ios_supported_orientations: Some(vec!["UIInterfaceOrientationPortrait".to_string()]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(settings.ios_device_family(), [2]);
+        assert_eq!(
+            settings.ios_supported_orientations(),
+            ["UIInterfaceOrientationPortrait"]
+        );
+
+        let error = build(BundleSettings {
+            ios_device_family: Some(vec![1, 3]),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("ios_device_family 3"), "{error}");
+        let error = build(BundleSettings {
+            ios_supported_orientations: Some(vec!["Portrait".to_string()]),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("\"Portrait\""), "{error}");
+    }
+
+    #[test]
+    fn bundle_settings_validation() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("icon.png"), "png").unwrap();
+        std::fs::create_dir_all(tmp.path().join("assets")).unwrap();
+        std::fs::write(tmp.path().join("assets/data.txt"), "data").unwrap();
+        let path = |name: &str| tmp.path().join(name).display().to_string();
+        let valid = BundleSettings {
+            identifier: Some("com.example.foo".to_string()),
+            icon: vec![path("icon.png")],
+            resources_mapping: vec![(path("assets/*"), String::new())],
+            deb_section: Some("contrib/games".to_string()),
+            ..Default::default()
+        };
+        for package_type in [PackageType::OsxBundle, PackageType::Deb] {
+            valid.validate(package_type).unwrap();
+        }
+
+        let error = |settings: BundleSettings, package_type| {
+            settings.validate(package_type).unwrap_err().to_string()
+        };
+        let missing_identifier = BundleSettings {
+            identifier: None,
+            ..valid.clone()
+        };
+        assert!(error(missing_identifier.clone(), PackageType::IosBundle).contains("`identifier`"));
+        missing_identifier.validate(PackageType::Deb).unwrap();
+        let directory_icon = BundleSettings {
+            icon: vec![path("assets")],
+            ..valid.clone()
+        };
+        assert!(error(directory_icon, PackageType::Deb).contains("is a directory"));
+        let bad_section = BundleSettings {
+            deb_section: Some("game".to_string()),
+            category: Some(AppCategory::PuzzleGame),
+            ..valid.clone()
+        };
+        assert!(error(bad_section.clone(), PackageType::Deb).contains("use \"games\""));
+        bad_section.validate(PackageType::Rpm).unwrap();
+
+        // All problems are reported at once.
+        let report = error(
+            BundleSettings {
+                identifier: None,
+                icon: vec![path("assets")],
+                ..Default::default()
+            },
+            PackageType::OsxBundle,
+        );
+        assert!(report.starts_with("Found 2 problems"), "{report}");
+    }
+
+    #[test]
+    fn unmatched_patterns_warn() {
+        let tmp = fixture_package("");
+        std::fs::create_dir_all(tmp.path().join("assets")).unwrap();
+        std::fs::write(tmp.path().join("assets/data.txt"), "data").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let typo = tmp.path().join("asets/*").display().to_string();
+        let bundle_settings = BundleSettings {
+            icon: vec![tmp.path().join("icon.png").display().to_string()],
+            resources_mapping: vec![
+                (
+                    tmp.path().join("assets/*").display().to_string(),
+                    String::new(),
+                ),
+                (typo.clone(), String::new()),
+            ],
+            ..Default::default()
+        };
+        let builder = Settings::builder(info).bundle_settings(bundle_settings);
+
+        let settings = builder.clone().build().unwrap();
+        let icon = tmp.path().join("icon.png").display().to_string();
+        assert_eq!(
+            settings.unmatched_patterns(),
+            [icon.as_str(), typo.as_str()]
+        );
+        settings.check_patterns_match().unwrap();
+
+        let settings = builder.strict(true).build().unwrap();
+        let error = settings.check_patterns_match().unwrap_err().to_string();
+        assert!(error.contains(&typo), "{error}");
+    }
+
+    #[test]
+    fn max_bundle_size() {
+        let tmp = fixture_package("");
+        let bundles = [tmp.path().join("foo.deb")];
+        std::fs::write(&bundles[0], [0; 2000]).unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let builder = Settings::builder(info).bundle_settings(BundleSettings {
+            max_bundle_size: Some(1000),
+            ..Default::default()
+        });
+
+        let settings = builder.clone().build().unwrap();
+        settings.check_bundle_size(&bundles).unwrap();
+
+        let settings = builder.strict(true).build().unwrap();
+        let error = settings
+            .check_bundle_size(&bundles)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("foo.deb is 2.0 KiB (2000 bytes)"), "{error}");
+
+        std::fs::write(&bundles[0], [0; 1000]).unwrap();
+        settings.check_bundle_size(&bundles).unwrap();
+    }
+
+    #[test]
+    fn license_file_in_workspaces() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\"]\n",
+        )
+        .unwrap();
+        let member_dir = tmp.path().join("app");
+        write_named_fixture_package(
+            &member_dir,
+            "app",
+            "license-file = \"LICENSE\"\n\
+             \n\
+             [package.metadata.bundle]\nidentifier = \"com.example.app\"\n",
+        );
+        let license_content = || {
+            let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, Some("app")).unwrap();
+            Settings::builder(info).build().unwrap().license_content()
+        };
+
+        // The license at the workspace root is used if the member has none...
+        std::fs::write(tmp.path().join("LICENSE"), "workspace license").unwrap();
+        assert_eq!(license_content().as_deref(), Some("workspace license"));
+        // ...but the member's own license takes precedence.
+        std::fs::write(member_dir.join("LICENSE"), "member license").unwrap();
+        assert_eq!(license_content().as_deref(), Some("member license"));
+    }
+
+    #[test]
+    fn license_discovery() {
+        let tmp = tempfile::tempdir().unwrap();
+        let load = |license_fields: &str| {
+            write_fixture_package(tmp.path(), license_fields);
+            let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+            Settings::builder(info).build().unwrap()
+        };
+
+        let settings = load("license = \"MIT OR Apache-2.0\"\n");
+        assert_eq!(settings.license_expression(), Some("MIT OR Apache-2.0"));
+        assert_eq!(settings.license_content(), None);
+        for (name, content) in [
+            ("COPYING", "copying"),
+            ("LICENSE-MIT", "mit"),
+            ("LICENSE", "license"),
+        ] {
+            std::fs::write(tmp.path().join(name), content).unwrap();
+            assert_eq!(settings.license_content().as_deref(), Some(content));
+        }
+        std::fs::write(tmp.path().join("EULA.txt"), "eula").unwrap();
+        let settings = load("license-file = \"EULA.txt\"\n");
+        assert_eq!(settings.license_expression(), None);
+        assert_eq!(settings.license_content().as_deref(), Some("eula"));
+    }
+
+    #[test]
+    fn builder_validation() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n",
+        );
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+
+        let overrides: BundleSettings = toml::from_str("name = \"Overridden\"\n").unwrap();
+        let settings = Settings::builder(info.clone())
+            .overrides(overrides)
+            .build()
+            .unwrap();
+        assert_eq!(settings.bundle_name(), "Overridden");
+        assert_eq!(settings.bundle_identifier(), "com.example.foo");
+
+        let error = |builder: super::SettingsBuilder| builder.build().unwrap_err().to_string();
+        assert!(
+            error(Settings::builder(info.clone()).binary_name("")).contains("must not be empty")
+        );
+        assert!(
+            error(Settings::builder(info.clone()).binary_name("bin/foo")).contains("not a path")
+        );
+        assert!(error(Settings::builder(info.clone()).jobs(0)).contains("at least 1"));
+        assert!(error(Settings::builder(info.clone()).sign_key(" ")).contains("signing key"));
+        let overrides = BundleSettings::from_overrides(&["deb_priority=low".to_string()]).unwrap();
+        assert!(
+            error(Settings::builder(info.clone()).overrides(overrides)).contains("deb_priority")
+        );
+        let overrides =
+            BundleSettings::from_overrides(&["appimage_compression=zip".to_string()]).unwrap();
+        assert!(
+            error(Settings::builder(info.clone()).overrides(overrides))
+                .contains("appimage_compression")
+        );
+        let overrides =
+            BundleSettings::from_overrides(&["appimage_compression=zstd".to_string()]).unwrap();
+        let settings = Settings::builder(info.clone())
+            .overrides(overrides)
+            .build()
+            .unwrap();
+        assert_eq!(settings.appimage_compression(), Some("zstd"));
+        for version in ["13", "13.x", "13..0", " 13.0", "1.2.3.4"] {
+            let overrides =
+                BundleSettings::from_overrides(&[format!("ios_minimum_os_version={version}")])
+                    .unwrap();
+            assert!(
+                error(Settings::builder(info.clone()).overrides(overrides))
+                    .contains("ios_minimum_os_version"),
+                "{version}"
+            );
+        }
+        let overrides =
+            BundleSettings::from_overrides(&["ios_minimum_os_version=12.4.1".to_string()]).unwrap();
+        let settings = Settings::builder(info.clone())
+            .overrides(overrides)
+            .build()
+            .unwrap();
+        assert_eq!(settings.ios_minimum_os_version(), Some("12.4.1"));
+        let settings = Settings::builder(info.clone()).build().unwrap();
+        assert_eq!(settings.appimage_compression(), None);
+        assert_eq!(settings.deb_priority(), "optional");
+        assert_eq!(settings.deb_section(), "misc");
+        assert!(
+            error(Settings::builder(info).build_artifact(BuildArtifact::Bin("bar".to_string())))
+                .contains("No `bin` target named 'bar'")
+        );
+    }
+
+    #[test]
+    fn reverse_dns_identifiers() {
+        assert!(is_reverse_dns("com.example.app"));
+        assert!(is_reverse_dns("io.github.user-name.project2"));
+        assert!(is_reverse_dns("foo.mycrate"));
+        assert!(!is_reverse_dns(""));
+        assert!(!is_reverse_dns("mycrate"));
+        assert!(!is_reverse_dns("com..example"));
+        assert!(!is_reverse_dns("com.example."));
+        assert!(!is_reverse_dns("com.example_app"));
+    }
+
+    #[test]
+    fn missing_identifier_is_only_a_problem_for_apple_bundles() {
+        let tmp = fixture_package("");
+        let settings = |package_type, identifier: Option<&str>| {
+            let info = BundleTargetInfo::load(tmp.path(), package_type, None).unwrap();
+            Settings::builder(info)
+                .bundle_settings(BundleSettings {
+                    identifier: identifier.map(str::to_string),
+                    ..Default::default()
+                })
+                .build()
+        };
+        let deb = settings(PackageType::Deb, None).unwrap();
+        assert_eq!(deb.identifier_problem(), None);
+        let deb = settings(PackageType::Deb, Some("mycrate")).unwrap();
+        assert!(deb.identifier_problem().unwrap().contains("\"mycrate\""));
+        assert!(settings(PackageType::OsxBundle, None).is_err());
+    }
 
     #[test]
     fn parse_cargo_toml() {
@@ -487,6 +2949,144 @@ mod tests {
         );
     }
 
+    #[test]
+    fn serialize_round_trip() {
+        let toml_str = "\
+            name = \"Example Application\"\n\
+            identifier = \"com.example.app\"\n\
+            resources_mapping = [[\"data\", \"foo/bar\"]]\n\
+            category = \"Puzzle Game\"\n\
+            long_description = \"\"\"\n\
+            This is an example of a\n\
+            simple application.\n\
+            \"\"\"\n";
+        let bundle: BundleSettings = toml::from_str(toml_str).unwrap();
+
+        let serialized = toml::to_string(&bundle).unwrap();
+        let round_tripped: BundleSettings = toml::from_str(&serialized).unwrap();
+        assert_eq!(toml::to_string(&round_tripped).unwrap(), serialized);
+        assert_eq!(round_tripped.name, "Example Application");
+        assert_eq!(round_tripped.identifier.as_deref(), Some("com.example.app"));
+        assert_eq!(round_tripped.resources_mapping, bundle.resources_mapping);
+        assert_eq!(round_tripped.category, Some(AppCategory::PuzzleGame));
+        assert_eq!(round_tripped.long_description, bundle.long_description);
+
+        // Unset and empty settings are left out entirely.
+        let json = serde_json::to_value(&bundle).unwrap();
+        let keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "category",
+                "identifier",
+                "long_description",
+                "name",
+                "resources_mapping"
+            ]
+        );
+        let from_json: BundleSettings = serde_json::from_value(json).unwrap();
+        assert_eq!(toml::to_string(&from_json).unwrap(), serialized);
+    }
+
+    #[test]
+    fn parse_linux_files() {
+        let toml_str = "\
+            [linux_files]\n\
+            \"config/default.toml\" = \"/etc/myapp/config.toml\"\n\
+            \"docs/myapp.1\" = \"/usr/share/man/man1/\"\n";
+        let bundle: BundleSettings = toml::from_str(toml_str).unwrap();
+        assert_eq!(bundle.linux_files.len(), 2);
+        assert_eq!(
+            bundle.linux_files.get("config/default.toml"),
+            Some(&"/etc/myapp/config.toml".to_string())
+        );
+        assert_eq!(
+            bundle.linux_files.get("docs/myapp.1"),
+            Some(&"/usr/share/man/man1/".to_string())
+        );
+    }
+
+    #[test]
+    fn load_metadata_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let toml_path = tmp.path().join("bundle.toml");
+        std::fs::write(&toml_path, "name = \"From TOML\"\nversion = \"1.2.3\"\n").unwrap();
+        let json_path = tmp.path().join("bundle.json");
+        std::fs::write(
+            &json_path,
+            r#"{"name": "From JSON", "category": "Utility"}"#,
+        )
+        .unwrap();
+
+        let from_toml = BundleSettings::from_file(&toml_path).unwrap();
+        assert_eq!(from_toml.name, "From TOML".to_string());
+        let from_json = BundleSettings::from_file(&json_path).unwrap();
+        assert_eq!(from_json.category, Some(AppCategory::Utility));
+
+        // Settings from the file take precedence over those they are merged with.
+        let merged = from_json.merge(from_toml);
+        assert_eq!(merged.name, "From JSON".to_string());
+        assert_eq!(merged.version, Some("1.2.3".to_string()));
+    }
+
+    #[test]
+    fn config_overrides() {
+        let overrides = [
+            "identifier=com.acme.tool".to_string(),
+            "copyright=© 2024 Acme".to_string(),
+            "category=Utility".to_string(),
+            "version=1.0".to_string(),
+            "emit_checksums=true".to_string(),
+        ];
+        let bundle = BundleSettings::from_overrides(&overrides).unwrap();
+        assert_eq!(bundle.identifier, Some("com.acme.tool".to_string()));
+        assert_eq!(bundle.copyright, Some("© 2024 Acme".to_string()));
+        assert_eq!(bundle.category, Some(AppCategory::Utility));
+        assert_eq!(bundle.version, Some("1.0".to_string()));
+        assert_eq!(bundle.emit_checksums, Some(true));
+
+        let loaded: BundleSettings =
+            toml::from_str("identifier = \"com.example.app\"\nname = \"App\"\n").unwrap();
+        let merged = bundle.merge(loaded);
+        assert_eq!(merged.identifier, Some("com.acme.tool".to_string()));
+        assert_eq!(merged.name, "App".to_string());
+
+        for (entry, error) in [
+            ("icon=icon.png", "Unsupported --config key \"icon\""),
+            ("identifier", "expected KEY=VALUE"),
+            ("emit_checksums=yes", "must be `true` or `false`"),
+        ] {
+            let message = BundleSettings::from_overrides(&[entry.to_string()])
+                .unwrap_err()
+                .to_string();
+            assert!(message.contains(error), "{message}");
+        }
+    }
+
+    #[test]
+    fn merge_bin_icon_over_package_icon() {
+        let toml_str = "\
+            icon = [\"package.png\"]\n\
+            \n\
+            [bin.foo]\n\
+            icon = [\"foo.png\"]\n\
+            \n\
+            [bin.bar]\n\
+            name = \"Bar App\"\n";
+        let bundle: BundleSettings = toml::from_str(toml_str).unwrap();
+
+        let foo = bundle.bin.get("foo").unwrap().clone().merge(bundle.clone());
+        assert_eq!(foo.icon, vec!["foo.png".to_string()]);
+
+        let bar = bundle.bin.get("bar").unwrap().clone().merge(bundle.clone());
+        assert_eq!(bar.icon, vec!["package.png".to_string()]);
+    }
+
     #[test]
     fn parse_bin_and_example_bundles() {
         let toml_str = "\
@@ -1,3 +1,4 @@
+use anyhow::Context;
 use std::{
     ffi::OsString,
     path::{Path, PathBuf},
@@ -9,7 +10,11 @@ use target_build_utils::TargetInfo;
 
 use crate::{
     Cli,
-    bundle::{BuildArtifact, PackageType, common::print_warning, metadata::BundleSettings},
+    bundle::{
+        BuildArtifact, PackageType,
+        common::{print_skipping, print_warning},
+        metadata::BundleSettings,
+    },
 };
 
 #[derive(Clone, Debug)]
@@ -20,24 +25,25 @@ pub struct BundleTargetInfo {
     project_out_directory: PathBuf,
     pub profile: String,
     pub package: Package,
+    /// The `[workspace.metadata]` table of the workspace the package is in.
+    pub workspace_metadata: Value,
+    /// The root directory of the workspace the package is in.
+    pub workspace_root: PathBuf,
+    pub metadata_file: Option<PathBuf>,
+    pub target_dir: Option<PathBuf>,
 }
 
 impl BundleTargetInfo {
     pub fn get_target_dir(&self, build_artifact: &BuildArtifact) -> PathBuf {
-        let mut cargo = std::process::Command::new(
-            std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")),
-        );
-        cargo.args(["metadata", "--no-deps", "--format-version", "1"]);
-
-        let target_dir = cargo.output().ok().and_then(|output| {
-            let json_string = String::from_utf8(output.stdout).ok()?;
-            let json: Value = serde_json::from_str(&json_string).ok()?;
-            Some(PathBuf::from(json.get("target_directory")?.as_str()?))
-        });
+        self.target_dir_for(self.target_triple.as_deref(), build_artifact)
+    }
 
-        let mut path = target_dir.unwrap_or(self.project_out_directory.join("target"));
+    /// Returns the directory cargo places `build_artifact` in when building
+    /// for `triple`, or for the host if `None`.
+    pub fn target_dir_for(&self, triple: Option<&str>, build_artifact: &BuildArtifact) -> PathBuf {
+        let mut path = self.target_root();
 
-        if let Some(triple) = self.target_triple.as_ref() {
+        if let Some(triple) = triple {
             path.push(triple);
         }
         path.push(if self.profile == "dev" {
@@ -51,33 +57,226 @@ impl BundleTargetInfo {
         path
     }
 
+    /// Returns the cargo target directory, without the target triple or
+    /// profile.
+    pub fn target_root(&self) -> PathBuf {
+        self.base_target_dir(|message| {
+            let _ = print_warning(message);
+        })
+    }
+
+    /// Returns the cargo target directory, without the target triple or
+    /// profile. If `cargo metadata` can't tell us, falls back to `target`
+    /// under the project directory and calls `warn` to explain why.
+    fn base_target_dir(&self, mut warn: impl FnMut(&str)) -> PathBuf {
+        if let Some(target_dir) = &self.target_dir {
+            return target_dir.clone();
+        }
+        let error = match self.metadata_target_dir() {
+            Ok(target_dir) => return target_dir,
+            Err(error) => error,
+        };
+        let fallback = self.project_out_directory.join("target");
+        if fallback.is_dir() {
+            warn(&format!(
+                "Failed to get the target directory from `cargo metadata` ({error:#}); \
+                 falling back to {fallback:?}"
+            ));
+        } else {
+            warn(&format!(
+                "Failed to get the target directory from `cargo metadata` ({error:#}), \
+                 and the fallback {fallback:?} does not exist"
+            ));
+        }
+        fallback
+    }
+
+    /// Asks `cargo metadata` for the target directory of the package.
+    fn metadata_target_dir(&self) -> crate::Result<PathBuf> {
+        let mut cargo = std::process::Command::new(
+            std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")),
+        );
+        cargo.args(["metadata", "--no-deps", "--format-version", "1"]);
+        cargo.arg("--manifest-path");
+        cargo.arg(&self.package.manifest_path);
+
+        let output = cargo
+            .output()
+            .with_context(|| "Failed to run cargo metadata")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let json: Value = serde_json::from_slice(&output.stdout)
+            .with_context(|| "Failed to parse the output of cargo metadata")?;
+        let target_dir = json
+            .get("target_directory")
+            .and_then(Value::as_str)
+            .with_context(|| "No target_directory in the output of cargo metadata")?;
+        Ok(PathBuf::from(target_dir))
+    }
+
+    /// Loads the package named `package_name`, or the package in or above
+    /// `dir` if `None`, from the workspace containing `dir`, to be bundled as
+    /// `package_type` for the host in the `dev` profile.
+    pub fn load(
+        dir: &Path,
+        package_type: PackageType,
+        package_name: Option<&str>,
+    ) -> crate::Result<Self> {
+        let package_dir = get_package_dir(dir);
+        let workspace_dir = get_workspace_dir(&package_dir);
+        let cargo_settings = load_metadata(&workspace_dir)
+            .with_context(|| format!("Failed to load the Cargo.toml in {workspace_dir:?}"))?;
+        let package = match package_name {
+            Some(package) => cargo_settings
+                .packages
+                .iter()
+                .find(|p| p.name.as_str() == package)
+                .ok_or_else(|| anyhow::anyhow!("Package '{package}' not found in workspace")),
+            // Like cargo, default to the member the command is run in, which
+            // isn't the root package of the workspace.
+            None => package_in_dir(&cargo_settings, &package_dir)
+                .or_else(|| cargo_settings.root_package())
+                .ok_or_else(|| anyhow::anyhow!("No root package found in workspace")),
+        }?;
+        Ok(Self {
+            target_info: None,
+            target_triple: None,
+            package_type,
+            // Relative paths in the settings are relative to the package
+            // being bundled, wherever the command was run.
+            project_out_directory: package
+                .manifest_path
+                .parent()
+                .map_or(package_dir, |dir| dir.as_std_path().to_path_buf()),
+            profile: "dev".to_string(),
+            package: package.to_owned(),
+            workspace_metadata: cargo_settings.workspace_metadata.clone(),
+            workspace_root: cargo_settings.workspace_root.clone().into(),
+            metadata_file: None,
+            target_dir: None,
+        })
+    }
+
+    /// Bundles for the target `triple` instead of the host.  Fails if the
+    /// triple is neither one `rustc` knows nor the path of a custom target
+    /// file, as the bundle's architecture and OS are taken from it.
+    pub fn with_target(mut self, triple: &str) -> crate::Result<Self> {
+        let triple = normalize_target_triple(triple);
+        self.target_info = parse_target_triple(&triple)?;
+        if self.target_info.is_none() {
+            print_warning(&format!(
+                "The architecture and OS of target {triple:?} are unknown to this version of \
+                 cargo-bundler; pass --arch and --format if the bundle's are wrong"
+            ))?;
+        }
+        self.target_triple = Some(triple);
+        Ok(self)
+    }
+
     pub fn get_project_dir(&self) -> &Path {
         self.project_out_directory.as_path()
     }
 
-    pub fn get_bundle_settings(&self, build_artifact: &BuildArtifact) -> (BundleSettings, String) {
-        let bundle_settings =
-            bundle_settings_of_package(&self.package, &self.package_type).expect("");
+    pub fn get_bundle_settings(
+        &self,
+        build_artifact: &BuildArtifact,
+    ) -> crate::Result<(BundleSettings, String)> {
+        let bundle_settings = bundle_settings_of_package(
+            &self.package,
+            &self.workspace_metadata,
+            &self.package_type,
+            self.metadata_file.as_deref(),
+        )?;
         let bundle_settings = bundle_settings_with_artifact(bundle_settings, build_artifact);
         match &build_artifact {
+            // A package may have a `lib` target with the same name as its
+            // `bin` target, so only ever look at the `bin` targets here.  Like
+            // `cargo run`, prefer the package's `default-run` binary.
             BuildArtifact::Main => {
-                if let Some(target) = self
-                    .package
-                    .targets
-                    .iter()
-                    .find(|target| target.kind.contains(&TargetKind::Bin))
-                {
-                    (bundle_settings, target.name.clone())
+                let default_run = self.package.default_run.as_ref().and_then(|default_run| {
+                    self.bin_targets()
+                        .find(|target| &target.name == default_run)
+                });
+                if let Some(target) = default_run.or_else(|| self.bin_targets().next()) {
+                    Ok((bundle_settings, target.name.clone()))
                 } else {
-                    panic!(
+                    anyhow::bail!(
                         "No `bin` target is found in package '{}'",
                         self.package.name
                     );
                 }
             }
-            BuildArtifact::Bin(name) => (bundle_settings, name.clone()),
-            BuildArtifact::Example(name) => (bundle_settings, name.clone()),
+            BuildArtifact::Bin(name) => {
+                if !self.bin_targets().any(|target| &target.name == name) {
+                    anyhow::bail!(
+                        "No `bin` target named '{name}' is found in package '{}'",
+                        self.package.name
+                    );
+                }
+                Ok((bundle_settings, name.clone()))
+            }
+            BuildArtifact::Example(name) => Ok((bundle_settings, name.clone())),
+        }
+    }
+
+    /// Returns the name of the package's `example` target that `example`
+    /// refers to: either the target's name, or the path of its source file,
+    /// relative to the current or the package directory.  The name is also
+    /// the file name of the built example.
+    pub fn example_target_name(&self, example: &str) -> crate::Result<String> {
+        let examples = || {
+            self.package
+                .targets
+                .iter()
+                .filter(|target| target.kind.contains(&TargetKind::Example))
+        };
+        if let Some(target) = examples().find(|target| target.name == example) {
+            return Ok(target.name.clone());
         }
+        let package_dir = self
+            .package
+            .manifest_path
+            .parent()
+            .map(|dir| dir.as_std_path());
+        let candidates: Vec<PathBuf> = [std::path::absolute(example).ok()]
+            .into_iter()
+            .chain([package_dir.map(|dir| dir.join(example))])
+            .flatten()
+            .filter_map(|path| path.canonicalize().ok())
+            .collect();
+        examples()
+            .find(|target| {
+                target
+                    .src_path
+                    .as_std_path()
+                    .canonicalize()
+                    .is_ok_and(|src_path| candidates.contains(&src_path))
+            })
+            .map(|target| target.name.clone())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No `example` target named or with the source file '{example}' is found in \
+                     package '{}'",
+                    self.package.name
+                )
+            })
+    }
+
+    /// Returns true if the package has a `bin` target called `name`.
+    pub fn has_bin_target(&self, name: &str) -> bool {
+        self.bin_targets().any(|target| target.name == name)
+    }
+
+    fn bin_targets(&self) -> impl Iterator<Item = &cargo_metadata::Target> {
+        self.package
+            .targets
+            .iter()
+            .filter(|target| target.kind.contains(&TargetKind::Bin))
     }
 }
 
@@ -98,7 +297,7 @@ fn bundle_settings_with_artifact(
             }
         }
         BuildArtifact::Example(example_name) => {
-            if let Some(extra_bundle_settings) = opt_map.bin.get(example_name) {
+            if let Some(extra_bundle_settings) = opt_map.example.get(example_name) {
                 extra_bundle_settings.clone().merge(opt_map)
             } else {
                 _ = print_warning(&format!(
@@ -118,91 +317,595 @@ fn load_metadata(dir: &Path) -> crate::Result<Metadata> {
         .exec()?)
 }
 
+/// Returns `triple` with surrounding whitespace removed and, unless it is
+/// the path of a custom target file, in lower case, as `rustc` spells it.
+pub fn normalize_target_triple(triple: &str) -> String {
+    let triple = triple.trim();
+    if triple.ends_with(".json") {
+        triple.to_string()
+    } else {
+        triple.to_ascii_lowercase()
+    }
+}
+
+/// Checks that `triple` is the name of a target `rustc --print target-list`
+/// lists or the path of a custom target file, and returns its target info if
+/// it is in the table built into cargo-bundler, which lacks targets added
+/// after it was built.  For an unknown triple, the error suggests the most
+/// similar of the known targets.
+pub fn parse_target_triple(triple: &str) -> crate::Result<Option<TargetInfo>> {
+    check_target_triple(triple, known_target_triples)
+}
+
+fn check_target_triple(
+    triple: &str,
+    known_target_triples: impl FnOnce() -> Vec<String>,
+) -> crate::Result<Option<TargetInfo>> {
+    if let Ok(info) = TargetInfo::from_str(triple) {
+        return Ok(Some(info));
+    }
+    let known = known_target_triples();
+    if known.iter().any(|known| known == triple) {
+        return Ok(None);
+    }
+    let suggestions = similar_target_triples(triple, &known);
+    if suggestions.is_empty() {
+        anyhow::bail!(
+            "Unknown target triple {triple:?}; run `rustc --print target-list` to list the known \
+             triples, or pass the path of a custom target file"
+        );
+    }
+    anyhow::bail!(
+        "Unknown target triple {triple:?}; did you mean {}? Run `rustc --print target-list` \
+         to list all known triples",
+        suggestions.join(", ")
+    )
+}
+
+/// Returns the targets that `rustc` knows, or none if it can't be run.
+fn known_target_triples() -> Vec<String> {
+    let mut rustc =
+        std::process::Command::new(std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into()));
+    rustc.args(["--print", "target-list"]);
+    match rustc.output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns up to three of the `known` triples most similar to `triple`, most
+/// similar first.
+fn similar_target_triples<'a>(triple: &str, known: &'a [String]) -> Vec<&'a str> {
+    const CONFIDENCE_THRESHOLD: f64 = 0.85;
+    let mut scored: Vec<(f64, &str)> = known
+        .iter()
+        .map(|known| (strsim::jaro_winkler(triple, known), known.as_str()))
+        .filter(|&(confidence, _)| confidence >= CONFIDENCE_THRESHOLD)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+    scored.into_iter().take(3).map(|(_, known)| known).collect()
+}
+
+/// Returns the nearest directory at or above `dir` with a `Cargo.toml`, or
+/// `dir` itself if there is none.
+fn get_package_dir(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
 /*
     The specification of the Cargo.toml Manifest that covers the "workspace" section is here:
     https://doc.rust-lang.org/cargo/reference/manifest.html#the-workspace-section
 
-    Determining if the current project folder is part of a workspace:
-        - Walk up the file system, looking for a Cargo.toml file.
-        - Stop at the first one found.
-        - If one is found before reaching "/" then this folder belongs to that parent workspace
+    Determining the root of the workspace that the package in `package_dir` belongs to:
+        - If its manifest sets `package.workspace`, that is the root.
+        - Otherwise walk up the file system, starting at `package_dir`, and stop at the first
+          Cargo.toml with a [workspace] section that doesn't exclude the package.
+        - If there is none, the package is its own workspace root.
 */
-fn get_workspace_dir(current_dir: PathBuf) -> PathBuf {
-    let mut dir = current_dir.clone();
-    let set = load_metadata(&dir);
-    if set.is_ok() {
-        return dir;
+fn get_workspace_dir(package_dir: &Path) -> PathBuf {
+    let workspace = read_manifest(package_dir).and_then(|manifest| {
+        let workspace = manifest.get("package")?.get("workspace")?.as_str()?;
+        Some(package_dir.join(workspace))
+    });
+    if let Some(workspace) = workspace {
+        return workspace;
     }
-    while dir.pop() {
-        let set = load_metadata(&dir);
-        if set.is_ok() {
-            return dir;
+    for dir in package_dir.ancestors() {
+        let Some(workspace) =
+            read_manifest(dir).and_then(|mut manifest| manifest.remove("workspace"))
+        else {
+            continue;
+        };
+        let excluded = workspace
+            .get("exclude")
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .any(|excluded| package_dir.starts_with(dir.join(excluded)));
+        if !excluded {
+            return dir.to_path_buf();
         }
     }
+    package_dir.to_path_buf()
+}
+
+/// Parses the `Cargo.toml` in `dir`, if there is one and it is valid TOML.
+fn read_manifest(dir: &Path) -> Option<toml::Table> {
+    std::fs::read_to_string(dir.join("Cargo.toml"))
+        .ok()?
+        .parse()
+        .ok()
+}
 
-    // Nothing found walking up the file system, return the starting directory
-    current_dir
+/// Returns the workspace member whose manifest is in `package_dir`, if any.
+fn package_in_dir<'a>(metadata: &'a Metadata, package_dir: &Path) -> Option<&'a Package> {
+    let package_dir = package_dir.canonicalize().ok()?;
+    metadata.workspace_packages().into_iter().find(|package| {
+        package
+            .manifest_path
+            .parent()
+            .and_then(|dir| dir.canonicalize().ok())
+            .is_some_and(|dir| dir == package_dir)
+    })
 }
 
 fn bundle_settings_of_package(
     package: &Package,
+    workspace_metadata: &Value,
     format: &PackageType,
+    metadata_file: Option<&Path>,
 ) -> crate::Result<BundleSettings> {
-    if let Some(bundle) = package.metadata.get("bundle") {
-        let settings = serde_json::from_value::<BundleSettings>(bundle.clone())?;
-        if let Some(extra) = settings.targets.get(format.short_name()) {
-            return Ok(extra.clone().merge(settings));
+    let workspace_bundle = workspace_metadata.get("bundle");
+    let mut settings = match package.metadata.get("bundle") {
+        Some(bundle) => {
+            serde_json::from_value::<BundleSettings>(bundle.clone()).with_context(|| {
+                format!(
+                    "Failed to parse [package.metadata.bundle] of package \"{}\"",
+                    package.name
+                )
+            })?
+        }
+        None => {
+            if metadata_file.is_none() && workspace_bundle.is_none() {
+                print_warning(&format!(
+                    "No [package.metadata.bundle] section in package \"{}\"",
+                    package.name
+                ))?;
+            }
+            BundleSettings::default()
+        }
+    };
+    if let Some(bundle) = workspace_bundle {
+        let workspace_settings = serde_json::from_value::<BundleSettings>(bundle.clone())
+            .with_context(|| "Failed to parse [workspace.metadata.bundle]")?;
+        settings = settings.merge(workspace_settings);
+    }
+    if let Some(path) = metadata_file {
+        settings = BundleSettings::from_file(path)?.merge(settings);
+    }
+    if let Some(extra) = settings.targets.get(format.short_name()) {
+        return Ok(extra.clone().merge(settings));
+    }
+    Ok(settings)
+}
+
+/// Returns the names of the members of the workspace containing `dir` that
+/// have a `[package.metadata.bundle]` section and a `bin` target.  Other
+/// members are skipped with a message saying why.
+pub fn bundleable_packages(dir: &Path) -> crate::Result<Vec<String>> {
+    let workspace_dir = get_workspace_dir(&get_package_dir(dir));
+    let cargo_settings = load_metadata(&workspace_dir)
+        .with_context(|| format!("Failed to load the Cargo.toml in {workspace_dir:?}"))?;
+    let mut packages = Vec::new();
+    for package in cargo_settings.workspace_packages() {
+        if package.metadata.get("bundle").is_none() {
+            print_skipping(&format!(
+                "{}, which has no [package.metadata.bundle] section",
+                package.name
+            ))?;
+        } else if !package
+            .targets
+            .iter()
+            .any(|target| target.kind.contains(&TargetKind::Bin))
+        {
+            print_skipping(&format!("{}, which has no bin target", package.name))?;
+        } else {
+            packages.push(package.name.to_string());
         }
-        return Ok(settings);
     }
-    print_warning(&format!(
-        "No [package.metadata.bundle] section in package \"{}\"",
-        package.name
-    ))?;
-    Ok(BundleSettings::default())
+    Ok(packages)
 }
 
-impl TryFrom<(&Cli, PackageType)> for BundleTargetInfo {
-    fn try_from(value: (&Cli, PackageType)) -> Result<Self, String> {
-        let target = value.0.get_target();
+/// Builds the target info for bundling `package` (or the root package, if
+/// `None`) in the given format.
+impl TryFrom<(&Cli, PackageType, Option<&str>)> for BundleTargetInfo {
+    fn try_from(value: (&Cli, PackageType, Option<&str>)) -> Result<Self, Self::Error> {
         let profile = if value.0.release {
             "release".to_string()
         } else if let Some(profile) = value.0.profile.as_ref() {
             if profile == "debug" {
-                return Err("Profile name `debug` is reserved".to_string());
+                anyhow::bail!("Profile name `debug` is reserved");
             }
             profile.to_string()
         } else {
             "dev".to_string()
         };
-        let package_name = value.0.package.as_deref().map(|s| s.to_string());
-        let workspace_dir = get_workspace_dir(value.0.dir.clone());
-        let cargo_settings = load_metadata(&workspace_dir).expect("1");
-        let package = match &package_name {
-            Some(package) => cargo_settings
-                .packages
-                .iter()
-                .find(|p| p.name.as_str() == package)
-                .ok_or_else(|| anyhow::anyhow!("Package '{package}' not found in workspace")),
-            None => cargo_settings
-                .root_package()
-                .ok_or_else(|| anyhow::anyhow!("No root package found in workspace")),
+        let mut info = Self::load(&value.0.dir, value.1, value.2)?;
+        if let Some(triple) = value.0.target.as_deref() {
+            info = info.with_target(triple)?;
         }
-        .expect("msg");
+        info.profile = profile;
+        info.metadata_file = value.0.metadata_file.clone();
+        info.target_dir = value.0.target_dir.clone();
+        Ok(info)
+    }
 
-        let (target_triple, target_info) = match target {
-            Some((triple, target_info)) => (Some(triple), target_info),
-            None => (None, None),
+    type Error = anyhow::Error;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BuildArtifact, BundleSettings, BundleTargetInfo, bundle_settings_with_artifact,
+        bundleable_packages, check_target_triple, get_package_dir, get_workspace_dir,
+        normalize_target_triple, similar_target_triples,
+    };
+    use crate::test_utils::{fixture_package, write_named_fixture_package};
+    use crate::{
+        Cli,
+        bundle::{PackageType, category::AppCategory},
+    };
+
+    #[test]
+    fn invalid_manifest_is_an_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("Cargo.toml"), "[package\nname = ").unwrap();
+        let cli =
+            <Cli as clap::Parser>::parse_from(["cargo-bundler", tmp.path().to_str().unwrap()]);
+        let result = BundleTargetInfo::try_from((&cli, PackageType::Deb, None));
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("Failed to load the Cargo.toml"), "{error}");
+    }
+
+    #[test]
+    fn target_triples() {
+        let tmp = fixture_package("");
+        let info_for = |triple: &str| {
+            let cli = <Cli as clap::Parser>::parse_from([
+                "cargo-bundler",
+                "--target",
+                triple,
+                tmp.path().to_str().unwrap(),
+            ]);
+            BundleTargetInfo::try_from((&cli, PackageType::Deb, None))
         };
-        Ok(Self {
-            target_info,
-            target_triple,
-            package_type: value.1,
-            project_out_directory: workspace_dir,
-            profile,
-            package: package.to_owned(),
-        })
+
+        let info = info_for(" AArch64-Unknown-Linux-GNU ").unwrap();
+        assert_eq!(
+            info.target_triple.as_deref(),
+            Some("aarch64-unknown-linux-gnu")
+        );
+        assert_eq!(info.target_info.unwrap().target_arch(), "aarch64");
+
+        let error = info_for("x86_64-unknwn-linux-gnu").unwrap_err().to_string();
+        assert!(
+            error.starts_with("Unknown target triple \"x86_64-unknwn-linux-gnu\""),
+            "{error}"
+        );
+        let error = info_for("bogus").unwrap_err().to_string();
+        assert!(error.contains("rustc --print target-list"), "{error}");
+
+        let known = [
+            "x86_64-unknown-linux-gnu".to_string(),
+            "x86_64-unknown-linux-musl".to_string(),
+            "aarch64-apple-darwin".to_string(),
+        ];
+        assert_eq!(
+            similar_target_triples("x86_64-unknwn-linux-gnu", &known)[0],
+            "x86_64-unknown-linux-gnu"
+        );
+        assert!(similar_target_triples("bogus", &known).is_empty());
+        assert_eq!(normalize_target_triple("custom/My.json"), "custom/My.json");
+
+        // Targets newer than the built-in table are accepted if rustc knows
+        // them, just without target info.
+        let newer_rustc = || vec!["riscv64gc-unknown-newos".to_string()];
+        assert!(
+            check_target_triple("riscv64gc-unknown-newos", newer_rustc)
+                .unwrap()
+                .is_none()
+        );
+        let error = check_target_triple("riscv64gc-unknown-newo", newer_rustc)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("did you mean riscv64gc-unknown-newos?"),
+            "{error}"
+        );
+        assert!(
+            check_target_triple("x86_64-unknown-linux-gnu", Vec::new)
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn metadata_failure_warns_about_fallback() {
+        let tmp = fixture_package("");
+        let cli =
+            <Cli as clap::Parser>::parse_from(["cargo-bundler", tmp.path().to_str().unwrap()]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+
+        // Break the manifest so that `cargo metadata` fails.
+        std::fs::write(tmp.path().join("Cargo.toml"), "[package\n").unwrap();
+        let fallback = tmp.path().join("target");
+        let mut warnings = Vec::new();
+        assert_eq!(
+            info.base_target_dir(|message| warnings.push(message.to_string())),
+            fallback
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("does not exist"), "{}", warnings[0]);
+
+        std::fs::create_dir(&fallback).unwrap();
+        warnings.clear();
+        assert_eq!(
+            info.base_target_dir(|message| warnings.push(message.to_string())),
+            fallback
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("falling back to"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn explicit_project_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let project_dir = tmp.path().join("apps/sub");
+        write_named_fixture_package(&project_dir, "sub-app", "");
+
+        let cli =
+            <Cli as clap::Parser>::parse_from(["cargo-bundler", project_dir.to_str().unwrap()]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        assert_eq!(info.package.name.as_str(), "sub-app");
+        assert_eq!(info.get_project_dir(), project_dir.as_path());
     }
 
-    type Error = String;
+    #[test]
+    fn nested_member_uses_workspace_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"apps/*\"]\n",
+        )
+        .unwrap();
+        for member in ["app-a", "app-b"] {
+            write_named_fixture_package(&tmp.path().join("apps").join(member), member, "");
+        }
+        let member_dir = tmp.path().join("apps/app-a");
+        assert_eq!(get_package_dir(&member_dir.join("src")), member_dir);
+        assert_eq!(get_workspace_dir(&member_dir), tmp.path());
+
+        // Run from within a member, that member is bundled by default, and
+        // its siblings can be picked.
+        let info = BundleTargetInfo::load(&member_dir.join("src"), PackageType::Deb, None).unwrap();
+        assert_eq!(info.package.name.as_str(), "app-a");
+        assert_eq!(info.get_project_dir(), member_dir.as_path());
+        assert_eq!(
+            info.workspace_root.canonicalize().unwrap(),
+            tmp.path().canonicalize().unwrap()
+        );
+        let info = BundleTargetInfo::load(&member_dir, PackageType::Deb, Some("app-b")).unwrap();
+        assert_eq!(info.package.name.as_str(), "app-b");
+        assert_eq!(
+            info.get_project_dir().canonicalize().unwrap(),
+            tmp.path().join("apps/app-b").canonicalize().unwrap()
+        );
+
+        // From the virtual workspace root, a package has to be picked.
+        let error = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap_err();
+        assert!(error.to_string().contains("No root package"), "{error}");
+    }
+
+    #[test]
+    fn standalone_package_is_its_own_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = []\nexclude = [\"standalone\"]\n",
+        )
+        .unwrap();
+        let package_dir = tmp.path().join("standalone");
+        write_named_fixture_package(&package_dir, "standalone", "");
+        assert_eq!(get_workspace_dir(&package_dir), package_dir);
+        let info = BundleTargetInfo::load(&package_dir, PackageType::Deb, None).unwrap();
+        assert_eq!(info.package.name.as_str(), "standalone");
+        assert_eq!(
+            info.workspace_root.canonicalize().unwrap(),
+            package_dir.canonicalize().unwrap()
+        );
+
+        // A package with a [workspace] section of its own is its root, too.
+        let nested_dir = tmp.path().join("nested");
+        write_named_fixture_package(&nested_dir, "nested", "\n[workspace]\n");
+        assert_eq!(get_workspace_dir(&nested_dir), nested_dir);
+
+        // Without any manifest, the directory itself is used.
+        let empty_dir = tempfile::tempdir().unwrap();
+        assert_eq!(get_package_dir(empty_dir.path()), empty_dir.path());
+        assert_eq!(get_workspace_dir(empty_dir.path()), empty_dir.path());
+    }
+
+    #[test]
+    fn workspace_bundle_settings_are_inherited() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\n\
+             members = [\"app-a\", \"app-b\"]\n\
+             \n\
+             [workspace.metadata.bundle]\n\
+             copyright = \"Copyright (c) Example Ltd\"\n\
+             category = \"Utility\"\n",
+        )
+        .unwrap();
+        for (member, name) in [("app-a", "App A"), ("app-b", "App B")] {
+            write_named_fixture_package(
+                &tmp.path().join(member),
+                member,
+                &format!("\n[package.metadata.bundle]\nname = \"{name}\"\ncategory = \"Game\"\n"),
+            );
+        }
+
+        for (member, name) in [("app-a", "App A"), ("app-b", "App B")] {
+            let cli =
+                <Cli as clap::Parser>::parse_from(["cargo-bundler", tmp.path().to_str().unwrap()]);
+            let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, Some(member))).unwrap();
+            // Relative paths are resolved against the member, not the root.
+            assert_eq!(
+                info.get_project_dir().canonicalize().unwrap(),
+                tmp.path().join(member).canonicalize().unwrap()
+            );
+            let (settings, _) = info.get_bundle_settings(&BuildArtifact::Main).unwrap();
+            assert_eq!(settings.name, name);
+            assert_eq!(
+                settings.copyright.as_deref(),
+                Some("Copyright (c) Example Ltd")
+            );
+            // Package settings take precedence over workspace ones.
+            assert_eq!(settings.category, Some(AppCategory::Game));
+        }
+    }
+
+    #[test]
+    fn bundleable_workspace_members() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app\", \"lib\", \"tool\"]\n",
+        )
+        .unwrap();
+        // Only `app` has both bundle metadata and a bin target.
+        let members = [
+            ("app", "[package.metadata.bundle]\n"),
+            ("lib", "[package.metadata.bundle]\n"),
+            ("tool", ""),
+        ];
+        for (member, metadata) in members {
+            write_named_fixture_package(&tmp.path().join(member), member, metadata);
+        }
+        let lib_src = tmp.path().join("lib/src");
+        std::fs::rename(lib_src.join("main.rs"), lib_src.join("lib.rs")).unwrap();
+        assert_eq!(
+            bundleable_packages(tmp.path()).unwrap(),
+            vec!["app".to_string()]
+        );
+    }
+
+    #[test]
+    fn default_run_binary_is_bundled() {
+        let tmp = fixture_package("default-run = \"viewer\"\n");
+        std::fs::create_dir_all(tmp.path().join("src/bin")).unwrap();
+        std::fs::write(tmp.path().join("src/bin/editor.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(tmp.path().join("src/bin/viewer.rs"), "fn main() {}\n").unwrap();
+
+        let cli =
+            <Cli as clap::Parser>::parse_from(["cargo-bundler", tmp.path().to_str().unwrap()]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        let (_, binary_name) = info.get_bundle_settings(&BuildArtifact::Main).unwrap();
+        assert_eq!(binary_name, "viewer");
+    }
+
+    #[test]
+    fn examples_by_name_or_path() {
+        let tmp = fixture_package(
+            "\n[[example]]\nname = \"viewer-demo\"\npath = \"examples/demos/view.rs\"\n",
+        );
+        std::fs::create_dir_all(tmp.path().join("examples/demos")).unwrap();
+        std::fs::write(tmp.path().join("examples/demos/view.rs"), "fn main() {}\n").unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+
+        assert_eq!(
+            info.example_target_name("viewer-demo").unwrap(),
+            "viewer-demo"
+        );
+        assert_eq!(
+            info.example_target_name("examples/demos/view.rs").unwrap(),
+            "viewer-demo"
+        );
+        let src_path = tmp.path().join("examples/demos/view.rs");
+        assert_eq!(
+            info.example_target_name(src_path.to_str().unwrap())
+                .unwrap(),
+            "viewer-demo"
+        );
+        let error = info.example_target_name("view").unwrap_err().to_string();
+        assert!(error.contains("No `example` target"), "{error}");
+
+        let settings = crate::Settings::builder(info)
+            .build_artifact(BuildArtifact::Example("examples/demos/view.rs".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(settings.binary_name(), "viewer-demo");
+        assert!(matches!(
+            settings.build_artifact(),
+            BuildArtifact::Example(name) if name == "viewer-demo"
+        ));
+        assert!(
+            settings
+                .binary_path(PackageType::Deb)
+                .ends_with("examples/viewer-demo")
+        );
+    }
+
+    #[test]
+    fn misspelled_category_is_reported() {
+        let tmp = fixture_package("\n[package.metadata.bundle]\ncategory = \"Pzzle Game\"\n");
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::Deb, None).unwrap();
+        let error = info.get_bundle_settings(&BuildArtifact::Main).unwrap_err();
+        let error = format!("{error:#}");
+        assert!(
+            error.starts_with("Failed to parse [package.metadata.bundle] of package \"foo\""),
+            "{error}"
+        );
+        assert!(error.contains("did you mean \"Puzzle Game\"?"), "{error}");
+    }
+
+    #[test]
+    fn debug_profile_is_reserved() {
+        let cli = <Cli as clap::Parser>::parse_from(["cargo-bundler", "--profile", "debug", "."]);
+        let result = BundleTargetInfo::try_from((&cli, PackageType::Deb, None));
+        let error = result.unwrap_err().to_string();
+        assert_eq!(error, "Profile name `debug` is reserved");
+    }
+
+    #[test]
+    fn example_settings_come_from_example_map() {
+        let toml_str = "\
+            [bin.foo]\n\
+            name = \"Foo App\"\n\
+            \n\
+            [bin.bar]\n\
+            name = \"Bar App\"\n\
+            \n\
+            [example.baz]\n\
+            name = \"Baz Example\"\n";
+        let bundle: BundleSettings = toml::from_str(toml_str).unwrap();
+
+        let baz = bundle_settings_with_artifact(
+            bundle.clone(),
+            &BuildArtifact::Example("baz".to_string()),
+        );
+        assert_eq!(baz.name, "Baz Example".to_string());
+
+        // A bin section must not be applied to an example of the same name.
+        let foo = bundle_settings_with_artifact(bundle, &BuildArtifact::Example("foo".to_string()));
+        assert_eq!(foo.name, "".to_string());
+    }
 }
@@ -0,0 +1,145 @@
+// `--watch` notices changes by polling the modification times of the watched
+// files rather than subscribing to file system events.  Polling a package's
+// sources every so often is cheap, and works the same on every platform and
+// file system, including network mounts that don't deliver events.
+
+use crate::bundle::common;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// How often the watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the watched files must stay unchanged after a change before
+/// rebundling, so that e.g. saving several files at once or a `git checkout`
+/// triggers a single rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The modification times of the files below a set of paths.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Snapshot(BTreeMap<PathBuf, Option<SystemTime>>);
+
+impl Snapshot {
+    /// Records the files below `paths`, skipping hidden files and
+    /// directories and anything below one of the `excluded` directories.
+    pub fn take(paths: &[PathBuf], excluded: &[PathBuf]) -> Self {
+        let mut files = BTreeMap::new();
+        for path in paths {
+            let entries = WalkDir::new(path).into_iter().filter_entry(|entry| {
+                let hidden =
+                    entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.');
+                !hidden && !excluded.iter().any(|dir| entry.path().starts_with(dir))
+            });
+            for entry in entries.flatten() {
+                if entry.file_type().is_file() {
+                    let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+                    files.insert(entry.into_path(), modified);
+                }
+            }
+        }
+        Snapshot(files)
+    }
+
+    /// Returns the files that were added, removed or modified since
+    /// `earlier`, in order of path.
+    fn changes_since(&self, earlier: &Snapshot) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = self
+            .0
+            .iter()
+            .filter(|&(path, modified)| earlier.0.get(path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .chain(
+                earlier
+                    .0
+                    .keys()
+                    .filter(|path| !self.0.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        changed
+    }
+}
+
+/// Blocks until a file below `paths` was added, removed or modified since the
+/// `before` snapshot was taken, and then until the files stop changing, and
+/// returns the changed files.  Hidden files and the `excluded` directories
+/// (such as the cargo target directory, which bundling itself writes to) are
+/// ignored.
+pub fn wait_for_changes(
+    before: &Snapshot,
+    paths: &[PathBuf],
+    excluded: &[PathBuf],
+) -> Vec<PathBuf> {
+    let mut latest = loop {
+        let snapshot = Snapshot::take(paths, excluded);
+        if snapshot != *before {
+            break snapshot;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    loop {
+        thread::sleep(DEBOUNCE);
+        let snapshot = Snapshot::take(paths, excluded);
+        if snapshot == latest {
+            return latest.changes_since(before);
+        }
+        latest = snapshot;
+    }
+}
+
+/// Prints a `--watch` status message, in the same format as
+/// `print_bundling`.
+pub fn print_watch_status(msg: &str) -> crate::Result<()> {
+    common::print_progress("Watching", msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Snapshot, wait_for_changes};
+
+    #[test]
+    fn snapshot_changes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let src = tmp.path().join("src");
+        let target = tmp.path().join("target");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::create_dir_all(tmp.path().join(".git")).unwrap();
+        std::fs::write(src.join("main.rs"), "fn main() {}\n").unwrap();
+        let paths = [tmp.path().to_path_buf()];
+        let excluded = [target.clone()];
+        let before = Snapshot::take(&paths, &excluded);
+        assert_eq!(before.0.keys().collect::<Vec<_>>(), [&src.join("main.rs")]);
+
+        // Neither the excluded nor hidden directories are watched.
+        std::fs::write(target.join("foo"), "binary").unwrap();
+        std::fs::write(tmp.path().join(".git/index"), "index").unwrap();
+        assert_eq!(Snapshot::take(&paths, &excluded), before);
+
+        std::fs::write(src.join("lib.rs"), "").unwrap();
+        std::fs::remove_file(src.join("main.rs")).unwrap();
+        let after = Snapshot::take(&paths, &excluded);
+        assert_eq!(
+            after.changes_since(&before),
+            [src.join("lib.rs"), src.join("main.rs")]
+        );
+    }
+
+    #[test]
+    fn changes_during_a_build_are_noticed() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("main.rs"), "").unwrap();
+        let paths = [tmp.path().to_path_buf()];
+        let before = Snapshot::take(&paths, &[]);
+        // Saved while bundling, i.e. before waiting for changes.
+        std::fs::write(tmp.path().join("lib.rs"), "").unwrap();
+        assert_eq!(
+            wait_for_changes(&before, &paths, &[]),
+            [tmp.path().join("lib.rs")]
+        );
+    }
+}
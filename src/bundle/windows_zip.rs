@@ -0,0 +1,143 @@
+// A portable Windows zip holds the executable and its resources below a
+// single top-level directory, for users who don't want an installer:
+//
+// foobar-1.2.3-x86_64.zip
+//     foobar-1.2.3-x86_64/
+//         foobar.exe            # Binary executable file
+//         README.md             # The package's readme, if it has one
+//         ...                   # Resource files
+//
+// The resources are laid out relative to the executable, as the MSI
+// installers place them in the install directory.
+
+use crate::bundle::{PackageType, Settings, common};
+use anyhow::Context;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
+    let arch = settings.binary_arch();
+    let zip_base_name = format!(
+        "{}-{}-{}",
+        settings.binary_name(),
+        settings.version_string(),
+        arch
+    );
+    let zip_name = format!(
+        "{}.zip",
+        settings.artifact_name(arch, zip_base_name.clone())
+    );
+    common::print_bundling(&zip_name)?;
+    let zip_path = settings.bundle_dir(PackageType::WindowsZip).join(&zip_name);
+    common::prepare_output_path(&zip_path, settings.force())?;
+
+    let top_dir = Path::new(&zip_base_name);
+    let mut files = vec![(
+        settings.binary_path(PackageType::WindowsZip),
+        top_dir.join(format!("{}.exe", settings.binary_name())),
+    )];
+    if let Some(readme) = settings.readme_path() {
+        let name = readme.file_name().unwrap_or_default();
+        files.push((readme.clone(), top_dir.join(name)));
+    }
+    files.extend(settings.resources_paths(top_dir));
+
+    let mut zip = zip::ZipWriter::new(common::create_file(&zip_path)?);
+    add_files(&mut zip, &files).with_context(|| format!("Failed to write {zip_name}"))?;
+    zip.finish()?;
+    Ok(vec![zip_path])
+}
+
+/// Adds each `(src, dest)` pair of `files` to the zip, copying the file at
+/// `src` to the entry named `dest`.  Entries are added in order of name.
+fn add_files<W: io::Write + io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    files: &[(PathBuf, PathBuf)],
+) -> crate::Result<()> {
+    let mut files: Vec<_> = files
+        .iter()
+        // Zip entry names always use forward slashes.
+        .map(|(src, dest)| (src, dest.to_string_lossy().replace('\\', "/")))
+        .collect();
+    files.sort_by(|(_, a), (_, b)| a.cmp(b));
+    for (src, name) in files {
+        common::log_verbose("Archiving", &name)?;
+        zip.start_file(name, SimpleFileOptions::default())?;
+        io::copy(
+            &mut File::open(src).with_context(|| format!("Failed to open {src:?}"))?,
+            zip,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bundle::{PackageType, Settings, target_info::BundleTargetInfo};
+    use crate::test_utils::write_fixture_package;
+    use std::io::Read;
+    #[cfg(unix)]
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn portable_zip() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join("assets/sub")).unwrap();
+        std::fs::write(tmp.path().join("assets/sub/data.txt"), "data").unwrap();
+        std::fs::write(tmp.path().join("README.md"), "# foo\n").unwrap();
+        write_fixture_package(
+            tmp.path(),
+            &format!(
+                "readme = \"README.md\"\n\
+                 \n\
+                 [package.metadata.bundle]\nresources_mapping = [[{:?}, \"assets\"]]\n\
+                 filename_template = \"{{name}}-{{version}}-portable\"\n",
+                tmp.path().join("assets/*").display().to_string()
+            ),
+        );
+        let binary = tmp.path().join("prebuilt/foo.exe");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, b"MZ").unwrap();
+        #[cfg(unix)]
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let info = BundleTargetInfo::load(tmp.path(), PackageType::WindowsZip, None).unwrap();
+        let settings = Settings::builder(info)
+            .prebuilt_binary(&binary)
+            .checksums(true)
+            .build()
+            .unwrap();
+
+        let output_paths = crate::bundle(&settings, PackageType::WindowsZip).unwrap();
+        assert_eq!(
+            output_paths,
+            [settings
+                .bundle_dir(PackageType::WindowsZip)
+                .join("foo-0.1.0-portable.zip")]
+        );
+        assert!(output_paths[0].with_extension("zip.sha256").is_file());
+        let mut archive =
+            zip::ZipArchive::new(std::fs::File::open(&output_paths[0]).unwrap()).unwrap();
+        let names: Vec<String> = archive
+            .file_names()
+            .map(|name| name.unwrap().into_owned())
+            .collect();
+        let top_dir = format!("foo-0.1.0-{}", settings.binary_arch());
+        assert_eq!(
+            names,
+            [
+                format!("{top_dir}/README.md"),
+                format!("{top_dir}/assets/sub/data.txt"),
+                format!("{top_dir}/foo.exe"),
+            ]
+        );
+        let mut contents = String::new();
+        archive
+            .by_name(&format!("{top_dir}/assets/sub/data.txt"))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "data");
+    }
+}
@@ -1,11 +1,8 @@
-use super::settings::Settings;
+use super::settings::{MsiPlatform, Settings};
 use anyhow::Context;
 use quick_xml::se::Serializer;
 use serde::Serialize;
-use std::{
-    path::{Path, PathBuf},
-    process::Stdio,
-};
+use std::path::{Path, PathBuf};
 
 // A v4 UUID that was generated specifically for cargo-bundle, to be used as a
 // namespace for generating v5 UUIDs from bundle identifier strings.
@@ -14,23 +11,31 @@ const UUID_NAMESPACE: uuid::Uuid = uuid::Uuid::from_bytes([
 ]);
 
 pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
-    crate::bundle::common::print_warning("MSI bundle support is still experimental.")?;
+    crate::bundle::common::print_note("MSI bundle support is still experimental.")?;
+    let platform = settings.msi_platform()?;
 
     let base_dir = settings.get_target_dir().to_path_buf();
     std::fs::create_dir_all(&base_dir)?;
 
-    let package_dir = base_dir.join("bundle/wsxmsi");
+    let package_dir = settings.bundle_dir(crate::bundle::PackageType::WxsMsi);
+    let bundle_name = settings.bundle_name();
+    let output_name = sanitize_identifier(bundle_name.as_str(), '-', true);
+    let target_output_path = package_dir.join(format!(
+        "{}.msi",
+        settings.artifact_name(settings.binary_arch(), output_name.clone())
+    ));
+    crate::bundle::common::prepare_output_path(&target_output_path, settings.force())?;
     if package_dir.exists() {
         std::fs::remove_dir_all(&package_dir)
             .with_context(|| "Failed to remove old bundle".to_string())?;
     }
     // Generate .wixproj file
     let wixproj_path = base_dir.join("installer.wixproj");
-    std::fs::write(&wixproj_path, generate_wixproj_file(settings))?;
+    std::fs::write(&wixproj_path, generate_wixproj_file(settings, platform))?;
 
     // Generate .wxs file
     let wxs_path = base_dir.join("installer.wxs");
-    generate_wxs_file(&wxs_path, settings)?;
+    generate_wxs_file(&wxs_path, settings, platform)?;
 
     // Run dotnet build to generate MSI
     // For example: `dotnet build path/to/installer.wixproj -c Release`
@@ -38,25 +43,13 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
         "release" => "Release",
         _ => "Debug",
     };
-    let cmd = std::process::Command::new("dotnet")
+    let mut dotnet = std::process::Command::new("dotnet");
+    dotnet
         .args(["build", wixproj_path.to_str().unwrap(), "-c", configuration])
         .env("DOTNET_CLI_UI_LANGUAGE", "en")
-        .current_dir(settings.target.get_project_dir())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
-    let output = cmd.wait_with_output()?;
-
-    if !output.status.success() {
-        return Err(anyhow::anyhow!(
-            "Failed to build MSI: {}",
-            String::from_utf8_lossy(&output.stderr) + String::from_utf8_lossy(&output.stdout)
-        ));
-    }
-    let bundle_name = settings.bundle_name();
-
-    let output_name = sanitize_identifier(bundle_name.as_str(), '-', true);
-    let target_output_path = package_dir.join(format!("{output_name}.msi"));
+        .current_dir(settings.target.get_project_dir());
+    crate::bundle::common::run_command(&mut dotnet, crate::bundle::common::TOOL_TIMEOUT)
+        .with_context(|| "Failed to build MSI")?;
     let msi_path = base_dir
         .join("bin")
         .join(configuration)
@@ -66,18 +59,25 @@ pub fn bundle_project(settings: &Settings) -> crate::Result<Vec<PathBuf>> {
             .with_context(|| "Failed to remove old bundle".to_string())?;
     }
     std::fs::create_dir_all(&package_dir)?;
+    crate::bundle::common::log_verbose(
+        "Copying",
+        &format!("{} -> {}", msi_path.display(), target_output_path.display()),
+    )?;
     std::fs::copy(&msi_path, &target_output_path)?;
     std::fs::remove_file(msi_path)?;
     Ok(vec![target_output_path])
 }
 
-fn generate_wixproj_file(settings: &Settings) -> String {
+fn generate_wixproj_file(settings: &Settings, platform: MsiPlatform) -> String {
     let bundle_name = settings.bundle_name();
     let output_name = sanitize_identifier(bundle_name.as_str(), '-', true);
 
     let wix_project = WixProject {
         sdk: "WixToolset.Sdk/6.0.2".to_string(),
-        property_group: PropertyGroup { output_name },
+        property_group: PropertyGroup {
+            output_name,
+            installer_platform: platform.wix_name().to_string(),
+        },
         item_group: ItemGroup {
             package_reference: PackageReference {
                 include: "WixToolset.UI.wixext".to_string(),
@@ -94,7 +94,11 @@ fn generate_wixproj_file(settings: &Settings) -> String {
     buffer
 }
 
-fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()> {
+fn generate_wxs_file(
+    wxs_path: &Path,
+    settings: &Settings,
+    platform: MsiPlatform,
+) -> crate::Result<()> {
     let product_name = settings.bundle_name();
     let version = settings.version_string();
     let manufacturer = settings.authors_comma_separated().unwrap_or_default();
@@ -107,9 +111,26 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
     // Generate dynamic executable ID from binary name
     let exe_id = sanitize_identifier(binary_name.as_str(), '_', false);
 
-    // Generate license RTF file
-    let license_rtf_path = settings.target.get_project_dir().join("License.rtf");
-    generate_license_rtf(&license_rtf_path, settings)?;
+    // Use the given RTF license, or generate one from the license text
+    let license_rtf_path = match settings.msi_license_rtf() {
+        Some(path) => {
+            if !path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("rtf"))
+            {
+                anyhow::bail!("msi_license_rtf {path:?} is not an .rtf file");
+            }
+            if !path.is_file() {
+                anyhow::bail!("msi_license_rtf {path:?} does not exist");
+            }
+            path
+        }
+        None => {
+            let path = settings.target.get_project_dir().join("License.rtf");
+            generate_license_rtf(&path, settings)?;
+            path
+        }
+    };
 
     // Build components from binary and resources
     let mut installfolder_components = Vec::new();
@@ -230,6 +251,59 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
         format!("{manufacturer}{product_name}DesktopFolderShortcut").as_bytes(),
     );
 
+    // Only put a shortcut on the desktop when asked to
+    let desktop_directory = settings
+        .msi_create_desktop_shortcut()
+        .then(|| StandardDirectory {
+            id: "DesktopFolder".to_string(),
+            directory: None,
+            component: Some(Component {
+                id: Some("DesktopFolderShortcut".to_string()),
+                guid: Some(desktop_folder_shortcut_guid.to_string()),
+                registry_value: Some(RegistryValue {
+                    root: "HKCU".to_string(),
+                    key: format!("Software\\{}\\{product_name}", manufacturer.to_lowercase()),
+                    name: "installed".to_string(),
+                    value_type: "integer".to_string(),
+                    value: "1".to_string(),
+                    key_path: "yes".to_string(),
+                }),
+                shortcut: Some(Shortcut {
+                    id: "DesktopShortcut".to_string(),
+                    name: product_name.to_string(),
+                    description: None,
+                    target: format!("[#{exe_id}]"),
+                    icon: main_icon_id.to_string(),
+                    working_directory: "INSTALLFOLDER".to_string(),
+                }),
+                ..Component::default()
+            }),
+        });
+    let mut feature_component_refs = vec![ComponentRef {
+        id: "RegistryComponent".to_string(),
+    }];
+    if desktop_directory.is_some() {
+        feature_component_refs.push(ComponentRef {
+            id: "DesktopFolderShortcut".to_string(),
+        });
+    }
+    let start_menu_folder = settings.msi_start_menu_folder();
+    let build_info_properties = settings
+        .build_info()
+        .map(|build_info| {
+            vec![
+                Property {
+                    id: "BuildGitCommit".to_string(),
+                    value: build_info.git_commit,
+                },
+                Property {
+                    id: "BuildTimestamp".to_string(),
+                    value: build_info.timestamp,
+                },
+            ]
+        })
+        .unwrap_or_default();
+
     // Build the complete WiX document structure
     let wix_doc = WixDocument {
         xmlns: "http://wixtoolset.org/schemas/v4/wxs".to_string(),
@@ -256,19 +330,12 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
                 component_group_ref: ComponentGroupRef {
                     id: "ProductComponents".to_string(),
                 },
-                component_ref: vec![
-                    ComponentRef {
-                        id: "RegistryComponent".to_string(),
-                    },
-                    ComponentRef {
-                        id: "DesktopFolderShortcut".to_string(),
-                    },
-                ],
+                component_ref: feature_component_refs,
             },
             wix_ui: WixUI {
                 id: "WixUI_InstallDir".to_string(),
             },
-            properties: vec![
+            properties: [
                 Property {
                     id: "WIXUI_INSTALLDIR".to_string(),
                     value: "INSTALLFOLDER".to_string(),
@@ -281,7 +348,10 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
                     id: "WIXUI_EXITDIALOGOPTIONALCHECKBOX".to_string(),
                     value: "1".to_string(),
                 },
-            ],
+            ]
+            .into_iter()
+            .chain(build_info_properties)
+            .collect(),
             custom_action: CustomAction {
                 id: "LaunchApplication".to_string(),
                 directory: "INSTALLFOLDER".to_string(),
@@ -310,90 +380,67 @@ fn generate_wxs_file(wxs_path: &Path, settings: &Settings) -> crate::Result<()>
         },
         fragments: vec![
             Fragment {
-                standard_directories: Some(vec![
-                    StandardDirectory {
-                        id: "ProgramFilesFolder".to_string(),
-                        directory: Some(Directory {
-                            id: "INSTALLFOLDER".to_string(),
-                            name: product_name.to_string(),
-                            directories: root_directories,
-                            components: installfolder_components,
-                        }),
-                        component: None,
-                    },
-                    StandardDirectory {
-                        id: "ProgramMenuFolder".to_string(),
-                        directory: Some(Directory {
-                            id: "ApplicationProgramsFolder".to_string(),
-                            name: product_name.to_string(),
-                            components: vec![Component {
-                                id: Some("RegistryComponent".to_string()),
-                                guid: Some(program_menu_folder_guid.to_string()),
-                                registry_value: Some(RegistryValue {
-                                    root: "HKCU".to_string(),
-                                    key: format!(
-                                        "Software\\{}\\{product_name}",
-                                        manufacturer.to_lowercase(),
-                                    ),
-                                    name: "installed".to_string(),
-                                    value_type: "integer".to_string(),
-                                    value: "1".to_string(),
-                                    key_path: "yes".to_string(),
-                                }),
-                                shortcut: Some(Shortcut {
-                                    id: "ApplicationStartMenuShortcut".to_string(),
-                                    name: product_name.to_string(),
-                                    description: Some(product_name.to_string()),
-                                    target: format!("[#{exe_id}]"),
-                                    icon: main_icon_id.to_string(),
-                                    working_directory: "INSTALLFOLDER".to_string(),
-                                }),
-                                remove_folder: Some(RemoveFolder {
-                                    id: "RemoveAppProgramsFolder".to_string(),
-                                    directory: "ApplicationProgramsFolder".to_string(),
-                                    on: "uninstall".to_string(),
-                                }),
-                                remove_file: Some(RemoveFile {
-                                    id: "RemoveAppPrograms".to_string(),
-                                    directory: "ApplicationProgramsFolder".to_string(),
-                                    name: "*.*".to_string(),
-                                    on: "uninstall".to_string(),
-                                }),
-                                file: None,
-                            }],
-                            directories: vec![],
-                        }),
-                        component: None,
-                    },
-                    StandardDirectory {
-                        id: "DesktopFolder".to_string(),
-                        directory: None,
-                        component: Some(Component {
-                            id: Some("DesktopFolderShortcut".to_string()),
-                            guid: Some(desktop_folder_shortcut_guid.to_string()),
-                            registry_value: Some(RegistryValue {
-                                root: "HKCU".to_string(),
-                                key: format!(
-                                    "Software\\{}\\{product_name}",
-                                    manufacturer.to_lowercase(),
-                                ),
-                                name: "installed".to_string(),
-                                value_type: "integer".to_string(),
-                                value: "1".to_string(),
-                                key_path: "yes".to_string(),
-                            }),
-                            shortcut: Some(Shortcut {
-                                id: "DesktopShortcut".to_string(),
+                standard_directories: Some(
+                    vec![
+                        StandardDirectory {
+                            id: platform.program_files_folder().to_string(),
+                            directory: Some(Directory {
+                                id: "INSTALLFOLDER".to_string(),
                                 name: product_name.to_string(),
-                                description: None,
-                                target: format!("[#{exe_id}]"),
-                                icon: main_icon_id.to_string(),
-                                working_directory: "INSTALLFOLDER".to_string(),
+                                directories: root_directories,
+                                components: installfolder_components,
                             }),
-                            ..Component::default()
-                        }),
-                    },
-                ]),
+                            component: None,
+                        },
+                        StandardDirectory {
+                            id: "ProgramMenuFolder".to_string(),
+                            directory: Some(Directory {
+                                id: "ApplicationProgramsFolder".to_string(),
+                                name: start_menu_folder,
+                                components: vec![Component {
+                                    id: Some("RegistryComponent".to_string()),
+                                    guid: Some(program_menu_folder_guid.to_string()),
+                                    registry_value: Some(RegistryValue {
+                                        root: "HKCU".to_string(),
+                                        key: format!(
+                                            "Software\\{}\\{product_name}",
+                                            manufacturer.to_lowercase(),
+                                        ),
+                                        name: "installed".to_string(),
+                                        value_type: "integer".to_string(),
+                                        value: "1".to_string(),
+                                        key_path: "yes".to_string(),
+                                    }),
+                                    shortcut: Some(Shortcut {
+                                        id: "ApplicationStartMenuShortcut".to_string(),
+                                        name: product_name.to_string(),
+                                        description: Some(product_name.to_string()),
+                                        target: format!("[#{exe_id}]"),
+                                        icon: main_icon_id.to_string(),
+                                        working_directory: "INSTALLFOLDER".to_string(),
+                                    }),
+                                    remove_folder: Some(RemoveFolder {
+                                        id: "RemoveAppProgramsFolder".to_string(),
+                                        directory: "ApplicationProgramsFolder".to_string(),
+                                        on: "uninstall".to_string(),
+                                    }),
+                                    remove_file: Some(RemoveFile {
+                                        id: "RemoveAppPrograms".to_string(),
+                                        directory: "ApplicationProgramsFolder".to_string(),
+                                        name: "*.*".to_string(),
+                                        on: "uninstall".to_string(),
+                                    }),
+                                    file: None,
+                                }],
+                                directories: vec![],
+                            }),
+                            component: None,
+                        },
+                    ]
+                    .into_iter()
+                    .chain(desktop_directory)
+                    .collect(),
+                ),
                 component_group: None,
             },
             Fragment {
@@ -713,6 +760,8 @@ struct WixProject {
 struct PropertyGroup {
     #[serde(rename = "OutputName")]
     output_name: String,
+    #[serde(rename = "InstallerPlatform")]
+    installer_platform: String,
 }
 
 #[derive(Serialize)]
@@ -734,7 +783,21 @@ fn rtf_safe_content(origin_content: &str) -> String {
         .replace('\\', "\\\\")
         .replace('{', "\\{")
         .replace('}', "\\}")
-        .replace('\n', "\\par\n");
+        .replace('\n', "\\par\n")
+        .chars()
+        .fold(String::new(), |mut rtf, c| {
+            // RTF is 7-bit; anything else is written as a signed UTF-16 code
+            // unit followed by a `?` fallback for readers without Unicode.
+            if c.is_ascii() {
+                rtf.push(c);
+            } else {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    rtf.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+            rtf
+        });
     let rtf_output = format!(
         r#"{{\rtf1\ansi\deff0
 {{\fonttbl{{\f0 Arial;}}}}
@@ -771,7 +834,11 @@ fn generate_component_id_from_path(path_str: &str) -> String {
 fn generate_license_rtf(rtf_path: &Path, settings: &Settings) -> crate::Result<()> {
     let license_content = settings
         .license_content()
-        .or_else(find_default_license)
+        .or_else(|| {
+            settings
+                .license_expression()
+                .map(|expression| format!("This software is licensed under {expression}."))
+        })
         .unwrap_or_else(|| "This software is licensed under the MIT License.".to_string());
 
     let rtf_content = rtf_safe_content(&license_content);
@@ -779,56 +846,43 @@ fn generate_license_rtf(rtf_path: &Path, settings: &Settings) -> crate::Result<(
     Ok(())
 }
 
-fn find_default_license() -> Option<String> {
-    [
-        "License_MIT.md",
-        "License_Apache.md",
-        "LICENSE",
-        "LICENSE_MIT",
-        "LICENSE_APACHE",
-        "LICENSE.txt",
-        "LICENSE-MIT",
-        "LICENSE-APACHE",
-        "COPYING",
-    ]
-    .iter()
-    .find_map(|&filename| std::fs::read_to_string(filename).ok())
-}
-
 fn get_icon_path(settings: &Settings) -> PathBuf {
     let package_dir = settings.target.get_project_dir();
+    let icon_paths: Vec<PathBuf> = settings
+        .icon_files()
+        .filter_map(|icon_result| icon_result.ok())
+        .map(|icon_path| package_dir.join(icon_path))
+        .filter(|full_path| full_path.exists())
+        .collect();
 
-    // Try to get the first icon file from BundleSettings.icon
-    if let Some(icon_result) = settings.icon_files().next()
-        && let Ok(icon_path) = icon_result
-    {
-        let full_path = package_dir.join(icon_path);
-
-        // Check if the icon file exists
-        if full_path.exists() {
-            let extension = full_path
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-
-            // WiX supports ICO, EXE, and DLL formats for icons
-            if matches!(extension.as_str(), "ico" | "exe" | "dll") {
-                return full_path;
-            }
+    // WiX supports ICO, EXE, and DLL formats for icons
+    let is_wix_icon = |path: &&PathBuf| {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        matches!(extension.as_str(), "ico" | "exe" | "dll")
+    };
+    if let Some(icon_path) = icon_paths.iter().find(is_wix_icon) {
+        return icon_path.clone();
+    }
 
-            let out_dir = settings.get_target_dir();
+    let ico_path = settings
+        .get_target_dir()
+        .join(format!("{}-generated.ico", settings.binary_name()));
 
-            let file_stem = full_path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("icon");
+    // Build a multi-resolution icon from the PNG files, if there are any.
+    if let Ok(file) = crate::bundle::common::create_file(&ico_path)
+        && let Ok(true) = crate::bundle::common::write_ico_from_pngs(&icon_paths, file)
+    {
+        return ico_path;
+    }
 
-            let ico_path = out_dir.join(format!("{file_stem}-generated.ico"));
-            if convert_to_ico(&full_path, &ico_path).is_ok() {
-                return ico_path;
-            }
-        }
+    if let Some(icon_path) = icon_paths.first()
+        && convert_to_ico(icon_path, &ico_path).is_ok()
+    {
+        return ico_path;
     }
 
     // Fallback: use the executable file itself as the icon source
@@ -925,3 +979,16 @@ fn add_component_to_directory(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::rtf_safe_content;
+
+    #[test]
+    fn rtf_escapes_license_text() {
+        let rtf = rtf_safe_content("{a}\\b\nZa\u{17c}\u{f3}\u{142}\u{107} \u{1f600}");
+        assert!(rtf.starts_with("{\\rtf1"));
+        assert!(rtf.contains("\\{a\\}\\\\b\\par\n"));
+        assert!(rtf.contains("Za\\u380?\\u243?\\u322?\\u263? \\u-10179?\\u-8704?"));
+    }
+}
@@ -0,0 +1,447 @@
+//! Wraps Rust executables in OS-specific app bundles.
+//!
+//! This is the library behind the `cargo bundler` subcommand.  To bundle a
+//! package programmatically, load it with [`BundleTargetInfo::load`], turn it
+//! into [`Settings`] with [`Settings::builder`], build it with
+//! [`build_project_if_unbuilt`] and pass the settings to [`bundle()`].
+
+pub mod bundle;
+#[cfg(test)]
+mod test_utils;
+
+pub use crate::bundle::metadata::BundleSettings;
+pub use crate::bundle::target_info::BundleTargetInfo;
+pub use crate::bundle::{BuildArtifact, PackageType, Settings, SettingsBuilder};
+use anyhow::Context;
+pub use anyhow::Result;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process;
+use target_build_utils::TargetInfo;
+
+#[macro_export]
+macro_rules! version_0 {
+    () => {
+        concat!("v", clap::crate_version!())
+    };
+}
+
+#[macro_export]
+macro_rules! version_info {
+    () => {
+        concat!(clap::crate_name!(), " ", $crate::version_0!())
+    };
+}
+
+fn about_info() -> String {
+    format!(
+        "{}\n{}\n{}",
+        version_info!(),
+        clap::crate_authors!(", "),
+        "Bundle Rust executables into OS bundles",
+    )
+}
+
+#[derive(clap::Parser, Clone)]
+#[command(version = version_0!(), author = clap::crate_authors!(", "), bin_name = "cargo bundler", about = about_info())]
+pub struct Cli {
+    /// Bundle the specified binary
+    #[arg(short, long, value_name = "NAME")]
+    pub bin: Option<String>,
+
+    /// Bundle the specified example, given by its name or the path of its source file
+    #[arg(short, long, value_name = "NAME", conflicts_with = "bin")]
+    pub example: Option<String>,
+
+    /// Which bundle format to produce (can be repeated)
+    #[arg(short, long, value_name = "FORMAT", value_parser = PossibleValuesParser::new(PackageType::all()).map(|s| PackageType::try_from(s).unwrap()))]
+    pub format: Vec<PackageType>,
+
+    /// Build a bundle from a target built in release mode
+    #[arg(short, long)]
+    pub release: bool,
+
+    /// Build a bundle from a target build using the given profile
+    #[arg(long, value_name = "NAME", conflicts_with = "release")]
+    pub profile: Option<String>,
+
+    /// Build a bundle for the target triple
+    #[arg(short, long, value_name = "TRIPLE")]
+    pub target: Option<String>,
+
+    /// Label the bundle with this architecture without changing the build. A `--target` triple takes precedence
+    #[arg(long, value_name = "ARCH", value_parser = PossibleValuesParser::new(bundle::ARCHES.iter().copied()))]
+    pub arch: Option<String>,
+
+    /// Bundle a universal macOS binary, built for both `x86_64-apple-darwin` and `aarch64-apple-darwin` and merged with `lipo`
+    #[arg(long, conflicts_with = "target")]
+    pub universal: bool,
+
+    /// Submit macOS bundles to Apple for notarization after signing, and staple the ticket to them. Needs `osx_signing_identity` and `osx_notarize_keychain_profile`
+    #[arg(long)]
+    pub notarize: bool,
+
+    /// Choose the default bundle formats as if running on this OS, without changing the build target
+    #[arg(long, value_name = "OS", value_parser = PossibleValuesParser::new(["linux", "macos", "ios", "windows"]))]
+    pub host_os: Option<String>,
+
+    /// Set crate features for the bundle. Eg: `--features "f1 f2"`
+    #[arg(long, value_name = "FEATURES")]
+    pub features: Option<String>,
+
+    /// Build a bundle with all crate features.
+    #[arg(long)]
+    pub all_features: bool,
+
+    /// Build a bundle without the default crate features.
+    #[arg(long)]
+    pub no_default_features: bool,
+
+    /// Bundle every workspace member that has a `[package.metadata.bundle]` section and a bin target
+    #[arg(long, conflicts_with = "package")]
+    pub workspace: bool,
+
+    /// The name of the package to bundle. Can be repeated, and `all` bundles every member with bundle metadata. If not specified, the package in or above the directory is used.
+    #[arg(short, long, value_name = "SPEC")]
+    pub package: Vec<String>,
+
+    /// Directory where the built binary is placed, as with `cargo build --target-dir`
+    #[arg(long, value_name = "DIR")]
+    pub target_dir: Option<PathBuf>,
+
+    /// Load additional bundle settings from a TOML or JSON file, overriding `[package.metadata.bundle]`
+    #[arg(long, value_name = "PATH")]
+    pub metadata_file: Option<PathBuf>,
+
+    /// Use verbose output, logging every copied file and spawned command (-vv also makes cargo verbose)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Number of parallel jobs to run `cargo build` with
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<u32>,
+
+    /// Bundle this already-built executable instead of the cargo build output. Implies `--no-build`
+    #[arg(long, value_name = "FILE")]
+    pub binary_path: Option<PathBuf>,
+
+    /// Bundle the existing build output without running `cargo build` (or the before-build command) first
+    #[arg(long)]
+    pub no_build: bool,
+
+    /// Bundle even if the working tree has uncommitted changes and `require_clean_tree` is set
+    #[arg(long)]
+    pub allow_dirty: bool,
+
+    /// Overwrite existing bundle outputs instead of failing
+    #[arg(long)]
+    pub force: bool,
+
+    /// Remove the previous bundle outputs of each format before bundling (cargo build outputs are kept)
+    #[arg(long)]
+    pub clean: bool,
+
+    /// Treat warnings as errors: fail at once for `icon` or `resources` entries matching no files, and at the end if any other warning was printed (notes, such as for unsigned packages, don't count)
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Opt the AppImage out of desktop integration prompts, overriding `appimage_no_integration`
+    #[arg(long)]
+    pub no_appimage_integration: bool,
+
+    /// Don't look for `icon.png`, `assets/icon.icns`, `resources/app.ico` and the like when no `icon` is configured
+    #[arg(long)]
+    pub no_default_icons: bool,
+
+    /// GPG key ID to sign deb and rpm packages with, overriding `deb_sign_key` and `rpm_sign_key`
+    #[arg(long, value_name = "KEYID")]
+    pub sign_key: Option<String>,
+
+    /// Write a `<bundle>.sha256` checksum file next to each produced bundle
+    #[arg(long)]
+    pub checksums: bool,
+
+    /// Write a sorted listing of the bundled files and their sizes to this path, for diffing across releases
+    #[arg(long, value_name = "PATH")]
+    pub manifest_out: Option<PathBuf>,
+
+    /// Write the binary's debug symbols to a `.dSYM` bundle (macOS) or `.debug` file (Linux) next to the bundle
+    #[arg(long)]
+    pub include_debug_symbols: bool,
+
+    /// Show progress while copying resources (shown automatically for more than 50 files on a terminal)
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Override a cargo configuration value for the build, passed on as `cargo build --config`, e.g. `--cargo-config profile.release.lto=true`. Can be repeated
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub cargo_config: Vec<String>,
+
+    /// Override a bundle setting, e.g. `--config identifier=com.example.app`. Can be repeated
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub config: Vec<String>,
+
+    /// Shell command to run in the package directory before `cargo build`, overriding `before_build_command`
+    #[arg(long, value_name = "COMMAND")]
+    pub before_build_command: Option<String>,
+
+    /// Set an environment variable for `cargo build` and build scripts, overriding the same variable in `build_env`. Can be repeated
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub build_env: Vec<String>,
+
+    /// Show the produced bundles in the file manager (Finder, Explorer or via `xdg-open`) once bundling succeeded
+    #[arg(long)]
+    pub open: bool,
+
+    /// Keep running, and build and bundle again whenever the package's sources, manifest, icons or resources change. Implies `--force`
+    #[arg(long, conflicts_with = "print_config")]
+    pub watch: bool,
+
+    /// Print the effective bundle settings of each package and format as TOML, after merging, then exit without building
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Print every accepted `category` value with its macOS and Linux equivalents, then exit
+    #[arg(long)]
+    pub list_categories: bool,
+
+    /// Path to the project to bundle. Defaults to the current directory.
+    #[arg(value_name = "DIR", default_value = ".")]
+    pub dir: PathBuf,
+
+    /// Extra arguments passed verbatim to `cargo build`, e.g. `-- --locked`
+    #[arg(last = true, value_name = "BUILD_ARGS")]
+    pub build_args: Vec<String>,
+}
+
+impl Cli {
+    pub fn get_target(&self) -> Option<(String, Option<TargetInfo>)> {
+        self.target
+            .as_ref()
+            .map(|triple| bundle::target_info::normalize_target_triple(triple))
+            .map(|triple| {
+                let info = TargetInfo::from_str(&triple).ok();
+                (triple, info)
+            })
+    }
+
+    /// Returns the bundle formats to produce: the ones given with `--format`,
+    /// or else the defaults for the target OS.  `--host-os` stands in for the
+    /// OS this runs on when there is no target, or its OS isn't known, and
+    /// `--universal` implies macOS.
+    pub fn package_types(&self) -> Vec<PackageType> {
+        if !self.format.is_empty() {
+            let mut formats = Vec::new();
+            for &format in &self.format {
+                if !formats.contains(&format) {
+                    formats.push(format);
+                }
+            }
+            return formats;
+        }
+        if self.universal {
+            return vec![PackageType::OsxBundle];
+        }
+        let host_os = self.host_os.as_deref().unwrap_or(env::consts::OS);
+        let os = match self.get_target() {
+            Some((_, Some(info))) => info.target_os().to_string(),
+            _ => host_os.to_string(),
+        };
+        match os.as_str() {
+            "macos" => vec![PackageType::OsxBundle],
+            "ios" => vec![PackageType::IosBundle],
+            "linux" => vec![PackageType::Deb, PackageType::AppImage],
+            "windows" => vec![PackageType::WindowsMsi],
+            _os => vec![],
+        }
+    }
+}
+
+/// Bundles the already-built binary described by `settings` in the
+/// `package_type` format, returning the paths of the produced bundles.  A
+/// checksum file is written next to each bundle if requested.
+pub fn bundle(settings: &Settings, package_type: PackageType) -> crate::Result<Vec<PathBuf>> {
+    settings.validate_binary_exists()?;
+    settings.check_patterns_match()?;
+    let output_paths = package_type.bundle_project(settings)?;
+    settings.check_bundle_size(&output_paths)?;
+    if settings.emit_checksums() {
+        for path in &output_paths {
+            if path.is_dir() {
+                bundle::print_warning(&format!(
+                    "Not writing a checksum for {path:?}, which is a directory"
+                ))?;
+            } else {
+                bundle::write_checksum(path)?;
+            }
+        }
+    }
+    Ok(output_paths)
+}
+
+/// Writes the files that went into the `bundles`, each paired with its
+/// format, one `<path>\t<size>` line per file, to `path`.  This is called
+/// once after every format of a package was bundled, rather than by `bundle`,
+/// so that the manifest covers all of them.
+pub fn write_manifest(path: &Path, bundles: &[(PackageType, PathBuf)]) -> crate::Result<()> {
+    let mut manifest = String::new();
+    for (file, size) in bundle::manifest_entries(bundles)? {
+        manifest.push_str(&format!("{file}\t{size}\n"));
+    }
+    std::fs::write(path, manifest).with_context(|| format!("Failed to write manifest {path:?}"))?;
+    bundle::log_verbose("Manifest", &path.display().to_string())
+}
+
+/// Fails if `require_clean_tree` is set and the git working tree containing
+/// the package has uncommitted changes.  The check is skipped, with a warning,
+/// if git isn't available or the package isn't in a git repository.
+pub fn check_clean_tree(settings: &Settings) -> crate::Result<()> {
+    if !settings.require_clean_tree() {
+        return Ok(());
+    }
+    let mut git = process::Command::new("git");
+    git.args(["status", "--porcelain"]);
+    if let Some(package_dir) = settings.target.package.manifest_path.parent() {
+        git.current_dir(package_dir);
+    }
+    bundle::log_verbose("Running", &format!("{git:?}"))?;
+    let output = match git.output() {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            return bundle::print_warning(
+                "Could not get the git status of the package, skipping the clean tree check",
+            );
+        }
+    };
+    let changes = String::from_utf8_lossy(&output.stdout);
+    if !changes.trim().is_empty() {
+        anyhow::bail!(
+            "The working tree has uncommitted changes, refusing to bundle \
+             (use --allow-dirty to bundle anyway):\n{}",
+            changes.trim_end()
+        );
+    }
+    Ok(())
+}
+
+/// Runs the `before_build_command` through the shell, in the directory of the
+/// package being bundled.
+fn run_before_build_command(settings: &Settings, command: &str) -> crate::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = process::Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = process::Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    if let Some(package_dir) = settings.target.package.manifest_path.parent() {
+        shell.current_dir(package_dir);
+    }
+    bundle::log_verbose("Running", &format!("{shell:?}"))?;
+    let status = shell.status()?;
+    if !status.success() {
+        anyhow::bail!("Before-build command `{command}` was unsuccessful: {status}");
+    }
+    Ok(())
+}
+
+/// Runs `cargo build` to make sure the binary file is up-to-date.  For a
+/// universal macOS binary, it is built for each architecture and the builds
+/// are merged with `lipo`.
+pub fn build_project_if_unbuilt(settings: &Settings) -> crate::Result<()> {
+    if settings.no_build() {
+        return Ok(());
+    }
+
+    if let Some(command) = settings.before_build_command() {
+        run_before_build_command(settings, command)?;
+    }
+
+    if settings.universal() {
+        settings.check_universal_targets()?;
+        for triple in bundle::UNIVERSAL_TARGETS {
+            run_cargo_build(settings, Some(triple))?;
+        }
+        settings.merge_universal_binaries()
+    } else {
+        run_cargo_build(settings, settings.target_triple())
+    }
+}
+
+/// Runs `cargo build` for `triple`, or for the host if `None`.
+fn run_cargo_build(settings: &Settings, triple: Option<&str>) -> crate::Result<()> {
+    let mut cargo =
+        process::Command::new(env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")));
+    cargo.arg("build");
+    cargo.arg("--manifest-path");
+    cargo.arg(&settings.target.package.manifest_path);
+    if let Some(triple) = triple {
+        cargo.arg(format!("--target={triple}"));
+    }
+    if let Some(target_dir) = settings.target.target_dir.as_ref() {
+        cargo.arg("--target-dir");
+        cargo.arg(target_dir);
+    }
+    if let Some(features) = settings.features() {
+        cargo.arg(format!("--features={features}"));
+    }
+    match settings.build_artifact() {
+        BuildArtifact::Main => {}
+        BuildArtifact::Bin(name) => {
+            cargo.arg(format!("--bin={name}"));
+        }
+        BuildArtifact::Example(name) => {
+            cargo.arg(format!("--example={name}"));
+        }
+    }
+    // Without `--bin` or `--example`, cargo builds every binary anyway.
+    if settings.target.package_type == PackageType::OsxBundle
+        && !matches!(settings.build_artifact(), BuildArtifact::Main)
+    {
+        for name in settings.osx_additional_binaries() {
+            cargo.arg(format!("--bin={name}"));
+        }
+    }
+    match settings.build_profile() {
+        "dev" => {}
+        "release" => {
+            cargo.arg("--release");
+        }
+        custom => {
+            cargo.arg("--profile");
+            cargo.arg(custom);
+        }
+    }
+    if settings.all_features() {
+        cargo.arg("--all-features");
+    }
+    if settings.no_default_features() {
+        cargo.arg("--no-default-features");
+    }
+    if let Some(jobs) = settings.jobs() {
+        cargo.arg(format!("--jobs={jobs}"));
+    }
+    if bundle::verbosity() > 1 {
+        cargo.arg("--verbose");
+    }
+    for config in settings.cargo_config() {
+        cargo.arg("--config");
+        cargo.arg(config);
+    }
+    cargo.args(settings.build_args());
+    cargo.envs(settings.build_env());
+    bundle::log_verbose("Running", &format!("{cargo:?}"))?;
+    let status = cargo.status()?;
+    if !status.success() {
+        anyhow::bail!(
+            "Result of `cargo build` operation was unsuccessful: {}",
+            status
+        );
+    }
+    Ok(())
+}
@@ -1,175 +1,358 @@
-mod bundle;
-
-use crate::bundle::target_info::BundleTargetInfo;
-use crate::bundle::{BuildArtifact, PackageType, Settings};
-use anyhow::Result;
-use clap::builder::{PossibleValuesParser, TypedValueParser};
-use std::env;
-use std::ffi::OsString;
+use cargo_bundler::bundle::category::AppCategory;
+use cargo_bundler::{
+    BundleTargetInfo, Cli, PackageType, Result, Settings, build_project_if_unbuilt, bundle,
+    check_clean_tree, write_manifest,
+};
 use std::path::PathBuf;
-use std::process;
-use target_build_utils::TargetInfo;
 
-#[macro_export]
-macro_rules! version_0 {
-    () => {
-        concat!("v", clap::crate_version!())
-    };
+/// When invoked as `cargo bundler` (or the older `cargo bundle`), cargo runs
+/// `cargo-bundler bundler ...`, so the subcommand name has to be dropped
+/// before the arguments are handed to clap.
+fn strip_cargo_subcommand(mut args: Vec<String>) -> Vec<String> {
+    if args.len() > 1 && (args[1] == "bundle" || args[1] == "bundler") {
+        args.remove(1);
+    }
+    args
 }
 
-#[macro_export]
-macro_rules! version_info {
-    () => {
-        concat!(clap::crate_name!(), " ", $crate::version_0!())
-    };
-}
+fn run() -> Result<()> {
+    let started = std::time::Instant::now();
+    let args = strip_cargo_subcommand(std::env::args().collect());
+    let mut cli = <Cli as clap::Parser>::parse_from(args); // <Cli as clap::Parser>::parse();
+    if cli.list_categories {
+        print!("{}", category_listing());
+        return Ok(());
+    }
+    cli.dir = std::path::absolute(&cli.dir)?;
+    bundle::set_verbosity(cli.verbose);
+    bundle::set_strict(cli.strict);
 
-fn about_info() -> String {
-    format!(
-        "{}\n{}\n{}",
-        version_info!(),
-        clap::crate_authors!(", "),
-        "Bundle Rust executables into OS bundles",
-    )
+    if cli.watch {
+        return watch(&cli);
+    }
+    let bundles = bundle_once(&cli)?;
+    if cli.print_config {
+        return Ok(());
+    }
+    print_finished(&bundles, started)?;
+    if cli.open {
+        reveal_bundles(&bundles)?;
+    }
+    Ok(())
 }
 
-#[derive(clap::Parser, Clone)]
-#[command(version = version_0!(), author = clap::crate_authors!(", "), bin_name = "cargo bundler", about = about_info())]
-pub struct Cli {
-    /// Bundle the specified binary
-    #[arg(short, long, value_name = "NAME")]
-    pub bin: Option<String>,
-
-    /// Bundle the specified example
-    #[arg(short, long, value_name = "NAME", conflicts_with = "bin")]
-    pub example: Option<String>,
+/// The settings of each format to bundle, grouped by package.
+type PackageFormats = Vec<Vec<(PackageType, Settings)>>;
 
-    /// Which bundle format to produce
-    #[arg(short, long, value_name = "FORMAT", value_parser = PossibleValuesParser::new(PackageType::all()).map(|s| PackageType::try_from(s).unwrap()))]
-    pub format: Option<PackageType>,
+/// The bundles that were made, each with its format.
+type Bundles = Vec<(PackageType, PathBuf)>;
 
-    /// Build a bundle from a target built in release mode
-    #[arg(short, long)]
-    pub release: bool,
-
-    /// Build a bundle from a target build using the given profile
-    #[arg(long, value_name = "NAME", conflicts_with = "release")]
-    pub profile: Option<String>,
-
-    /// Build a bundle for the target triple
-    #[arg(short, long, value_name = "TRIPLE")]
-    pub target: Option<String>,
-
-    /// Set crate features for the bundle. Eg: `--features "f1 f2"`
-    #[arg(long, value_name = "FEATURES")]
-    pub features: Option<String>,
-
-    /// Build a bundle with all crate features.
-    #[arg(long)]
-    pub all_features: bool,
-
-    /// Build a bundle without the default crate features.
-    #[arg(long)]
-    pub no_default_features: bool,
-
-    /// The name of the package to bundle. If not specified, the root package will be used.
-    #[arg(short, long, value_name = "SPEC")]
-    pub package: Option<String>,
-
-    pub dir: PathBuf,
+/// Builds and bundles every requested package and format once, returning the
+/// bundles.  With `--print-config` the settings are printed instead, and no
+/// bundles are made.
+fn bundle_once(cli: &Cli) -> Result<Bundles> {
+    let warnings_before = bundle::strict_warnings();
+    let packages = load_packages(cli)?;
+    bundle_packages(cli, &packages, warnings_before)
 }
 
-impl Cli {
-    pub fn get_target(&self) -> Option<(String, Option<TargetInfo>)> {
-        self.target
-            .as_ref()
-            .map(|triple| (triple.to_string(), TargetInfo::from_str(triple).ok()))
+/// Loads the settings of every requested package and format.
+fn load_packages(cli: &Cli) -> Result<PackageFormats> {
+    let package_types = cli.package_types();
+    let packages: Vec<Option<String>> = if cli.workspace || cli.package.iter().any(|p| p == "all") {
+        let packages = bundle::target_info::bundleable_packages(&cli.dir)?;
+        if packages.is_empty() {
+            anyhow::bail!(
+                "No package in the workspace has a [package.metadata.bundle] section and a bin target"
+            );
+        }
+        packages.into_iter().map(Some).collect()
+    } else if cli.package.is_empty() {
+        vec![None]
+    } else {
+        cli.package.iter().cloned().map(Some).collect()
+    };
+    if cli.manifest_out.is_some() && packages.len() > 1 {
+        anyhow::bail!("--manifest-out can only be used when bundling a single package");
     }
+    packages
+        .iter()
+        .map(|package| {
+            package_types
+                .iter()
+                .map(|&package_type| {
+                    let target_build_info: BundleTargetInfo =
+                        (cli, package_type, package.as_deref()).try_into()?;
+                    Ok((package_type, Settings::new(&target_build_info, cli)?))
+                })
+                .collect()
+        })
+        .collect()
 }
 
-/// Runs `cargo build` to make sure the binary file is up-to-date.
-fn build_project_if_unbuilt(settings: &Settings) -> crate::Result<()> {
-    if std::env::var("CARGO_BUNDLE_SKIP_BUILD").is_ok() {
-        return Ok(());
+/// Builds and bundles the formats of each package, or prints their settings
+/// with `--print-config`.  With `--strict`, fails if any warnings were
+/// printed since there were `warnings_before`, which is counted before the
+/// packages are loaded so that warnings about their settings count too.
+fn bundle_packages(
+    cli: &Cli,
+    packages: &PackageFormats,
+    warnings_before: usize,
+) -> Result<Bundles> {
+    let mut bundles = Vec::new();
+    if !cli.print_config {
+        clean_bundle_dirs(packages)?;
     }
-
-    let mut cargo =
-        process::Command::new(env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")));
-    cargo.arg("build");
-    if let Some(triple) = settings.target_triple() {
-        cargo.arg(format!("--target={triple}"));
+    for formats in packages {
+        if cli.print_config {
+            for (_, settings) in formats {
+                print!("{}", effective_config(settings)?);
+            }
+            continue;
+        }
+        // The builds run one after another, as the formats usually share the
+        // same binary and only the first of them has to build it.
+        for (_, settings) in formats {
+            check_clean_tree(settings)?;
+            build_project_if_unbuilt(settings)?;
+            settings.validate_binary_exists()?;
+        }
+        let package_bundles = bundle_formats(formats)?;
+        if let Some((_, settings)) = formats.first()
+            && let Some(manifest_path) = settings.manifest_out()
+        {
+            write_manifest(manifest_path, &package_bundles)?;
+        }
+        bundles.extend(package_bundles);
     }
-    if let Some(features) = settings.features() {
-        cargo.arg(format!("--features={features}"));
+    let warnings = bundle::strict_warnings() - warnings_before;
+    if warnings > 0 {
+        anyhow::bail!(
+            "{warnings} warning{} printed, failing because of --strict",
+            if warnings == 1 { " was" } else { "s were" }
+        );
     }
-    match settings.build_artifact() {
-        BuildArtifact::Main => {}
-        BuildArtifact::Bin(name) => {
-            cargo.arg(format!("--bin={name}"));
-        }
-        BuildArtifact::Example(name) => {
-            cargo.arg(format!("--example={name}"));
+    Ok(bundles)
+}
+
+/// Prints the bundles and a summary of them, with the time taken since
+/// `started`.
+fn print_finished(bundles: &Bundles, started: std::time::Instant) -> Result<()> {
+    let bundles: Vec<(&str, PathBuf)> = bundles
+        .iter()
+        .map(|(package_type, path)| (package_type.short_name(), path.clone()))
+        .collect();
+    bundle::print_finished(&bundles, started.elapsed())
+}
+
+/// Removes the bundle directory of each format to be cleaned with `--clean`.
+/// The packages of a workspace share these directories, so each is removed
+/// once, before any package is bundled, rather than by each package.
+fn clean_bundle_dirs(packages: &PackageFormats) -> Result<()> {
+    let mut cleaned = Vec::new();
+    for (package_type, settings) in packages.iter().flatten() {
+        let dir = settings.bundle_dir(*package_type);
+        if settings.clean() && !cleaned.contains(&dir) {
+            bundle::remove_bundle_dir(&dir)?;
+            cleaned.push(dir);
         }
     }
-    match settings.build_profile() {
-        "dev" => {}
-        "release" => {
-            cargo.arg("--release");
+    Ok(())
+}
+
+/// Bundles like `bundle_once`, then again each time the sources, manifest,
+/// icons or resources of a bundled package change.  Failures are reported
+/// without stopping, so this only returns if printing fails.
+fn watch(cli: &Cli) -> Result<()> {
+    let mut reveal = cli.open;
+    loop {
+        let started = std::time::Instant::now();
+        let warnings_before = bundle::strict_warnings();
+        let packages = load_packages(cli);
+        let (watched, excluded): (Vec<PathBuf>, Vec<PathBuf>) = match &packages {
+            Ok(packages) => {
+                let settings = packages.iter().flatten().map(|(_, settings)| settings);
+                let watched = settings.clone().flat_map(Settings::watch_paths).collect();
+                let excluded = settings
+                    .map(|settings| settings.target.target_root())
+                    .collect();
+                (watched, excluded)
+            }
+            // Without settings, watch the whole project for a fix.
+            Err(_) => (vec![cli.dir.clone()], vec![cli.dir.join("target")]),
+        };
+        // Taken before bundling, so that changes saved during the build
+        // trigger another one.
+        let before = bundle::Snapshot::take(&watched, &excluded);
+        let result = packages.and_then(|packages| bundle_packages(cli, &packages, warnings_before));
+        let time = chrono::Local::now().format("%H:%M:%S");
+        match result {
+            Ok(bundles) => {
+                print_finished(&bundles, started)?;
+                bundle::print_watch_status(&format!("[{time}] Waiting for changes"))?;
+                if reveal {
+                    reveal_bundles(&bundles)?;
+                    reveal = false;
+                }
+            }
+            Err(error) => {
+                bundle::print_error(&error)?;
+                bundle::print_watch_status(&format!(
+                    "[{time}] Bundling failed, waiting for changes"
+                ))?;
+            }
         }
-        custom => {
-            cargo.arg("--profile");
-            cargo.arg(custom);
+        let changed = bundle::wait_for_changes(&before, &watched, &excluded);
+        let more = match changed.len() {
+            0 | 1 => String::new(),
+            count => format!(" and {} more", count - 1),
+        };
+        if let Some(path) = changed.first() {
+            bundle::print_watch_status(&format!("{}{more} changed, rebundling", path.display()))?;
         }
     }
-    if settings.all_features() {
-        cargo.arg("--all-features");
-    }
-    if settings.no_default_features() {
-        cargo.arg("--no-default-features");
-    }
-    let status = cargo.status()?;
-    if !status.success() {
-        anyhow::bail!(
-            "Result of `cargo build` operation was unsuccessful: {}",
-            status
-        );
+}
+
+/// Shows the bundles in the file manager, once per output directory.  As
+/// the bundles were already made, failing to do so is only a warning.
+fn reveal_bundles(bundles: &Bundles) -> Result<()> {
+    let mut revealed_dirs = Vec::new();
+    for (_, path) in bundles {
+        let dir = path.parent();
+        if revealed_dirs.contains(&dir) {
+            continue;
+        }
+        revealed_dirs.push(dir);
+        if let Err(error) = bundle::reveal_in_file_manager(path) {
+            bundle::print_warning(&format!("Failed to show {path:?}: {error:#}"))?;
+        }
     }
     Ok(())
 }
 
-fn run() -> crate::Result<()> {
-    let mut args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 && args[1] == "bundle" {
-        args.remove(1);
+/// Returns the effective bundle settings as TOML, headed by a comment naming
+/// the package and format.  The `targets`, `bin` and `example` sections have
+/// already been merged in, so they are left out.
+fn effective_config(settings: &Settings) -> Result<String> {
+    let mut bundle_settings = settings.bundle_settings().clone();
+    bundle_settings.targets.clear();
+    bundle_settings.bin.clear();
+    bundle_settings.example.clear();
+    Ok(format!(
+        "# {} ({})\n{}\n",
+        settings.target.package.name,
+        settings.target.package_type,
+        toml::to_string_pretty(&bundle_settings)?
+    ))
+}
+
+/// Returns a table of every `AppCategory`, one per line, with its canonical
+/// name, macOS `LSApplicationCategoryType` and desktop file `Categories=`.
+fn category_listing() -> String {
+    let width = AppCategory::ALL
+        .iter()
+        .map(|category| category.as_str().len())
+        .max()
+        .unwrap_or(0);
+    let uti_width = AppCategory::ALL
+        .iter()
+        .map(|category| category.osx_application_category_type().len())
+        .max()
+        .unwrap_or(0);
+    let mut listing = format!(
+        "{:width$}  {:uti_width$}  {}\n",
+        "Category", "macOS", "Linux"
+    );
+    for category in AppCategory::ALL {
+        listing.push_str(&format!(
+            "{:width$}  {:uti_width$}  {};\n",
+            category.as_str(),
+            category.osx_application_category_type(),
+            category.freedesktop_categories().join(";")
+        ));
     }
-    let mut cli = <Cli as clap::Parser>::parse_from(args); // <Cli as clap::Parser>::parse();
-    cli.dir = env::current_dir()?;
-
-    let package_types = match cli.format {
-        Some(s) => vec![s],
-        None => match cli
-            .get_target()
-            .as_ref()
-            .map(|(_, t)| t.as_ref().map_or(std::env::consts::OS, |t| t.target_os()))
-            .unwrap_or(std::env::consts::OS)
-        {
-            "macos" => vec![PackageType::OsxBundle],
-            "ios" => vec![PackageType::IosBundle],
-            "linux" => vec![PackageType::Deb, PackageType::AppImage], // TODO: Do Rpm too, once it's implemented.
-            "windows" => vec![PackageType::WindowsMsi],
-            _os => vec![],
-        },
+    listing
+}
+
+/// Groups the indices of `package_types` by the thread to bundle them on.
+/// Most formats don't depend on each other and get a thread of their own, but
+/// the `ipa` builds its `.app` where the `ios` format does, so the two run on
+/// the same thread, `ios` first.
+fn format_groups(package_types: &[PackageType]) -> Vec<Vec<usize>> {
+    let thread_of = |package_type: PackageType| match package_type {
+        PackageType::IosIpa => PackageType::IosBundle,
+        other => other,
     };
-    for package_type in package_types {
-        let target_build_info: BundleTargetInfo = (&cli, package_type).try_into().expect("msg");
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (index, &package_type) in package_types.iter().enumerate() {
+        let thread = thread_of(package_type);
+        match groups
+            .iter_mut()
+            .find(|group| thread_of(package_types[group[0]]) == thread)
         {
-            let settings = Settings::new(&target_build_info, &cli)?;
-            build_project_if_unbuilt(&settings)?;
-            let output_paths = package_type.bundle_project(&settings)?;
-            bundle::print_finished(&output_paths)?;
+            Some(group) => group.push(index),
+            None => groups.push(vec![index]),
         }
     }
-    Ok(())
+    for group in &mut groups {
+        group.sort_by_key(|&index| package_types[index] == PackageType::IosIpa);
+    }
+    groups
+}
+
+/// Bundles the formats in parallel, on the threads `format_groups` picks.
+/// Returns the bundles that were made, or the errors of all the formats that
+/// failed.
+fn bundle_formats(formats: &[(PackageType, Settings)]) -> Result<Bundles> {
+    let package_types: Vec<PackageType> = formats.iter().map(|(format, _)| *format).collect();
+    let mut results: Vec<Option<Result<Vec<PathBuf>>>> = formats.iter().map(|_| None).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = format_groups(&package_types)
+            .into_iter()
+            .map(|group| {
+                scope.spawn(move || {
+                    group
+                        .into_iter()
+                        .map(|index| {
+                            let (package_type, settings) = &formats[index];
+                            (index, cargo_bundler::bundle(settings, *package_type))
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            let group_results = handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (index, result) in group_results {
+                results[index] = Some(result);
+            }
+        }
+    });
+    let results = results.into_iter().map(|result| result.unwrap());
+    let mut bundles = Vec::new();
+    let mut errors = Vec::new();
+    for ((package_type, _), result) in formats.iter().zip(results) {
+        match result {
+            Ok(output_paths) => {
+                bundles.extend(output_paths.into_iter().map(|path| (*package_type, path)))
+            }
+            Err(error) => errors.push((package_type.short_name(), error)),
+        }
+    }
+    match errors.len() {
+        0 => Ok(bundles),
+        1 => Err(errors.remove(0).1),
+        count => anyhow::bail!(
+            "Failed to bundle {count} formats:\n{}",
+            errors
+                .iter()
+                .map(|(format, error)| format!("  - {format}: {error:#}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
 }
 
 fn main() {
@@ -178,3 +361,274 @@ fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+#[path = "test_utils.rs"]
+mod test_utils;
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Cli, bundle_formats, bundle_once, category_listing, clean_bundle_dirs, effective_config,
+        format_groups, strip_cargo_subcommand,
+    };
+    use crate::test_utils::{fixture_package, write_named_fixture_package};
+    use cargo_bundler::{BundleTargetInfo, PackageType, Settings, bundle};
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn strips_cargo_subcommand() {
+        assert_eq!(
+            strip_cargo_subcommand(args(&["cargo-bundler", "bundler", "--release", "."])),
+            args(&["cargo-bundler", "--release", "."])
+        );
+        assert_eq!(
+            strip_cargo_subcommand(args(&["cargo-bundle", "bundle", "--release", "."])),
+            args(&["cargo-bundle", "--release", "."])
+        );
+        // Invoked directly, without going through cargo.
+        assert_eq!(
+            strip_cargo_subcommand(args(&["cargo-bundler", "--release", "."])),
+            args(&["cargo-bundler", "--release", "."])
+        );
+    }
+
+    #[test]
+    fn repeated_package_flag() {
+        let cli =
+            <Cli as clap::Parser>::parse_from(["cargo-bundler", "-p", "app-a", "-p", "app-b"]);
+        assert_eq!(cli.package, args(&["app-a", "app-b"]));
+    }
+
+    #[test]
+    fn host_os_picks_default_formats() {
+        let cli = <Cli as clap::Parser>::parse_from(["cargo-bundler", "--host-os", "linux"]);
+        assert_eq!(
+            cli.package_types(),
+            vec![PackageType::Deb, PackageType::AppImage]
+        );
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--host-os",
+            "windows",
+            "--target",
+            "x86_64-apple-darwin",
+        ]);
+        assert_eq!(cli.package_types(), vec![PackageType::OsxBundle]);
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--host-os",
+            "macos",
+            "--format",
+            "rpm",
+        ]);
+        assert_eq!(cli.package_types(), vec![PackageType::Rpm]);
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "-f",
+            "deb",
+            "--format",
+            "appimage",
+            "-f",
+            "deb",
+        ]);
+        assert_eq!(
+            cli.package_types(),
+            vec![PackageType::Deb, PackageType::AppImage]
+        );
+        assert!(
+            <Cli as clap::Parser>::try_parse_from(["cargo-bundler", "--host-os", "plan9"]).is_err()
+        );
+    }
+
+    #[test]
+    fn categories_are_listed() {
+        let listing = category_listing();
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines.len(), 41);
+        assert!(lines[0].starts_with("Category "), "{}", lines[0]);
+        let puzzle = lines
+            .iter()
+            .find(|line| line.starts_with("Puzzle Game "))
+            .unwrap();
+        assert!(
+            puzzle.contains(" public.app-category.puzzle-games "),
+            "{puzzle}"
+        );
+        assert!(puzzle.ends_with(" Game;LogicGame;"), "{puzzle}");
+    }
+
+    #[test]
+    fn build_args_after_double_dash() {
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--release",
+            "apps/viewer",
+            "--",
+            "--locked",
+            "-Zbuild-std",
+        ]);
+        assert_eq!(cli.dir, PathBuf::from("apps/viewer"));
+        assert_eq!(cli.build_args, args(&["--locked", "-Zbuild-std"]));
+
+        let cli = <Cli as clap::Parser>::parse_from(["cargo-bundler", "--", "--locked"]);
+        assert_eq!(cli.dir, PathBuf::from("."));
+        assert_eq!(cli.build_args, args(&["--locked"]));
+    }
+
+    #[test]
+    fn effective_config_is_merged() {
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nname = \"Foo\"\ncategory = \"Utility\"\n\
+             \n\
+             [package.metadata.bundle.targets.deb]\nname = \"Foo for Debian\"\n",
+        );
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--print-config",
+            "--config",
+            "copyright=Copyright (c) Foo",
+            tmp.path().to_str().unwrap(),
+        ]);
+        let info = BundleTargetInfo::try_from((&cli, PackageType::Deb, None)).unwrap();
+        let config = effective_config(&Settings::new(&info, &cli).unwrap()).unwrap();
+        assert!(config.starts_with("# foo (deb)\n"), "{config}");
+        assert!(config.contains("name = \"Foo for Debian\"\n"), "{config}");
+        assert!(config.contains("category = \"Utility\"\n"), "{config}");
+        assert!(
+            config.contains("copyright = \"Copyright (c) Foo\"\n"),
+            "{config}"
+        );
+        assert!(!config.contains("targets"), "{config}");
+    }
+
+    #[test]
+    fn workspace_bundle_dirs_are_cleaned_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(
+            tmp.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"app-a\", \"app-b\"]\n",
+        )
+        .unwrap();
+        for member in ["app-a", "app-b"] {
+            write_named_fixture_package(&tmp.path().join(member), member, "");
+        }
+        let packages: Vec<Vec<_>> = ["app-a", "app-b"]
+            .into_iter()
+            .map(|member| {
+                let info =
+                    BundleTargetInfo::load(tmp.path(), PackageType::Deb, Some(member)).unwrap();
+                vec![(
+                    PackageType::Deb,
+                    Settings::builder(info).clean(true).build().unwrap(),
+                )]
+            })
+            .collect();
+        // The members share the bundle directory, so the bundles of one must
+        // not be removed when bundling the next.
+        let deb_dir = packages[0][0].1.bundle_dir(PackageType::Deb);
+        assert_eq!(packages[1][0].1.bundle_dir(PackageType::Deb), deb_dir);
+        std::fs::create_dir_all(&deb_dir).unwrap();
+        std::fs::write(deb_dir.join("stale.deb"), "old").unwrap();
+        clean_bundle_dirs(&packages).unwrap();
+        assert!(!deb_dir.exists());
+    }
+
+    #[test]
+    fn ipa_is_bundled_after_ios() {
+        use PackageType::{Deb, IosBundle, IosIpa, Rpm};
+        assert_eq!(format_groups(&[Deb, Rpm]), [vec![0], vec![1]]);
+        assert_eq!(
+            format_groups(&[IosIpa, Deb, IosBundle]),
+            [vec![2, 0], vec![1]]
+        );
+        assert_eq!(format_groups(&[IosIpa]), [vec![0]]);
+    }
+
+    /// Held by the tests that turn on strict mode, which is global, so that
+    /// they don't count each other's warnings.
+    static STRICT_MODE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn settings_warnings_fail_strict() {
+        let _strict = STRICT_MODE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let tmp = fixture_package("");
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--strict",
+            "--print-config",
+            "--format",
+            "deb",
+            tmp.path().to_str().unwrap(),
+        ]);
+        // The missing [package.metadata.bundle] section is only warned about
+        // while the settings are loaded.
+        bundle::set_strict(true);
+        let result = bundle_once(&cli);
+        bundle::set_strict(false);
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("failing because of --strict"), "{error}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn clean_deb_passes_strict() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _strict = STRICT_MODE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let tmp = fixture_package(
+            "\n\
+             [package.metadata.bundle]\nidentifier = \"com.example.foo\"\n",
+        );
+        let binary = tmp.path().join("prebuilt/foo");
+        std::fs::create_dir_all(binary.parent().unwrap()).unwrap();
+        std::fs::write(&binary, b"#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let cli = <Cli as clap::Parser>::parse_from([
+            "cargo-bundler",
+            "--strict",
+            "--format",
+            "deb",
+            "--binary-path",
+            binary.to_str().unwrap(),
+            tmp.path().to_str().unwrap(),
+        ]);
+        // The notes about the experimental formats and the unsigned package
+        // are printed on every run, so they don't count.
+        bundle::set_strict(true);
+        let result = bundle_once(&cli);
+        bundle::set_strict(false);
+        let bundles = result.unwrap();
+        assert_eq!(bundles.len(), 1);
+        assert_eq!(bundles[0].0, PackageType::Deb);
+    }
+
+    #[test]
+    fn failed_formats_are_all_reported() {
+        let tmp = fixture_package("");
+        // Nothing was built, so bundling fails for every format.
+        let formats: Vec<_> = [PackageType::Deb, PackageType::Rpm]
+            .into_iter()
+            .map(|package_type| {
+                let info = BundleTargetInfo::load(tmp.path(), package_type, None).unwrap();
+                (package_type, Settings::builder(info).build().unwrap())
+            })
+            .collect();
+        let error = bundle_formats(&formats).unwrap_err().to_string();
+        assert!(error.starts_with("Failed to bundle 2 formats:"), "{error}");
+        assert!(error.contains("  - deb: Binary"), "{error}");
+        assert!(error.contains("  - rpm: Binary"), "{error}");
+
+        let error = bundle_formats(&formats[..1]).unwrap_err().to_string();
+        assert!(error.starts_with("Binary"), "{error}");
+    }
+}
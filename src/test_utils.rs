@@ -0,0 +1,34 @@
+// Fixtures shared by the unit tests of the library and of the binary, which
+// includes this file as its own module.
+
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Creates a package named `foo`, with a `src/main.rs`, in a new temporary
+/// directory.  `extra_toml` is appended to its `Cargo.toml` right after the
+/// `[package]` fields, so it can add more of them as well as other tables.
+pub fn fixture_package(extra_toml: &str) -> TempDir {
+    let tmp = tempfile::tempdir().unwrap();
+    write_fixture_package(tmp.path(), extra_toml);
+    tmp
+}
+
+/// Writes the package that `fixture_package` creates to `dir`, replacing its
+/// `Cargo.toml` if there already is one.
+pub fn write_fixture_package(dir: &Path, extra_toml: &str) {
+    write_named_fixture_package(dir, "foo", extra_toml);
+}
+
+/// Like `write_fixture_package`, but names the package `name`, for tests that
+/// need several packages, such as the members of a workspace.
+pub fn write_named_fixture_package(dir: &Path, name: &str, extra_toml: &str) {
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n{extra_toml}"
+        ),
+    )
+    .unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+}